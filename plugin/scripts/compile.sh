@@ -1,6 +1,6 @@
 #!/bin/bash
 # UserPromptSubmit hook for wm
-# Surfaces relevant working memory context for the current intent
+# Surfaces working memory items the session has not seen yet
 #
 # AIDEV-NOTE: Uses additionalContext to inject relevant knowledge into Claude's context.
 # Never blocks - returns empty response on any failure.
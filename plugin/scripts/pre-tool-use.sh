@@ -0,0 +1,30 @@
+#!/bin/bash
+# PreToolUse hook for wm
+# Checks Bash commands and file edits against .wm/guardrails.toml rules
+#
+# AIDEV-NOTE: Outputs a permissionDecision (deny for block rules, ask for warn
+# rules) only when a rule matches. Never blocks on its own failures.
+
+# Skip if wm is disabled
+if [ "${WM_DISABLED:-}" = "1" ]; then
+    exit 0
+fi
+
+# Use CLAUDE_PROJECT_DIR if available, otherwise current directory
+PROJECT_DIR="${CLAUDE_PROJECT_DIR:-.}"
+
+# Skip if there are no machine-checkable guardrails
+if [ ! -f "$PROJECT_DIR/.wm/guardrails.toml" ]; then
+    exit 0
+fi
+
+# Skip if wm binary not available
+if ! command -v wm &> /dev/null; then
+    exit 0
+fi
+
+# Change to project directory for wm to find .wm/
+cd "$PROJECT_DIR" || exit 0
+
+# Pass the tool call payload straight through
+wm hook pre-tool-use 2>/dev/null || exit 0
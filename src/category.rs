@@ -0,0 +1,226 @@
+//! Knowledge categories
+//!
+//! Distill Pass 2 sorts insights into categories defined in config. Each one
+//! has a description for the LLM, an injection priority, an output file in
+//! .wm/distill/, and whether it is injected into sessions:
+//!
+//! ```toml
+//! [categories.decisions]
+//! description = "Architecture decisions and the reasons behind them"
+//! priority = 30
+//! filename = "decisions.md"
+//! ```
+//!
+//! The defaults are guardrails (hard rules) and metis (soft guidance). The
+//! categorization prompt, its parser, the files distill writes and the compiled
+//! working set are all driven by `all()`.
+//!
+//! AIDEV-NOTE: Insights the LLM puts in an unknown category land in the
+//! fallback category (metis if configured, see `fallback()`), so nothing
+//! extracted is silently dropped.
+
+use crate::state;
+use crate::types::{CategoriesConfig, CategoryConfig};
+use crate::workspace::Workspace;
+
+/// A configured knowledge category
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    /// Key in config (e.g. "guardrails")
+    pub name: String,
+
+    /// Heading of the output file (e.g. "Guardrails")
+    pub title: String,
+
+    /// What belongs here, for the LLM
+    pub description: String,
+
+    /// Injection order: lower comes first
+    pub priority: u32,
+
+    /// Output file in .wm/distill/
+    pub filename: String,
+
+    /// Injected into sessions (false = stored and searchable only)
+    pub always: bool,
+
+    /// Confidence decays with time since the item was last reinforced
+    pub decay: bool,
+}
+
+impl Category {
+    fn new(name: &str, config: &CategoryConfig) -> Self {
+        let filename = match config.filename.trim() {
+            "" => format!("{}.md", name),
+            filename => filename.to_string(),
+        };
+        Category {
+            name: name.to_string(),
+            title: title(name),
+            description: config.description.trim().to_string(),
+            priority: config.priority,
+            filename,
+            always: config.always,
+            decay: config.decay,
+        }
+    }
+
+    /// Whether `label` names this category (any case, singular or plural,
+    /// spaces, dashes and underscores alike)
+    pub fn matches(&self, label: &str) -> bool {
+        let normalize = |s: &str| {
+            s.trim()
+                .to_lowercase()
+                .replace([' ', '_'], "-")
+                .trim_end_matches('s')
+                .to_string()
+        };
+        normalize(label) == normalize(&self.name)
+    }
+
+    /// Marker-format section header (e.g. "GUARDRAILS")
+    pub fn marker(&self) -> String {
+        self.name.to_uppercase().replace(['-', ' '], "_")
+    }
+}
+
+/// Heading for a category name: "known-pitfalls" -> "Known Pitfalls"
+fn title(name: &str) -> String {
+    name.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Configured categories, in injection order
+pub fn all(ws: &Workspace) -> Vec<Category> {
+    from_config(ws, &state::read_config(ws).categories)
+}
+
+/// Build categories from config, sorted by priority then name
+/// Categories with an unusable filename (not a plain *.md name, or one another
+/// category already writes) are skipped and logged.
+pub fn from_config(ws: &Workspace, config: &CategoriesConfig) -> Vec<Category> {
+    let mut categories: Vec<Category> = config
+        .0
+        .iter()
+        .map(|(name, config)| Category::new(name, config))
+        .collect();
+    categories.sort_by(|a, b| a.priority.cmp(&b.priority).then(a.name.cmp(&b.name)));
+
+    let mut kept: Vec<Category> = Vec::new();
+    for category in categories {
+        let plain = !category.filename.contains(['/', '\\'])
+            && category.filename.ends_with(".md")
+            && category.filename != "raw_extractions.md";
+        if !plain || kept.iter().any(|c| c.filename == category.filename) {
+            state::log(
+                ws,
+                "category",
+                &format!(
+                    "Skipping category '{}': unusable filename '{}'",
+                    category.name, category.filename
+                ),
+            );
+            continue;
+        }
+        kept.push(category);
+    }
+    kept
+}
+
+/// The category a label names, if any
+pub fn find<'a>(categories: &'a [Category], label: &str) -> Option<&'a Category> {
+    categories.iter().find(|c| c.matches(label))
+}
+
+/// Where insights without a known category go: metis if configured, else the
+/// first injected category (else the first one)
+/// Not simply the last category - a custom one left at the default priority
+/// sorts after metis but shouldn't collect everything the LLM mislabels.
+pub fn fallback(categories: &[Category]) -> Option<&Category> {
+    categories
+        .iter()
+        .find(|c| c.name == "metis")
+        .or_else(|| categories.iter().find(|c| c.always))
+        .or_else(|| categories.first())
+}
+
+/// The category a file in .wm/distill/ belongs to, if any
+pub fn for_filename<'a>(categories: &'a [Category], filename: &str) -> Option<&'a Category> {
+    categories.iter().find(|c| c.filename == filename)
+}
+
+/// Names of the categories, for messages ("guardrails, metis")
+pub fn names(categories: &[Category]) -> String {
+    categories
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(entries: &[(&str, u32, &str)]) -> CategoriesConfig {
+        let mut config = CategoriesConfig::default();
+        for (name, priority, filename) in entries {
+            config.0.insert(
+                name.to_string(),
+                CategoryConfig {
+                    description: String::new(),
+                    priority: *priority,
+                    filename: filename.to_string(),
+                    always: true,
+                    decay: true,
+                },
+            );
+        }
+        config
+    }
+
+    #[test]
+    fn test_from_config_orders_and_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let categories = from_config(
+            ws,
+            &config(&[
+                ("known-pitfalls", 15, ""),
+                ("glossary", 50, "metis.md"),
+                ("escape", 60, "../state.md"),
+            ]),
+        );
+        let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["guardrails", "known-pitfalls", "metis"]);
+        assert_eq!(categories[1].title, "Known Pitfalls");
+        assert_eq!(categories[1].filename, "known-pitfalls.md");
+        assert_eq!(categories[1].marker(), "KNOWN_PITFALLS");
+    }
+
+    #[test]
+    fn test_matches_labels() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let categories = from_config(ws, &config(&[("known-pitfalls", 15, "")]));
+        assert_eq!(find(&categories, "Guardrail").unwrap().name, "guardrails");
+        assert_eq!(
+            find(&categories, "KNOWN_PITFALLS").unwrap().name,
+            "known-pitfalls"
+        );
+        assert_eq!(
+            find(&categories, "known pitfall").unwrap().name,
+            "known-pitfalls"
+        );
+        assert!(find(&categories, "glossary").is_none());
+    }
+}
@@ -0,0 +1,603 @@
+//! Command-line interface - the `wm` binary is a thin wrapper around `main`
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::workspace::Workspace;
+use crate::{
+    audit, compile, compress, confidence, config, distill, dive, doctor, evidence, extract, guard,
+    history, init, lint, mcp, migrate, pause, prompts, shared, show, state, usage,
+};
+
+#[derive(Parser)]
+#[command(name = "wm")]
+#[command(about = "Working memory for AI coding assistants")]
+#[command(version)]
+struct Cli {
+    /// Project root containing .wm/ (default: WM_PROJECT_DIR, then nearest ancestor with .wm/, then cwd)
+    #[arg(long, global = true)]
+    project_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Initialize .wm/ in current project
+    Init,
+
+    /// Run LLM extraction from transcript
+    Extract {
+        /// Path to transcript file
+        #[arg(long)]
+        transcript: Option<String>,
+
+        /// Claude session ID (for session-scoped extraction)
+        #[arg(long)]
+        session_id: Option<String>,
+    },
+
+    /// Compile working set for current state
+    Compile {
+        /// User's current message (for intent detection)
+        #[arg(long)]
+        intent: Option<String>,
+    },
+
+    /// Compress state.md by synthesizing to higher-level abstractions
+    Compress,
+
+    /// Batch extract knowledge from all sessions (replaces per-turn extract)
+    Distill {
+        /// Preview what would be extracted without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Force re-extraction even for already-processed sessions
+        #[arg(long)]
+        force: bool,
+
+        /// Push distilled knowledge to Open Horizons via MCP
+        #[arg(long)]
+        push_to_oh: bool,
+
+        /// OH context ID to push to (required if --push-to-oh is set)
+        #[arg(long)]
+        context_id: Option<String>,
+
+        /// Filter to a specific project by name (substring match)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Process Codex sessions instead of Claude Code sessions
+        #[arg(long)]
+        codex: bool,
+
+        /// Stop once this run's LLM calls have cost this much (USD); rerun to continue
+        #[arg(long, value_name = "USD")]
+        max_cost: Option<f64>,
+
+        /// Stop once this run's LLM calls have used this many tokens; rerun to continue
+        #[arg(long, value_name = "TOKENS")]
+        max_tokens: Option<u64>,
+    },
+
+    /// Display state, working set, or sessions
+    Show {
+        /// What to show: state, working, sessions
+        #[arg(default_value = "state")]
+        what: String,
+
+        /// Session ID (for session-specific working set)
+        #[arg(long)]
+        session_id: Option<String>,
+    },
+
+    /// Manage Open Horizons dive pack context
+    Dive {
+        #[command(subcommand)]
+        command: DiveCommands,
+    },
+
+    /// Pause extract, compile, or both operations
+    Pause {
+        /// Operation to pause: extract, compile, or omit for both
+        operation: Option<String>,
+
+        /// Resume automatically after this long (e.g. 30m, 2h, 1h30m, 1d)
+        #[arg(long = "for", value_name = "DURATION", conflicts_with = "until")]
+        duration: Option<String>,
+
+        /// Resume automatically at this time (HH:MM, YYYY-MM-DD HH:MM, RFC 3339)
+        #[arg(long, value_name = "TIME")]
+        until: Option<String>,
+
+        /// Only pause this session; other sessions keep running
+        #[arg(long, value_name = "ID")]
+        session: Option<String>,
+    },
+
+    /// Resume extract, compile, or both operations
+    Resume {
+        /// Operation to resume: extract, compile, or omit for both
+        operation: Option<String>,
+
+        /// Resume a session-scoped pause
+        #[arg(long, value_name = "ID")]
+        session: Option<String>,
+    },
+
+    /// Show current pause/resume status, with remaining time and scope
+    Status,
+
+    /// Read and write settings (defaults < ~/.config/wm < .wm/config.toml < WM_* env)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Hook entry points (called by Claude Code hooks)
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+
+    /// Run as an MCP server over stdio (exposes memory as tools)
+    Mcp,
+
+    /// Promote locally distilled items into .wm/shared/ (committed for the team)
+    Share {
+        /// Category to share (e.g. guardrails, metis), or omit for all
+        category: Option<String>,
+    },
+
+    /// List snapshots of state.md, guardrails and metis (newest first)
+    History {
+        /// Only show snapshots of this file (e.g. state.md, metis.md)
+        file: Option<String>,
+    },
+
+    /// Compare two snapshots, or a snapshot with the current file
+    Diff {
+        /// Older snapshot ID (or unique prefix)
+        a: String,
+
+        /// Newer snapshot ID (default: the file's current content)
+        b: Option<String>,
+    },
+
+    /// Roll a knowledge file back to a snapshot
+    Restore {
+        /// Snapshot ID (or unique prefix)
+        snapshot: String,
+    },
+
+    /// Show or eject the LLM prompt templates (.wm/prompts/, ~/.config/wm/prompts/)
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommands,
+    },
+
+    /// Report LLM token usage and cost (by day and command)
+    Usage {
+        /// Group by: day, command or session (default: day and command)
+        #[arg(long)]
+        by: Option<String>,
+
+        /// Only include the last N days
+        #[arg(long)]
+        days: Option<u32>,
+    },
+
+    /// List distilled items whose confidence has decayed (candidates for pruning)
+    Stale {
+        /// Confidence threshold (default: confidence.stale_threshold)
+        #[arg(long)]
+        below: Option<f64>,
+
+        /// Only this category (e.g. metis)
+        #[arg(long)]
+        category: Option<String>,
+    },
+
+    /// Show the sessions, user quotes and transcript excerpts behind a distilled item
+    Why {
+        /// Item text (or a unique part of it)
+        item: String,
+
+        /// Messages of transcript shown before and after each quote
+        #[arg(long, default_value_t = 2)]
+        context: usize,
+    },
+
+    /// Find contradicting or superseded items across categories and state.md
+    Lint {
+        /// Resolve conflict N from the last check instead of checking again
+        #[arg(long, value_name = "N", requires = "keep")]
+        resolve: Option<usize>,
+
+        /// What to keep: newer, a, b, or both (with --scope-a/--scope-b)
+        #[arg(long, requires = "resolve")]
+        keep: Option<String>,
+
+        /// Paths item a applies to when keeping both (comma-separated)
+        #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+        scope_a: Vec<String>,
+
+        /// Paths item b applies to when keeping both (comma-separated)
+        #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+        scope_b: Vec<String>,
+    },
+
+    /// Browse the audit trail of LLM calls (enable with audit.enabled)
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommands,
+    },
+
+    /// Diagnose setup problems (store, config, LLM backend, sessions, hooks)
+    Doctor,
+
+    /// Upgrade .wm/ from an older layout (runs automatically on first use)
+    Migrate {
+        /// Show what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Git merge driver for .wm/shared/ files (merges item-wise)
+    #[command(name = "merge-driver")]
+    MergeDriver {
+        /// Common ancestor version (%O)
+        base: String,
+
+        /// Our version, overwritten with the result (%A)
+        ours: String,
+
+        /// Their version (%B)
+        theirs: String,
+    },
+
+    /// Prepare dive context (alias for 'wm dive prep')
+    #[command(name = "dive-prep")]
+    DivePrep {
+        /// Intent or context for the dive
+        intent: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective value of a key (e.g. operations.extract)
+    Get { key: String },
+
+    /// Set a key in .wm/config.toml (or the user-global config)
+    Set {
+        key: String,
+        value: String,
+
+        /// Write ~/.config/wm/config.toml instead of the project config
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Remove a key so lower layers (global, defaults) apply again
+    Unset {
+        key: String,
+
+        /// Edit ~/.config/wm/config.toml instead of the project config
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// List effective settings
+    List {
+        /// Show which layer (default, file, env) each value comes from
+        #[arg(long)]
+        show_origin: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PromptsCommands {
+    /// List templates and where each comes from, or print one
+    Show {
+        /// Template name (e.g. distill-extract); omit to list all
+        name: Option<String>,
+
+        /// Print the built-in default instead of the effective template
+        #[arg(long)]
+        default: bool,
+    },
+
+    /// Copy built-in templates into .wm/prompts/ for editing
+    Eject {
+        /// Template name; omit to eject all
+        name: Option<String>,
+
+        /// Write to ~/.config/wm/prompts/ instead (applies to every project)
+        #[arg(long)]
+        global: bool,
+
+        /// Overwrite templates that already exist
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// List recorded LLM calls, newest first
+    List {
+        /// Only show the most recent N calls
+        #[arg(long, short = 'n')]
+        limit: Option<usize>,
+    },
+
+    /// Show one call, with its redacted prompt and response if stored
+    Show {
+        /// Record ID (or unique prefix)
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DiveCommands {
+    /// List all dive preps (marks current with *)
+    List,
+
+    /// Create a new named dive prep
+    New {
+        /// Name for the dive prep (kebab-case)
+        name: String,
+    },
+
+    /// Switch to a named dive prep
+    Switch {
+        /// Name of the prep to switch to
+        name: String,
+    },
+
+    /// Delete a named dive prep
+    Delete {
+        /// Name of the prep to delete
+        name: String,
+    },
+
+    /// Save current dive_context.md as a named prep
+    Save {
+        /// Name to save as (kebab-case)
+        name: String,
+    },
+
+    /// Show current dive prep name
+    Current,
+
+    /// Show dive prep content
+    Show {
+        /// Name of prep to show (default: current)
+        name: Option<String>,
+    },
+
+    /// Load a dive pack from OH
+    Load {
+        /// Dive pack ID to load
+        pack_id: String,
+
+        /// Save as named prep instead of dive_context.md
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Clear the current dive context
+    Clear,
+
+    /// Prepare dive context (requires AI agent - use /wm:dive-prep in Claude Code)
+    Prep {
+        /// Intent or context for the dive
+        intent: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Called by post-submit hook
+    Compile {
+        /// Claude session ID (required for session-scoped output)
+        #[arg(long)]
+        session_id: String,
+    },
+
+    /// Called by session-start hook (startup, resume, clear, compact)
+    SessionStart {
+        /// Claude session ID (required for session-scoped tracking)
+        #[arg(long)]
+        session_id: String,
+    },
+
+    /// Called by pre-tool-use hook (checks .wm/guardrails.toml rules)
+    PreToolUse,
+
+    /// Called by sg after clearing (or manually)
+    Extract,
+}
+
+/// Parse arguments and run a wm command
+pub fn main() -> ExitCode {
+    // Check if disabled
+    if std::env::var("WM_DISABLED").is_ok() {
+        return ExitCode::SUCCESS;
+    }
+
+    let cli = Cli::parse();
+
+    let explicit = state::explicit_project_dir(cli.project_dir);
+    let pinned = explicit.is_some();
+    if let Commands::Init = cli.command {
+        let target = explicit
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        return exit_code(init::run(target));
+    }
+    let ws = &Workspace::new(state::project_dir(explicit));
+
+    // Upgrade older stores on first use (migrate previews/applies explicitly)
+    if !matches!(
+        cli.command,
+        Commands::Migrate { .. } | Commands::MergeDriver { .. }
+    ) {
+        migrate::auto_migrate(ws);
+    }
+
+    // Surface config.toml problems; hooks and the MCP server only log them
+    if !matches!(cli.command, Commands::Doctor | Commands::MergeDriver { .. })
+        && state::is_initialized(ws)
+    {
+        let quiet = matches!(cli.command, Commands::Hook { .. } | Commands::Mcp);
+        for problem in state::config_problems(ws) {
+            if quiet {
+                state::log(ws, "config", &problem);
+            } else {
+                eprintln!("Warning: {}", problem);
+            }
+        }
+    }
+
+    let result = match cli.command {
+        Commands::Init => unreachable!("handled above"),
+        Commands::Extract {
+            transcript,
+            session_id,
+        } => extract::run(ws, transcript, session_id),
+        Commands::Compile { intent } => compile::run(ws, intent),
+        Commands::Compress => compress::run(ws),
+        Commands::Distill {
+            dry_run,
+            force,
+            push_to_oh,
+            context_id,
+            project,
+            codex,
+            max_cost,
+            max_tokens,
+        } => distill::run(
+            ws,
+            distill::DistillOptions {
+                dry_run,
+                force,
+                push_to_oh,
+                context_id,
+                project,
+                codex,
+                max_cost,
+                max_tokens,
+            },
+            &distill::print_progress,
+        )
+        .map(|_| ())
+        .map_err(String::from),
+        Commands::Show { what, session_id } => show::run(ws, &what, session_id.as_deref()),
+        Commands::Dive { command } => match command {
+            DiveCommands::List => dive::list(ws),
+            DiveCommands::New { name } => dive::new(ws, &name, None),
+            DiveCommands::Switch { name } => dive::switch(ws, &name),
+            DiveCommands::Delete { name } => dive::delete(ws, &name),
+            DiveCommands::Save { name } => dive::save(ws, &name),
+            DiveCommands::Current => dive::current(ws),
+            DiveCommands::Show { name } => dive::show(ws, name.as_deref()),
+            DiveCommands::Load { pack_id, name } => dive::load(ws, &pack_id, name.as_deref()),
+            DiveCommands::Clear => dive::clear(ws),
+            DiveCommands::Prep { intent } => dive::prep(intent.as_deref()),
+        },
+        Commands::Pause {
+            operation,
+            duration,
+            until,
+            session,
+        } => pause::run_pause(
+            ws,
+            operation.as_deref(),
+            pause::PauseOptions {
+                duration,
+                until,
+                session,
+            },
+        ),
+        Commands::Resume { operation, session } => {
+            pause::run_resume(ws, operation.as_deref(), session.as_deref())
+        }
+        Commands::Status => pause::run_status(ws),
+        Commands::Config { command } => match command {
+            ConfigCommands::Get { key } => config::run_get(ws, &key),
+            ConfigCommands::Set { key, value, global } => config::run_set(ws, &key, &value, global),
+            ConfigCommands::Unset { key, global } => config::run_unset(ws, &key, global),
+            ConfigCommands::List { show_origin } => config::run_list(ws, show_origin),
+        },
+        Commands::Hook { command } => match command {
+            HookCommands::Compile { session_id } => compile::run_hook(ws, &session_id),
+            HookCommands::SessionStart { session_id } => {
+                compile::run_session_start_hook(ws, &session_id)
+            }
+            HookCommands::PreToolUse => guard::run_hook(ws),
+            HookCommands::Extract => extract::run_hook(ws),
+        },
+        Commands::Mcp => mcp::run(ws),
+        Commands::Share { category } => shared::run(ws, category.as_deref()),
+        Commands::History { file } => history::run_list(ws, file.as_deref()),
+        Commands::Diff { a, b } => history::run_diff(ws, &a, b.as_deref()),
+        Commands::Restore { snapshot } => history::run_restore(ws, &snapshot),
+        Commands::Prompts { command } => match command {
+            PromptsCommands::Show { name, default } => {
+                prompts::run_show(ws, name.as_deref(), default)
+            }
+            PromptsCommands::Eject {
+                name,
+                global,
+                force,
+            } => prompts::run_eject(ws, name.as_deref(), global, force),
+        },
+        Commands::Usage { by, days } => usage::run(ws, by.as_deref(), days),
+        Commands::Stale { below, category } => {
+            confidence::run_stale(ws, below, category.as_deref())
+        }
+        Commands::Why { item, context } => evidence::run_why(ws, &item, context),
+        Commands::Lint {
+            resolve,
+            keep,
+            scope_a,
+            scope_b,
+        } => lint::run(
+            ws,
+            resolve,
+            lint::ResolveOptions {
+                keep: keep.unwrap_or_default(),
+                scope_a,
+                scope_b,
+            },
+        ),
+        Commands::Audit { command } => match command {
+            AuditCommands::List { limit } => audit::run_list(ws, limit),
+            AuditCommands::Show { id } => audit::run_show(ws, &id),
+        },
+        Commands::Doctor => doctor::run(ws, pinned),
+        Commands::Migrate { dry_run } => migrate::run(ws, dry_run),
+        Commands::MergeDriver { base, ours, theirs } => {
+            shared::run_merge_driver(&base, &ours, &theirs)
+        }
+        Commands::DivePrep { intent } => dive::prep(intent.as_deref()),
+    };
+
+    exit_code(result)
+}
+
+fn exit_code(result: Result<(), String>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
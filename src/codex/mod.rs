@@ -7,5 +7,5 @@ pub mod reader;
 pub mod session;
 pub mod types;
 
-pub use reader::{format_context, read_codex_session};
+pub use reader::{format_context, ignored_call_ids, read_codex_session, session_facts};
 pub use session::discover_sessions;
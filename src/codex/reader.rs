@@ -7,7 +7,10 @@ use std::io::{BufRead, BufReader};
 use std::path::Path;
 
 use crate::codex::types::CodexEntry;
-use crate::types::{strip_xml_tags, ReadError};
+use crate::ignore::{IgnoreRules, SessionFacts};
+use crate::types::{ReadError, strip_xml_tags};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 
 /// Read and parse a Codex session JSONL file
 ///
@@ -39,17 +42,66 @@ pub fn read_codex_session(path: &Path) -> Result<Vec<CodexEntry>, ReadError> {
     Ok(entries)
 }
 
+/// Call IDs of function calls that touch an ignored path or run an ignored command
+pub fn ignored_call_ids(entries: &[CodexEntry], rules: &IgnoreRules) -> HashSet<String> {
+    entries
+        .iter()
+        .filter(|entry| {
+            let (Some(name), Some(args)) = (entry.function_call_name(), entry.function_call_args())
+            else {
+                return false;
+            };
+            let target = summarize_tool_args(name, args);
+            let ignored_target = match name {
+                "shell" => rules.ignores_command(&target),
+                _ => !target.is_empty() && rules.ignores_path(&target),
+            };
+            ignored_target || patched_files(args).any(|path| rules.ignores_path(path))
+        })
+        .filter_map(|entry| entry.call_id().map(String::from))
+        .collect()
+}
+
+/// Files named in apply_patch headers inside tool arguments
+fn patched_files(args: &str) -> impl Iterator<Item = &str> {
+    args.split("\\n")
+        .flat_map(|chunk| chunk.lines())
+        .filter_map(|line| {
+            ["*** Add File: ", "*** Update File: ", "*** Delete File: "]
+                .iter()
+                .find_map(|header| line.trim().strip_prefix(header))
+        })
+        .map(|path| path.trim().trim_end_matches(['"', '\\']))
+}
+
+/// Session ID, start time and first user message, for session-level ignore rules
+pub fn session_facts(entries: &[CodexEntry]) -> SessionFacts<'_> {
+    SessionFacts {
+        id: entries
+            .iter()
+            .find_map(|e| e.session_id())
+            .unwrap_or_default(),
+        started: entries
+            .iter()
+            .find_map(|e| DateTime::parse_from_rfc3339(&e.timestamp).ok())
+            .map(|ts| ts.with_timezone(&Utc)),
+        first_user_message: entries.iter().find_map(|e| e.user_message_text()),
+    }
+}
+
 /// Format Codex entries for context extraction (for sending to extraction LLM)
 ///
 /// Formats relevant entries into a human-readable transcript similar to
-/// the Claude Code format_context function.
-pub fn format_context(entries: &[CodexEntry]) -> String {
+/// the Claude Code format_context function. Calls in `ignored` are shown
+/// without arguments and their output is dropped.
+pub fn format_context(entries: &[CodexEntry], ignored: &HashSet<String>) -> String {
     let mut output = String::new();
 
     for entry in entries {
         if !entry.is_relevant() {
             continue;
         }
+        let is_ignored = entry.call_id().is_some_and(|id| ignored.contains(id));
 
         if let Some(text) = entry.user_message_text() {
             let cleaned = strip_environment_context(text);
@@ -76,7 +128,9 @@ pub fn format_context(entries: &[CodexEntry]) -> String {
                 output.push_str(name);
 
                 // Add summary of arguments for context
-                if let Some(args) = entry.function_call_args() {
+                if is_ignored {
+                    output.push_str("([ignored])");
+                } else if let Some(args) = entry.function_call_args() {
                     let summary = summarize_tool_args(name, args);
                     if !summary.is_empty() {
                         output.push('(');
@@ -87,6 +141,7 @@ pub fn format_context(entries: &[CodexEntry]) -> String {
                 output.push('\n');
             }
         } else if entry.is_function_call_output()
+            && !is_ignored
             && let Some(output_text) = entry.function_call_output()
         {
             // Truncate very long outputs (respecting UTF-8 boundaries)
@@ -193,8 +248,31 @@ mod tests {
             .unwrap(),
         ];
 
-        let formatted = format_context(&entries);
+        let formatted = format_context(&entries, &HashSet::new());
         assert!(formatted.contains("USER: Hello"));
         assert!(formatted.contains("ASSISTANT: Hi there"));
     }
+
+    #[test]
+    fn test_format_context_drops_ignored_calls() {
+        let entries: Vec<CodexEntry> = [
+            r#"{"timestamp":"t","type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"cat secrets/key.txt\"]}","call_id":"c1"}}"#,
+            r#"{"timestamp":"t","type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":"TOPSECRET"}}"#,
+            r#"{"timestamp":"t","type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"ls\"]}","call_id":"c2"}}"#,
+            r#"{"timestamp":"t","type":"response_item","payload":{"type":"function_call_output","call_id":"c2","output":"README.md"}}"#,
+        ]
+        .iter()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+        let rules = IgnoreRules::new(
+            std::path::PathBuf::from("/repo"),
+            "secrets/\n",
+            &Default::default(),
+        );
+
+        let formatted = format_context(&entries, &ignored_call_ids(&entries, &rules));
+        assert!(!formatted.contains("TOPSECRET"));
+        assert!(formatted.contains("TOOL: shell([ignored])"));
+        assert!(formatted.contains("README.md"));
+    }
 }
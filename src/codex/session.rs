@@ -9,7 +9,7 @@ use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
 use crate::codex::types::CodexEntry;
-use crate::types::{system_time_to_datetime, CodexSessionInfo};
+use crate::types::{CodexSessionInfo, system_time_to_datetime};
 
 /// Get the Codex sessions root directory (~/.codex/sessions/)
 pub fn codex_sessions_dir() -> Option<PathBuf> {
@@ -78,7 +78,7 @@ pub fn discover_sessions(project_filter: Option<&str>) -> Result<Vec<CodexSessio
     }
 
     // Sort by modification time, newest first
-    sessions.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified_at));
 
     Ok(sessions)
 }
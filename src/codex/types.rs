@@ -151,6 +151,15 @@ impl CodexEntry {
         }
     }
 
+    /// Call ID linking a function call to its output
+    pub fn call_id(&self) -> Option<&str> {
+        if self.is_function_call() || self.is_function_call_output() {
+            self.payload.get("call_id").and_then(|v| v.as_str())
+        } else {
+            None
+        }
+    }
+
     /// Extract function call output
     pub fn function_call_output(&self) -> Option<String> {
         if self.is_function_call_output() {
@@ -1,15 +1,24 @@
 //! Working set compilation
 //!
-//! Reads distilled knowledge (guardrails + metis) and optional dive context,
-//! then combines them into a working set for the current session.
-//! All content is pre-curated, no LLM filtering needed.
-
+//! Reads distilled knowledge (one file per category, guardrails + metis by
+//! default) and optional dive context, then combines them into a working set
+//! for the current session. All content is pre-curated, no LLM filtering needed.
+
+use crate::category::{self, Category};
+use crate::confidence::{self, Scorer};
+use crate::scope::{self, ScopeContext};
+use crate::shared;
 use crate::state;
-use crate::types::{HookResponse, HookSpecificOutput};
+use crate::types::{HookResponse, HookSpecificOutput, content_hash};
+use crate::workspace::Workspace;
+use std::collections::BTreeSet;
 
 /// Distill directory constant (matches distill.rs)
 const DISTILL_DIR: &str = "distill";
 
+/// Manually added items directory (matches distill::MANUAL_DIR)
+const MANUAL_DIR: &str = "manual";
+
 /// Run wm compile with optional intent (CLI entry point)
 /// AIDEV-NOTE: Returns Ok() instead of Err when not initialized. This is intentional:
 /// extract/compile can be triggered automatically by hooks, so they must not spam error
@@ -17,34 +26,26 @@ const DISTILL_DIR: &str = "distill";
 /// Err to inform the user. See also: extract::run().
 /// AIDEV-NOTE: Intent parameter is now unused since we don't do LLM filtering.
 /// Kept for API compatibility.
-pub fn run(_intent: Option<String>) -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn run(ws: &Workspace, _intent: Option<String>) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         eprintln!("Not initialized. Run 'wm init' first.");
         return Ok(());
     }
 
     // Check if compile is paused
-    if !state::is_compile_enabled() {
+    if !state::is_compile_enabled(ws, None) {
         println!("Compile is paused. Use 'wm resume compile' to enable.");
         return Ok(());
     }
 
-    // Read distilled knowledge (pre-curated, no filtering needed)
-    let guardrails = read_distilled_file("guardrails.md");
-    let metis = read_distilled_file("metis.md");
-
-    // Check for dive context - try named prep first, then legacy fallback
-    let dive_context = read_dive_context();
-
-    // Combine all sources
-    let combined = combine_context(&dive_context, &guardrails, &metis);
+    let combined = build_working_set(ws);
 
     if combined.trim().is_empty() {
         println!("No distilled knowledge found. Run 'wm distill' first.");
         return Ok(());
     }
 
-    state::write_working_set(&combined)
+    state::write_working_set(ws, &combined)
         .map_err(|e| format!("Failed to write working set: {}", e))?;
     println!("Compiled working set to .wm/working_set.md");
     Ok(())
@@ -52,112 +53,336 @@ pub fn run(_intent: Option<String>) -> Result<(), String> {
 
 /// Run from post-submit hook - reads intent from stdin, outputs JSON
 /// Never blocks - returns empty response on any failure
-/// AIDEV-NOTE: Intent is consumed from stdin but not used for filtering since
-/// distilled content is pre-curated and always relevant.
-pub fn run_hook(session_id: &str) -> Result<(), String> {
-    if !state::is_initialized() {
+/// AIDEV-NOTE: Only injects items the session hasn't seen yet (new or changed since
+/// the last injection). The full working set is injected once by the SessionStart
+/// hook; if that hook never ran, the injected record is empty and everything goes out.
+/// The prompt itself is not used for filtering; only path-scoped items are filtered,
+/// by the session's cwd and recently touched files.
+pub fn run_hook(ws: &Workspace, session_id: &str) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         // Silent success if not initialized
         return Ok(());
     }
 
     // Check if compile is paused
-    if !state::is_compile_enabled() {
-        state::log("compile", "Paused via config, returning empty");
-        let response = HookResponse {
-            hook_specific_output: None,
-        };
-        let json = serde_json::to_string(&response).map_err(|e| e.to_string())?;
-        println!("{}", json);
-        return Ok(());
+    if !state::is_compile_enabled(ws, Some(session_id)) {
+        state::log(ws, "compile", "Paused via config, returning empty");
+        return print_empty_response();
     }
 
-    state::log("compile", "Hook fired");
+    state::log(ws, "compile", "Hook fired");
 
-    // Consume stdin (intent) but don't use it - distilled content is always relevant
-    let _ = read_hook_input();
-    state::log("compile", &format!("Session: {}", session_id));
+    let input = read_hook_json();
+    state::log(ws, "compile", &format!("Session: {}", session_id));
 
-    // Read distilled knowledge (pre-curated, no filtering needed)
-    let guardrails = read_distilled_file("guardrails.md");
-    let metis = read_distilled_file("metis.md");
+    let items = collect_items(&hook_working_set(ws, &input, session_id));
 
-    // Check for dive context - try named prep first, then legacy fallback
-    let dive_context = read_dive_context();
+    // Two prompts in one session must not both inject the same fresh items
+    let _lock = match state::lock_injected_items(ws, session_id) {
+        Ok(lock) => lock,
+        Err(e) => {
+            state::log(ws, "compile", &format!("Skipping injection: {}", e));
+            return print_empty_response();
+        }
+    };
+    let mut injected = state::read_injected_items(ws, session_id);
+    let fresh: Vec<&ContextItem> = items
+        .iter()
+        .filter(|item| !injected.contains(&item.hash()))
+        .collect();
 
-    // Log what we found
-    if !dive_context.trim().is_empty() {
+    if fresh.is_empty() {
         state::log(
+            ws,
             "compile",
-            &format!("Dive context: {} bytes", dive_context.len()),
+            &format!("No new items ({} already injected)", items.len()),
         );
+        return print_empty_response();
     }
-    if !guardrails.trim().is_empty() {
+
+    state::log(
+        ws,
+        "compile",
+        &format!("Injecting {} new of {} item(s)", fresh.len(), items.len()),
+    );
+
+    let delta = render_items(&fresh);
+    injected.extend(fresh.iter().map(|item| item.hash()));
+    if let Err(e) = state::write_injected_items(ws, session_id, &injected) {
         state::log(
+            ws,
             "compile",
-            &format!("Guardrails: {} bytes", guardrails.len()),
+            &format!("Failed to record injected items: {}", e),
         );
     }
-    if !metis.trim().is_empty() {
-        state::log("compile", &format!("Metis: {} bytes", metis.len()));
+
+    print_context_response("UserPromptSubmit", delta)
+}
+
+/// Run from SessionStart hook - injects the full working set once per session
+/// AIDEV-NOTE: Fires on startup, resume, clear and compact. Each of those starts
+/// from a context window that no longer holds earlier injections, so the session's
+/// injected record is reset to exactly what we send here.
+pub fn run_session_start_hook(ws: &Workspace, session_id: &str) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Ok(());
     }
 
-    // Combine all sources (no LLM filtering - all content is pre-curated)
-    let final_content = combine_context(&dive_context, &guardrails, &metis);
+    if !state::is_compile_enabled(ws, Some(session_id)) {
+        state::log(ws, "session-start", "Paused via config, returning empty");
+        return print_empty_response();
+    }
 
-    let has_content = !final_content.trim().is_empty();
+    let input = read_hook_json();
+    let source = input
+        .get("source")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    state::log(
+        ws,
+        "session-start",
+        &format!("Session: {} (source: {})", session_id, source),
+    );
+
+    let working_set = hook_working_set(ws, &input, session_id);
+    let final_content = working_set.content.clone();
+    let injected: BTreeSet<String> = collect_items(&working_set)
+        .iter()
+        .map(|item| item.hash())
+        .collect();
+    // Hold the lock so a concurrent UserPromptSubmit can't write back a record
+    // read before this reset. Without it we still inject, and at worst later
+    // prompts re-send some items.
+    match state::lock_injected_items(ws, session_id) {
+        Ok(_lock) => {
+            if let Err(e) = state::write_injected_items(ws, session_id, &injected) {
+                state::log(
+                    ws,
+                    "session-start",
+                    &format!("Failed to record injected items: {}", e),
+                );
+            }
+        }
+        Err(e) => state::log(
+            ws,
+            "session-start",
+            &format!("Not recording injected items: {}", e),
+        ),
+    }
 
-    if !has_content {
-        state::log("compile", "No distilled content found, returning empty");
-        let response = HookResponse {
-            hook_specific_output: None,
-        };
-        let json = serde_json::to_string(&response).map_err(|e| e.to_string())?;
-        println!("{}", json);
-        return Ok(());
+    if final_content.trim().is_empty() {
+        state::log(
+            ws,
+            "session-start",
+            "No distilled content found, returning empty",
+        );
+        return print_empty_response();
     }
 
     // Write working_set for debugging/inspection
-    let _ = state::write_working_set_for_session(session_id, &final_content);
+    let _ = state::write_working_set_for_session(ws, session_id, &final_content);
+
+    state::log(
+        ws,
+        "session-start",
+        &format!("Injected {} item(s)", injected.len()),
+    );
+    print_context_response("SessionStart", final_content)
+}
+
+/// Print a hook response carrying no additional context
+fn print_empty_response() -> Result<(), String> {
+    let response = HookResponse {
+        hook_specific_output: None,
+    };
+    let json = serde_json::to_string(&response).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
 
-    // Output hook response with proper Claude Code structure
+/// Print a hook response injecting `content` with proper Claude Code structure
+fn print_context_response(hook_event_name: &str, content: String) -> Result<(), String> {
     let response = HookResponse {
         hook_specific_output: Some(HookSpecificOutput {
-            hook_event_name: "UserPromptSubmit".to_string(),
-            additional_context: Some(final_content),
+            hook_event_name: hook_event_name.to_string(),
+            additional_context: Some(content),
+            permission_decision: None,
+            permission_decision_reason: None,
         }),
     };
-
     let json = serde_json::to_string(&response).map_err(|e| e.to_string())?;
-    state::log("compile", "Complete");
     println!("{}", json);
-
     Ok(())
 }
 
-/// Read intent from hook input (stdin contains JSON with prompt field)
-fn read_hook_input() -> Option<String> {
+/// Read hook input JSON from stdin (Null if missing or malformed)
+fn read_hook_json() -> serde_json::Value {
     use std::io::{self, Read};
 
     let mut buffer = String::new();
-    if io::stdin().read_to_string(&mut buffer).is_ok() && !buffer.trim().is_empty() {
-        // Try to parse as JSON hook input
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&buffer) {
-            return json
-                .get("prompt")
-                .and_then(|v| v.as_str())
-                .map(String::from);
+    if io::stdin().read_to_string(&mut buffer).is_err() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_str(&buffer).unwrap_or(serde_json::Value::Null)
+}
+
+/// Compile the working set for a hook, scoped to where the session is working
+/// AIDEV-NOTE: Skips reading the transcript entirely when nothing is scoped,
+/// which keeps the per-prompt hook cheap for projects not using scopes.
+fn hook_working_set(ws: &Workspace, input: &serde_json::Value, session_id: &str) -> WorkingSet {
+    let working_set = compile_working_set(ws);
+    if !working_set.has_scoped_items() {
+        return working_set;
+    }
+    working_set.in_scope(&ScopeContext::from_hook_input(ws, input, session_id))
+}
+
+/// The compiled working set and the sources it was built from
+#[derive(Debug, Clone, Default)]
+pub struct WorkingSet {
+    /// Current dive prep (or legacy dive_context.md)
+    pub dive_context: String,
+
+    /// Distilled knowledge of each injected category, in priority order
+    pub sections: Vec<KnowledgeSection>,
+
+    /// Combined markdown injected into sessions
+    pub content: String,
+}
+
+impl WorkingSet {
+    /// Keep only path-scoped items that apply to `scope`
+    /// AIDEV-NOTE: User items were already checked against every project item,
+    /// scoped or not, so a project rule for backend/ still overrides the user's
+    /// version of it elsewhere.
+    pub(crate) fn in_scope(mut self, scope: &ScopeContext) -> WorkingSet {
+        for section in &mut self.sections {
+            section.content = filter_scoped(&section.content, &section.category.title, scope);
+            section.user_items.retain(|item| scope.includes(item));
         }
-        // Fallback: treat raw input as the intent
-        Some(buffer.trim().to_string())
-    } else {
-        None
+        self.content = combine_context(&self);
+        self
+    }
+
+    /// Whether any item is path-scoped (if not, scoping can't change anything)
+    fn has_scoped_items(&self) -> bool {
+        self.sections.iter().any(|section| {
+            split_items(&section.content)
+                .iter()
+                .chain(&section.user_items)
+                .any(|item| scope::is_scoped(item))
+        })
+    }
+}
+
+/// One category's share of the working set
+#[derive(Debug, Clone)]
+pub struct KnowledgeSection {
+    pub category: Category,
+
+    /// Contents of distill/<filename> (with manual and shared items appended)
+    pub content: String,
+
+    /// User-global items not already covered by project items
+    pub user_items: Vec<String>,
+}
+
+impl KnowledgeSection {
+    /// Heading for this category's user-global items
+    fn user_title(&self) -> String {
+        format!(
+            "User {} (project items take precedence on conflict)",
+            self.category.title
+        )
+    }
+}
+
+/// Compile the working set from dive context and each injected category
+/// (project first, then user-global items layered underneath)
+/// Includes every item regardless of path scope.
+pub fn compile_working_set(ws: &Workspace) -> WorkingSet {
+    // Read distilled knowledge (pre-curated, filtered by confidence)
+    let scorer = Scorer::load(ws);
+    let mut sections: Vec<KnowledgeSection> = category::all(ws)
+        .into_iter()
+        .filter(|category| category.always)
+        .map(|category| KnowledgeSection {
+            content: rank_by_confidence(
+                &read_distilled_file(ws, &category.filename),
+                &category,
+                &scorer,
+            ),
+            category,
+            user_items: Vec::new(),
+        })
+        .collect();
+
+    // Check for dive context - try named prep first, then legacy fallback
+    let dive_context = read_dive_context(ws);
+
+    // User-global items, minus anything the project already says
+    let project_items: Vec<String> = sections
+        .iter()
+        .flat_map(|section| split_items(&section.content))
+        .collect();
+    for section in &mut sections {
+        section.user_items = read_user_items(&section.category.filename, &project_items);
     }
+
+    let mut working_set = WorkingSet {
+        dive_context,
+        sections,
+        content: String::new(),
+    };
+    working_set.content = combine_context(&working_set);
+    working_set
 }
 
-/// Read a distilled file from .wm/distill/
-fn read_distilled_file(filename: &str) -> String {
-    let path = state::wm_path(DISTILL_DIR).join(filename);
-    std::fs::read_to_string(path).unwrap_or_default()
+/// Build the full working set as markdown
+/// Shared by the CLI, the SessionStart hook and the MCP server.
+pub fn build_working_set(ws: &Workspace) -> String {
+    compile_working_set(ws).content
+}
+
+/// Read a distilled file from .wm/distill/, with manually added items from
+/// .wm/manual/ and team items from .wm/shared/ appended
+/// AIDEV-NOTE: Items the local distill already has (normalized) are skipped.
+/// Shared items are rendered without their IDs so the working set reads the
+/// same whether knowledge came from this user's sessions or from the team.
+pub fn read_distilled_file(ws: &Workspace, filename: &str) -> String {
+    let path = state::wm_path(ws, DISTILL_DIR).join(filename);
+    let local = std::fs::read_to_string(path).unwrap_or_default();
+    let manual =
+        std::fs::read_to_string(state::wm_path(ws, MANUAL_DIR).join(filename)).unwrap_or_default();
+
+    let mut seen: Vec<String> = split_items(&local)
+        .iter()
+        .map(|item| normalize_item(item))
+        .collect();
+    let mut extra: Vec<String> = Vec::new();
+    let candidates = split_items(&manual).into_iter().chain(
+        shared::read_items(ws, filename)
+            .into_iter()
+            .map(|item| item.text),
+    );
+    for text in candidates {
+        let normalized = normalize_item(&text);
+        if !seen.contains(&normalized) {
+            seen.push(normalized);
+            extra.push(text);
+        }
+    }
+
+    if extra.is_empty() {
+        return local;
+    }
+    let bullets: Vec<String> = extra.iter().map(|item| format!("- {}", item)).collect();
+    if local.trim().is_empty() {
+        let title = shared::title_for(ws, filename).unwrap_or_else(|| "Knowledge".to_string());
+        format!("# {}\n\n{}\n", title, bullets.join("\n"))
+    } else {
+        format!("{}\n{}\n", local.trim_end(), bullets.join("\n"))
+    }
 }
 
 /// Read dive context - tries named prep first, then legacy fallback
@@ -165,10 +390,10 @@ fn read_distilled_file(filename: &str) -> String {
 /// 1. Current named prep from .wm/dives/{name}.md (if config.dive.current is set)
 /// 2. Legacy .wm/dive_context.md
 /// 3. Legacy .wm/OH_context.md
-fn read_dive_context() -> String {
+fn read_dive_context(ws: &Workspace) -> String {
     // Try current named prep first
-    if let Some(current_name) = state::current_dive() {
-        let path = state::dive_prep_path(&current_name);
+    if let Some(current_name) = state::current_dive(ws) {
+        let path = state::dive_prep_path(ws, &current_name);
         if let Ok(content) = std::fs::read_to_string(&path) {
             return content;
         }
@@ -176,30 +401,392 @@ fn read_dive_context() -> String {
     }
 
     // Legacy fallback
-    std::fs::read_to_string(state::wm_path("dive_context.md"))
-        .or_else(|_| std::fs::read_to_string(state::wm_path("OH_context.md")))
+    std::fs::read_to_string(state::wm_path(ws, "dive_context.md"))
+        .or_else(|_| std::fs::read_to_string(state::wm_path(ws, "OH_context.md")))
         .unwrap_or_default()
 }
 
+/// Read user-global items from ~/.config/wm/distill/, minus those the project overrides
+fn read_user_items(filename: &str, project_items: &[String]) -> Vec<String> {
+    let Some(path) = state::global_path(DISTILL_DIR).map(|dir| dir.join(filename)) else {
+        return Vec::new();
+    };
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    without_overridden(split_items(&content), project_items)
+}
+
+/// Drop user items that restate or contradict a project item
+/// AIDEV-NOTE: This is how project items take precedence: a user item that is
+/// `confidence::similar` to a project item ("Use tabs" vs "Never use tabs")
+/// talks about the same thing, so only the project's version is shown.
+/// Conflicts phrased too differently to catch are left to the model, guided by
+/// the precedence note in the user sections.
+fn without_overridden(user_items: Vec<String>, project_items: &[String]) -> Vec<String> {
+    user_items
+        .into_iter()
+        .filter(|item| !project_items.iter().any(|p| confidence::similar(p, item)))
+        .collect()
+}
+
+/// Drop path-scoped items that don't apply to `scope` from a distilled file
+/// Files without scoped items are returned untouched.
+fn filter_scoped(content: &str, default_title: &str, scope: &ScopeContext) -> String {
+    let items = split_items(content);
+    if !items.iter().any(|item| scope::is_scoped(item)) {
+        return content.to_string();
+    }
+
+    let kept: Vec<&str> = items
+        .iter()
+        .filter(|item| scope.includes(item))
+        .map(String::as_str)
+        .collect();
+    if kept.len() == items.len() {
+        return content.to_string();
+    }
+    if kept.is_empty() {
+        return String::new();
+    }
+
+    let title = content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .unwrap_or(default_title);
+    format_section(title, kept.into_iter())
+}
+
+/// Order a distilled file's items by confidence, dropping those below the
+/// injection threshold
+/// Files whose items are already in order and all injected are returned untouched.
+fn rank_by_confidence(content: &str, category: &Category, scorer: &Scorer) -> String {
+    let items = split_items(content);
+    let mut ranked: Vec<(f64, &str)> = items
+        .iter()
+        .filter(|item| scorer.injects(category, item))
+        .map(|item| (scorer.score(category, item), item.as_str()))
+        .collect();
+    // Stable sort: equally confident items keep their file order
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let unchanged = ranked.len() == items.len()
+        && ranked
+            .iter()
+            .zip(&items)
+            .all(|((_, a), b)| *a == b.as_str());
+    if unchanged {
+        return content.to_string();
+    }
+    if ranked.is_empty() {
+        return String::new();
+    }
+
+    let title = content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .unwrap_or(&category.title);
+    format_section(title, ranked.into_iter().map(|(_, item)| item))
+}
+
+/// Normalize an item for duplicate detection
+pub fn normalize_item(item: &str) -> String {
+    item.trim().trim_end_matches('.').to_lowercase()
+}
+
 /// Combine context sources into a single markdown document
-/// Order: dive_context (session-specific grounding) → guardrails → metis
-fn combine_context(dive_context: &str, guardrails: &str, metis: &str) -> String {
+/// Order: dive_context (session-specific grounding) → each category by priority
+/// (guardrails → metis by default) → user-global items, in the same order
+fn combine_context(working_set: &WorkingSet) -> String {
     let mut sections = Vec::new();
 
     // Dive context first (session-specific grounding)
-    if !dive_context.trim().is_empty() {
-        sections.push(dive_context.trim().to_string());
+    if !working_set.dive_context.trim().is_empty() {
+        sections.push(working_set.dive_context.trim().to_string());
     }
 
-    // Guardrails (hard constraints)
-    if !guardrails.trim().is_empty() {
-        sections.push(guardrails.trim().to_string());
+    // Project knowledge, hard constraints first with the default priorities
+    for section in &working_set.sections {
+        if !section.content.trim().is_empty() {
+            sections.push(section.content.trim().to_string());
+        }
     }
 
-    // Metis (wisdom/patterns)
-    if !metis.trim().is_empty() {
-        sections.push(metis.trim().to_string());
+    // User-global knowledge, layered under the project
+    for section in &working_set.sections {
+        if !section.user_items.is_empty() {
+            sections.push(format_section(
+                &section.user_title(),
+                section.user_items.iter().map(String::as_str),
+            ));
+        }
     }
 
     sections.join("\n\n---\n\n")
 }
+
+/// Format a titled bullet list section
+fn format_section<'a>(title: &str, items: impl Iterator<Item = &'a str>) -> String {
+    let bullets: Vec<String> = items.map(|item| format!("- {}", item)).collect();
+    format!("# {}\n\n{}", title, bullets.join("\n"))
+}
+
+// ============================================================================
+// Per-session injection tracking
+// ============================================================================
+
+/// A single injectable unit of the working set
+/// AIDEV-NOTE: Category files are split into bullet items so a new item
+/// doesn't force re-injection of the whole file. Dive context is one item.
+#[derive(Debug, Clone)]
+struct ContextItem {
+    /// Position of the item's section in the working set (dive context is 0)
+    rank: usize,
+
+    /// Section heading (None for dive context, which brings its own)
+    title: Option<String>,
+
+    text: String,
+}
+
+impl ContextItem {
+    /// Stable identity used in the per-session injected record
+    fn hash(&self) -> String {
+        content_hash(&self.text)
+    }
+}
+
+/// Collect all items of a working set, in injection order
+fn collect_items(working_set: &WorkingSet) -> Vec<ContextItem> {
+    let mut items = Vec::new();
+
+    if !working_set.dive_context.trim().is_empty() {
+        items.push(ContextItem {
+            rank: 0,
+            title: None,
+            text: working_set.dive_context.trim().to_string(),
+        });
+    }
+
+    // Project sections, then user-global sections (as in combine_context)
+    let count = working_set.sections.len();
+    for (i, section) in working_set.sections.iter().enumerate() {
+        let groups = [
+            (
+                1 + i,
+                &section.category.title,
+                split_items(&section.content),
+            ),
+            (
+                1 + count + i,
+                &section.user_title(),
+                section.user_items.clone(),
+            ),
+        ];
+        for (rank, title, texts) in groups {
+            items.extend(texts.into_iter().map(|text| ContextItem {
+                rank,
+                title: Some(title.clone()),
+                text,
+            }));
+        }
+    }
+
+    items
+}
+
+/// Split a distilled markdown file into bullet items
+/// Headings are dropped; continuation lines stay attached to their bullet.
+/// Content without any bullets is treated as a single item.
+pub fn split_items(content: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    let mut in_bullet = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            items.push(item.trim().to_string());
+            in_bullet = true;
+        } else if trimmed.is_empty() || trimmed.starts_with('#') {
+            in_bullet = false;
+        } else if in_bullet && let Some(last) = items.last_mut() {
+            last.push(' ');
+            last.push_str(trimmed);
+        }
+    }
+
+    if items.is_empty() && !content.trim().is_empty() {
+        items.push(content.trim().to_string());
+    }
+
+    items
+}
+
+/// Render a subset of items as markdown, grouped by section in working set order
+fn render_items(items: &[&ContextItem]) -> String {
+    let ranks: BTreeSet<usize> = items.iter().map(|item| item.rank).collect();
+    let mut sections = Vec::new();
+
+    for rank in ranks {
+        let group: Vec<&&ContextItem> = items.iter().filter(|item| item.rank == rank).collect();
+        let texts = group.iter().map(|item| item.text.as_str());
+        match &group[0].title {
+            None => sections.push(texts.collect::<Vec<_>>().join("\n\n")),
+            Some(title) => sections.push(format_section(title, texts)),
+        }
+    }
+
+    sections.join("\n\n---\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_items_bullets() {
+        let content = "# Guardrails\n\n- Never force push\n- Always run tests\n";
+        assert_eq!(
+            split_items(content),
+            vec!["Never force push", "Always run tests"]
+        );
+    }
+
+    #[test]
+    fn test_split_items_continuation_lines() {
+        let content = "- First item\n  continues here\n\n- Second";
+        assert_eq!(
+            split_items(content),
+            vec!["First item continues here", "Second"]
+        );
+    }
+
+    #[test]
+    fn test_split_items_freeform() {
+        assert_eq!(split_items("Just prose."), vec!["Just prose."]);
+        assert!(split_items("  \n").is_empty());
+    }
+
+    fn section(name: &str, content: &str, user_items: &[&str]) -> KnowledgeSection {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let categories = category::from_config(ws, &Default::default());
+        KnowledgeSection {
+            category: category::find(&categories, name).unwrap().clone(),
+            content: content.to_string(),
+            user_items: user_items.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_combine_context_layers_user_items_last() {
+        let working_set = WorkingSet {
+            sections: vec![
+                section("guardrails", "# Guardrails\n\n- Never force push\n", &[]),
+                section("metis", "", &["Prefers small commits"]),
+            ],
+            ..WorkingSet::default()
+        };
+        let combined = combine_context(&working_set);
+        assert_eq!(
+            combined,
+            "# Guardrails\n\n- Never force push\n\n---\n\n\
+             # User Metis (project items take precedence on conflict)\n\n- Prefers small commits"
+        );
+    }
+
+    #[test]
+    fn test_project_items_override_user_items() {
+        let project = vec![
+            "Indent with tabs in this repo".to_string(),
+            "Never force push".to_string(),
+        ];
+        let user = vec![
+            "Never indent with tabs".to_string(),
+            "never force push.".to_string(),
+            "Prefers small commits".to_string(),
+        ];
+        assert_eq!(
+            without_overridden(user, &project),
+            vec!["Prefers small commits".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_filter_scoped_drops_items_outside_scope() {
+        let content = "# Metis\n\n- Prefer small commits\n- Use sqlx macros [scope: backend/]\n";
+        let frontend = ScopeContext {
+            paths: vec!["frontend/app.ts".to_string()],
+            ..Default::default()
+        };
+        let backend = ScopeContext {
+            paths: vec!["backend/src/db.rs".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter_scoped(content, "Metis", &frontend),
+            "# Metis\n\n- Prefer small commits"
+        );
+        assert_eq!(filter_scoped(content, "Metis", &backend), content);
+    }
+
+    #[test]
+    fn test_rank_by_confidence_orders_and_drops() {
+        use crate::confidence::{ItemRecord, Store};
+        use crate::types::{ConfidenceConfig, content_hash};
+        use chrono::{Duration, Utc};
+
+        let now = Utc::now();
+        let record = |text: &str, sessions: usize, days_ago: i64| {
+            let key = content_hash(&normalize_item(text));
+            let record = ItemRecord {
+                text: text.to_string(),
+                category: "metis".to_string(),
+                sessions: (0..sessions).map(|i| i.to_string()).collect(),
+                first_seen: now - Duration::days(days_ago),
+                last_reinforced: now - Duration::days(days_ago),
+            };
+            (key, record)
+        };
+        let store = Store {
+            items: [
+                record("Once, long ago", 1, 365),
+                record("Once, recently", 1, 1),
+                record("Often", 4, 1),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let scorer = Scorer::new(store, ConfidenceConfig::default(), now);
+        let metis = section("metis", "", &[]).category;
+
+        let content = "# Metis\n\n- Once, recently\n- Once, long ago\n- Often\n- Added by hand\n";
+        assert_eq!(
+            rank_by_confidence(content, &metis, &scorer),
+            "# Metis\n\n- Added by hand\n- Often\n- Once, recently"
+        );
+        let ordered = "# Metis\n\n- Often\n";
+        assert_eq!(rank_by_confidence(ordered, &metis, &scorer), ordered);
+    }
+
+    #[test]
+    fn test_render_items_groups_by_section() {
+        let dive = ContextItem {
+            rank: 0,
+            title: None,
+            text: "# Dive: auth".to_string(),
+        };
+        let metis = ContextItem {
+            rank: 2,
+            title: Some("Metis".to_string()),
+            text: "Prefer small commits".to_string(),
+        };
+        let rendered = render_items(&[&metis, &dive]);
+        assert_eq!(
+            rendered,
+            "# Dive: auth\n\n---\n\n# Metis\n\n- Prefer small commits"
+        );
+    }
+}
@@ -6,16 +6,20 @@
 //! - Abstracting specific instances into general principles
 //! - Preserving critical constraints and preferences
 
+use crate::history;
+use crate::llm;
+use crate::lock;
+use crate::prompts;
 use crate::state;
-use std::process::{Command, Stdio};
+use crate::workspace::Workspace;
 
 /// Run wm compress
-pub fn run() -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn run(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
-    let state_path = state::wm_path("state.md");
+    let state_path = state::wm_path(ws, "state.md");
     let current_state = std::fs::read_to_string(&state_path)
         .map_err(|e| format!("Failed to read state.md: {}", e))?;
 
@@ -29,6 +33,7 @@ pub fn run() -> Result<(), String> {
     let char_count = current_state.len();
 
     state::log(
+        ws,
         "compress",
         &format!(
             "Starting compression of state.md ({} lines, {} chars)",
@@ -39,29 +44,29 @@ pub fn run() -> Result<(), String> {
     println!("Compressing state.md ({} lines)...", line_count);
 
     // Call LLM to compress
-    let compressed = call_compression(&current_state)?;
+    let compressed = call_compression(ws, &current_state)?;
 
     if compressed.was_compressed {
-        // Backup old state before overwriting
-        let backup_path = state::wm_path("state.md.backup");
-        std::fs::write(&backup_path, &current_state)
-            .map_err(|e| format!("Failed to write backup: {}", e))?;
-
-        // Write compressed state with atomic rename
-        let tmp_path = state::wm_path("state.md.tmp");
-        std::fs::write(&tmp_path, &compressed.content)
-            .map_err(|e| format!("Failed to write temp file: {}", e))?;
-        std::fs::rename(&tmp_path, &state_path)
-            .map_err(|e| format!("Failed to rename state file: {}", e))?;
+        // Write compressed state; the previous version stays in .wm/history/
+        // An extraction that finished while we were compressing wins - rerun later.
+        let _lock =
+            lock::acquire(&state_path).map_err(|e| format!("Failed to lock state.md: {}", e))?;
+        let latest = std::fs::read_to_string(&state_path).unwrap_or_default();
+        if latest != current_state {
+            return Err(
+                "state.md changed while compressing (a session extracted meanwhile). Run 'wm compress' again."
+                    .to_string(),
+            );
+        }
+        history::write_tracked(ws, "state.md", &compressed.content, "compress")?;
 
         let new_line_count = compressed.content.lines().count();
-        let reduction = if line_count > 0 {
-            100 - (new_line_count * 100 / line_count)
-        } else {
-            0
-        };
+        let reduction = (new_line_count * 100)
+            .checked_div(line_count)
+            .map_or(0, |pct| 100usize.saturating_sub(pct));
 
         state::log(
+            ws,
             "compress",
             &format!(
                 "Compressed {} → {} lines ({}% reduction)",
@@ -72,9 +77,10 @@ pub fn run() -> Result<(), String> {
             "Compressed: {} → {} lines ({}% reduction)",
             line_count, new_line_count, reduction
         );
-        println!("Backup saved to .wm/state.md.backup");
+        println!("Previous version kept in history (see 'wm history state.md')");
     } else {
         state::log(
+            ws,
             "compress",
             "No compression possible - state already concise",
         );
@@ -89,47 +95,8 @@ struct CompressionResult {
     content: String,
 }
 
-fn call_compression(current_state: &str) -> Result<CompressionResult, String> {
-    // Prevent recursion
-    unsafe { std::env::set_var("WM_DISABLED", "1") };
-
-    // AIDEV-NOTE: The compression prompt focuses on synthesis and abstraction,
-    // not just deduplication. It references the same tacit knowledge criteria
-    // from extract to ensure we preserve the right things.
-    let system_prompt = r#"You are compressing accumulated tacit knowledge into a more concise form.
-
-TACIT KNOWLEDGE REMINDER (what we're preserving):
-- Rationale behind decisions (WHY this approach)
-- Paths rejected and why (judgment in pruning)
-- Constraints discovered through friction
-- Preferences revealed by corrections
-- Patterns followed without stating
-
-COMPRESSION STRATEGIES:
-1. MERGE related items into broader principles
-   - "Prefers X in context A" + "Prefers X in context B" → "Generally prefers X"
-
-2. ABSTRACT specific instances into general patterns
-   - Multiple specific file/function mentions → General architectural preference
-
-3. REMOVE obsolete items
-   - Superseded by later, more refined understanding
-   - No longer relevant to current codebase state
-   - Too specific to be useful in new contexts
-
-4. PRESERVE critical items
-   - Hard constraints that caused friction when violated
-   - Strong preferences that were corrected multiple times
-   - Architectural decisions with clear rationale
-
-5. CONSOLIDATE structure
-   - Group related items under clear headings
-   - Remove redundant phrasing
-   - Keep bullet points concise
-
-THE GOAL: A new Claude session 6 months from now should get the essential wisdom in fewer words. Compress aggressively but preserve meaning.
-
-RESPONSE FORMAT:
+/// Output instructions appended to the compress prompt
+const COMPRESS_FORMAT: &str = r#"RESPONSE FORMAT:
 
 If compression was possible, respond:
 WAS_COMPRESSED: YES
@@ -139,50 +106,22 @@ WAS_COMPRESSED: YES
 If the state is already concise and no meaningful compression is possible, respond:
 WAS_COMPRESSED: NO"#;
 
+fn call_compression(ws: &Workspace, current_state: &str) -> Result<CompressionResult, String> {
+    // AIDEV-NOTE: The compression prompt focuses on synthesis and abstraction,
+    // not just deduplication. It references the same tacit knowledge criteria
+    // from extract to ensure we preserve the right things.
+    let system_prompt = format!("{}\n{}", prompts::load(ws, "compress"), COMPRESS_FORMAT);
+
     let message = format!("CURRENT STATE TO COMPRESS:\n\n{}\n\nOUTPUT:", current_state);
 
     state::log(
+        ws,
         "compress",
         &format!("Sending {} chars to LLM", message.len()),
     );
 
-    let mut cmd = Command::new("claude");
-    cmd.arg("-p")
-        .arg("--output-format")
-        .arg("json")
-        .arg("--no-session-persistence")
-        .arg("--system-prompt")
-        .arg(system_prompt)
-        .arg(&message)
-        .env("SUPEREGO_DISABLED", "1")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdin(Stdio::null());
-
-    let child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn claude CLI: {}", e))?;
-
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for claude CLI: {}", e))?;
-
-    // Re-enable WM
-    unsafe { std::env::remove_var("WM_DISABLED") };
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        return Err(format!(
-            "Claude CLI failed (exit {:?}):\nstderr: {}\nstdout: {}",
-            output.status.code(),
-            stderr,
-            stdout
-        ));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    parse_compression_response(&stdout)
+    let result_str = llm::call_claude(ws, "compress", None, None, &system_prompt, &message)?;
+    Ok(parse_compression_result(ws, &result_str))
 }
 
 /// Strip markdown prefixes (same as extract.rs)
@@ -190,7 +129,7 @@ fn strip_markdown_prefix(line: &str) -> &str {
     line.trim().trim_start_matches(['#', '>', '*']).trim()
 }
 
-fn parse_compression_result(result_str: &str) -> CompressionResult {
+fn parse_compression_result(ws: &Workspace, result_str: &str) -> CompressionResult {
     let lines: Vec<&str> = result_str.lines().collect();
 
     for (i, line) in lines.iter().enumerate() {
@@ -213,21 +152,9 @@ fn parse_compression_result(result_str: &str) -> CompressionResult {
     }
 
     // Fallback: no marker found
-    state::log("compress", "No WAS_COMPRESSED marker found in response");
+    state::log(ws, "compress", "No WAS_COMPRESSED marker found in response");
     CompressionResult {
         was_compressed: false,
         content: String::new(),
     }
 }
-
-fn parse_compression_response(response: &str) -> Result<CompressionResult, String> {
-    let cli_response: serde_json::Value = serde_json::from_str(response)
-        .map_err(|e| format!("Failed to parse Claude CLI response: {}", e))?;
-
-    let result_str = cli_response
-        .get("result")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "Claude CLI response missing 'result' field".to_string())?;
-
-    Ok(parse_compression_result(result_str))
-}
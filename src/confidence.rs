@@ -0,0 +1,354 @@
+//! Confidence scoring of distilled items
+//!
+//! An item re-derived by many sessions is worth more than one extracted once
+//! from a throwaway session, and knowledge nobody has confirmed in months is
+//! likely out of date. Each item's confidence combines both:
+//!
+//! ```text
+//! strength   = 1 - 0.5^sessions             (1 session: 0.5, 2: 0.75, 3: 0.875)
+//! confidence = strength * 0.5^(days since last reinforced / half_life_days)
+//! ```
+//!
+//! After Pass 2 writes the category files, `update` matches every item against
+//! the Pass 1 insights of each session (see `similar`) and records the sessions
+//! that support it in .wm/distill/confidence.json. Compile orders items by
+//! confidence and leaves out those below `confidence.inject_threshold`;
+//! `wm stale` lists those below `confidence.stale_threshold`.
+//!
+//! AIDEV-NOTE: Items without a record (added by hand, via MCP, or from
+//! .wm/shared/) have no history to judge, so they score 1.0 and are always
+//! injected. Categories with `decay = false` (guardrails by default) keep their
+//! strength regardless of age.
+
+use crate::category::{self, Category};
+use crate::compile;
+use crate::lock;
+use crate::scope;
+use crate::state;
+use crate::types::{ConfidenceConfig, content_hash};
+use crate::workspace::Workspace;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Confidence records within .wm/distill/
+pub const CONFIDENCE_FILE: &str = "confidence.json";
+
+/// Word overlap (Jaccard) at which two phrasings count as the same insight
+const SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// What is known about one distilled item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRecord {
+    /// Item as last written
+    pub text: String,
+
+    /// Category name it was last written to
+    pub category: String,
+
+    /// Sessions whose extraction re-derived the item
+    #[serde(default)]
+    pub sessions: Vec<String>,
+
+    pub first_seen: DateTime<Utc>,
+
+    /// Latest activity of a supporting session
+    pub last_reinforced: DateTime<Utc>,
+}
+
+impl ItemRecord {
+    /// Confidence at `now`, 0.0 to 1.0
+    pub fn confidence(&self, half_life_days: u32, decays: bool, now: DateTime<Utc>) -> f64 {
+        let strength = 1.0 - 0.5_f64.powi(self.sessions.len().max(1) as i32);
+        if !decays || half_life_days == 0 {
+            return strength;
+        }
+        let age_days = (now - self.last_reinforced).num_seconds().max(0) as f64 / 86_400.0;
+        strength * 0.5_f64.powf(age_days / f64::from(half_life_days))
+    }
+}
+
+/// All records, keyed by a hash of the normalized item text
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Store {
+    #[serde(default)]
+    pub items: BTreeMap<String, ItemRecord>,
+}
+
+impl Store {
+    /// Load .wm/distill/confidence.json (empty if missing or unreadable)
+    pub fn load(ws: &Workspace) -> Self {
+        std::fs::read_to_string(path(ws))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record for an item, if it has one
+    pub fn get(&self, item: &str) -> Option<&ItemRecord> {
+        self.items.get(&key(item))
+    }
+}
+
+fn path(ws: &Workspace) -> std::path::PathBuf {
+    state::wm_path(ws, "distill").join(CONFIDENCE_FILE)
+}
+
+fn key(item: &str) -> String {
+    content_hash(&compile::normalize_item(item))
+}
+
+/// Words of an item used for similarity (scope tag dropped, 3+ characters)
+fn words(item: &str) -> BTreeSet<String> {
+    let (text, _) = scope::parse_scopes(item);
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether two phrasings are the same insight (normalized equal, or enough shared words)
+/// AIDEV-NOTE: A deliberately cheap heuristic - Pass 2 rewords and merges Pass 1
+/// insights, so exact matching would almost never find support.
+pub fn similar(a: &str, b: &str) -> bool {
+    if compile::normalize_item(a) == compile::normalize_item(b) {
+        return true;
+    }
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    union > 0 && a.intersection(&b).count() as f64 / union as f64 >= SIMILARITY_THRESHOLD
+}
+
+/// One session's Pass 1 output, as support for Pass 2 items
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub session_id: String,
+
+    /// When the session was last active
+    pub time: DateTime<Utc>,
+
+    /// Pass 1 insights
+    pub insights: Vec<String>,
+}
+
+/// Record which sessions support each written item (category name, text)
+/// Records of items no longer in any category file are dropped.
+pub fn update(ws: &Workspace, items: &[(&str, &str)], sources: &[Source]) -> Result<(), String> {
+    let path = path(ws);
+    let _lock =
+        lock::acquire(&path).map_err(|e| format!("Failed to lock {}: {}", CONFIDENCE_FILE, e))?;
+    let mut store = Store::load(ws);
+    let now = Utc::now();
+
+    for (category, text) in items {
+        let supporters: Vec<&Source> = sources
+            .iter()
+            .filter(|source| source.insights.iter().any(|i| similar(i, text)))
+            .collect();
+        let earliest = supporters.iter().map(|s| s.time).min().unwrap_or(now);
+        let latest = supporters.iter().map(|s| s.time).max().unwrap_or(now);
+
+        let record = store.items.entry(key(text)).or_insert_with(|| ItemRecord {
+            text: text.to_string(),
+            category: category.to_string(),
+            sessions: Vec::new(),
+            first_seen: earliest,
+            last_reinforced: latest,
+        });
+        record.text = text.to_string();
+        record.category = category.to_string();
+        for supporter in &supporters {
+            if !record.sessions.contains(&supporter.session_id) {
+                record.sessions.push(supporter.session_id.clone());
+            }
+        }
+        record.first_seen = record.first_seen.min(earliest);
+        if !supporters.is_empty() {
+            record.last_reinforced = record.last_reinforced.max(latest);
+        }
+    }
+
+    let current: BTreeSet<String> = category::all(ws)
+        .iter()
+        .flat_map(|category| local_items(ws, category))
+        .map(|item| key(&item))
+        .collect();
+    store.items.retain(|key, _| current.contains(key));
+
+    let json = serde_json::to_string_pretty(&store)
+        .map_err(|e| format!("Failed to serialize {}: {}", CONFIDENCE_FILE, e))?;
+    state::write_atomic(&path, &json)
+        .map_err(|e| format!("Failed to write {}: {}", CONFIDENCE_FILE, e))
+}
+
+/// Items of a category's local distill file (shared items excluded)
+pub fn local_items(ws: &Workspace, category: &Category) -> Vec<String> {
+    let content = std::fs::read_to_string(state::wm_path(ws, "distill").join(&category.filename))
+        .unwrap_or_default();
+    compile::split_items(&content)
+}
+
+/// Scores items for compile and `wm stale`
+#[derive(Debug, Clone)]
+pub struct Scorer {
+    store: Store,
+    config: ConfidenceConfig,
+    now: DateTime<Utc>,
+}
+
+impl Scorer {
+    /// Scorer for the current project and config
+    pub fn load(ws: &Workspace) -> Self {
+        Scorer::new(
+            Store::load(ws),
+            state::read_config(ws).confidence,
+            Utc::now(),
+        )
+    }
+
+    pub fn new(store: Store, config: ConfidenceConfig, now: DateTime<Utc>) -> Self {
+        Scorer { store, config, now }
+    }
+
+    /// Confidence of an item (1.0 when it has no record)
+    pub fn score(&self, category: &Category, item: &str) -> f64 {
+        self.store.get(item).map_or(1.0, |record| {
+            record.confidence(self.config.half_life_days, category.decay, self.now)
+        })
+    }
+
+    /// Whether an item is confident enough to inject
+    pub fn injects(&self, category: &Category, item: &str) -> bool {
+        self.score(category, item) >= self.config.inject_threshold
+    }
+
+    pub fn record(&self, item: &str) -> Option<&ItemRecord> {
+        self.store.get(item)
+    }
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Run wm stale - list items whose confidence has fallen below the threshold
+pub fn run_stale(
+    ws: &Workspace,
+    below: Option<f64>,
+    category_name: Option<&str>,
+) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+    let config = state::read_config(ws).confidence;
+    let threshold = below.unwrap_or(config.stale_threshold);
+    let categories = category::all(ws);
+    let selected: Vec<&Category> = match category_name {
+        None => categories.iter().collect(),
+        Some(name) => vec![category::find(&categories, name).ok_or_else(|| {
+            format!(
+                "Unknown category: {}. Use one of: {}.",
+                name,
+                category::names(&categories)
+            )
+        })?],
+    };
+
+    let scorer = Scorer::load(ws);
+    let mut stale: Vec<(f64, &Category, String)> = selected
+        .into_iter()
+        .flat_map(|category| {
+            local_items(ws, category)
+                .into_iter()
+                .map(move |item| (category, item))
+        })
+        .filter(|(_, item)| scorer.record(item).is_some())
+        .map(|(category, item)| (scorer.score(category, &item), category, item))
+        .filter(|(score, _, _)| *score < threshold)
+        .collect();
+
+    if stale.is_empty() {
+        println!("No items below confidence {:.2}.", threshold);
+        return Ok(());
+    }
+
+    stale.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (score, category, item) in &stale {
+        let record = scorer.record(item);
+        let sessions = record.map_or(0, |r| r.sessions.len());
+        let last = record
+            .map(|r| r.last_reinforced.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let injected = if *score < config.inject_threshold {
+            " (not injected)"
+        } else {
+            ""
+        };
+        println!(
+            "{:.2}  {:<12} {} session(s), last {}{}\n      {}",
+            score, category.name, sessions, last, injected, item
+        );
+    }
+    println!(
+        "\n{} item(s) below confidence {:.2}. Sessions that re-derive them raise it again; edit or remove them in .wm/distill/ to prune.",
+        stale.len(),
+        threshold
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn record(sessions: usize, days_ago: i64, now: DateTime<Utc>) -> ItemRecord {
+        ItemRecord {
+            text: "Prefer pnpm".to_string(),
+            category: "metis".to_string(),
+            sessions: (0..sessions).map(|i| format!("s{}", i)).collect(),
+            first_seen: now - Duration::days(days_ago),
+            last_reinforced: now - Duration::days(days_ago),
+        }
+    }
+
+    #[test]
+    fn test_confidence_grows_with_sessions_and_decays() {
+        let now = Utc::now();
+        assert_eq!(record(1, 0, now).confidence(90, true, now), 0.5);
+        assert_eq!(record(2, 0, now).confidence(90, true, now), 0.75);
+        assert!((record(1, 90, now).confidence(90, true, now) - 0.25).abs() < 1e-9);
+        assert_eq!(record(1, 90, now).confidence(90, false, now), 0.5);
+        assert_eq!(record(1, 90, now).confidence(0, true, now), 0.5);
+    }
+
+    #[test]
+    fn test_similar_phrasings() {
+        assert!(similar(
+            "Run migrations through sqlx-cli, never by hand",
+            "Never run migrations by hand; use sqlx-cli [scope: backend/]"
+        ));
+        assert!(similar("Use pnpm.", "use pnpm"));
+        assert!(!similar(
+            "Run migrations through sqlx-cli",
+            "Prefer small, focused commits"
+        ));
+    }
+
+    #[test]
+    fn test_scorer_defaults_unrecorded_items_to_full_confidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let now = Utc::now();
+        let mut store = Store::default();
+        store.items.insert(key("Prefer pnpm"), record(1, 200, now));
+        let scorer = Scorer::new(store, ConfidenceConfig::default(), now);
+        let categories = category::from_config(ws, &Default::default());
+        let metis = category::find(&categories, "metis").unwrap();
+        let guardrails = category::find(&categories, "guardrails").unwrap();
+
+        assert!(!scorer.injects(metis, "Prefer pnpm"));
+        assert!(scorer.injects(guardrails, "Prefer pnpm"));
+        assert_eq!(scorer.score(metis, "Added by hand"), 1.0);
+    }
+}
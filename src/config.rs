@@ -0,0 +1,764 @@
+//! Layered configuration and `wm config`
+//!
+//! The effective config is built from these layers, lowest precedence first:
+//!
+//! 1. built-in defaults
+//! 2. user-global `~/.config/wm/config.toml` (see `state::global_dir`)
+//! 3. project `.wm/config.toml`
+//! 4. environment variables `WM_<SECTION>_<KEY>`, e.g. `WM_OPERATIONS_EXTRACT=false`
+//!
+//! AIDEV-NOTE: Writes only touch keys that actually changed, so a project file
+//! holds just its overrides and global settings keep showing through. `version`
+//! is the .wm/ layout version: it is read from the project file only and can't
+//! be set with `wm config`.
+
+use crate::lock;
+use crate::state;
+use crate::types::Config;
+use crate::workspace::Workspace;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+use toml::{Table, Value};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Keys managed by wm itself rather than by users
+const INTERNAL_KEYS: &[&str] = &["version"];
+
+/// Sections whose entries are named by the user (`[categories.<name>]`)
+/// AIDEV-NOTE: Entries of these sections are checked against the first default
+/// entry's schema, so `categories.glossary.priority` is as valid as
+/// `categories.guardrails.priority`.
+const NAMED_SECTIONS: &[&str] = &["categories"];
+
+/// Where a config value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    Env(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::Global(path) | Origin::Project(path) => write!(f, "file:{}", path.display()),
+            Origin::Env(var) => write!(f, "env:{}", var),
+        }
+    }
+}
+
+/// One config source and the keys it sets
+#[derive(Clone)]
+struct Layer {
+    origin: Origin,
+    table: Table,
+}
+
+/// Path of the project config file
+pub fn project_path(ws: &Workspace) -> PathBuf {
+    state::wm_path(ws, CONFIG_FILE)
+}
+
+/// Path of the user-global config file
+pub fn global_path() -> Option<PathBuf> {
+    state::global_path(CONFIG_FILE)
+}
+
+// ============================================================================
+// Loading
+// ============================================================================
+
+/// Load the effective config from all layers
+/// AIDEV-NOTE: Every state helper reads config, so one command loads it many
+/// times. Results are cached per process, keyed on both files' modification
+/// time and size (edits by other processes are still picked up); writes made
+/// through this module drop the cache. The environment layer is read once per
+/// process, as wm never sets WM_<key> variables itself.
+pub fn load(ws: &Workspace) -> Result<Config, String> {
+    let path = project_path(ws);
+    let stamps = [
+        file_stamp(global_path().as_deref()),
+        file_stamp(Some(&path)),
+    ];
+    if let Some((seen, config)) = loaded().get(&path)
+        && *seen == stamps
+    {
+        return Ok(config.clone());
+    }
+
+    let mut merged = Table::new();
+    for layer in layers(ws)? {
+        merge(&mut merged, &layer.table);
+    }
+    let config: Config = Value::Table(merged)
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid config: {}", e.message().trim()))?;
+    loaded().insert(path, (stamps, config.clone()));
+    Ok(config)
+}
+
+/// Modification time and size of a config file (None if missing)
+type Stamp = Option<(SystemTime, u64)>;
+
+fn file_stamp(path: Option<&Path>) -> Stamp {
+    let metadata = fs::metadata(path?).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Configs loaded by this process, by project config path, with the
+/// (global, project) file stamps they were built from
+type Loaded = HashMap<PathBuf, ([Stamp; 2], Config)>;
+
+fn loaded() -> MutexGuard<'static, Loaded> {
+    static LOADED: LazyLock<Mutex<Loaded>> = LazyLock::new(Mutex::default);
+    LOADED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// All layers, lowest precedence first
+fn layers(ws: &Workspace) -> Result<Vec<Layer>, String> {
+    let mut layers = vec![Layer {
+        origin: Origin::Default,
+        table: defaults(),
+    }];
+
+    if let Some(path) = global_path()
+        && let Some(mut table) = read_table(&path, &path.display().to_string())?
+    {
+        for key in INTERNAL_KEYS {
+            table.remove(*key);
+        }
+        layers.push(Layer {
+            origin: Origin::Global(path),
+            table,
+        });
+    }
+
+    let path = project_path(ws);
+    if let Some(table) = read_table(&path, CONFIG_FILE)? {
+        layers.push(Layer {
+            origin: Origin::Project(path),
+            table,
+        });
+    }
+
+    layers.extend(env_layers()?.iter().cloned());
+    Ok(layers)
+}
+
+/// One layer per WM_<key> environment variable, read once per process
+fn env_layers() -> Result<&'static [Layer], String> {
+    static ENV_LAYERS: LazyLock<Result<Vec<Layer>, String>> = LazyLock::new(|| {
+        let mut layers = Vec::new();
+        for key in keys() {
+            let var = env_var(&key);
+            if let Ok(raw) = std::env::var(&var)
+                && !raw.is_empty()
+            {
+                let value = parse_value(&key, &raw).map_err(|e| format!("{}: {}", var, e))?;
+                let mut table = Table::new();
+                set_path(&mut table, &key, value);
+                layers.push(Layer {
+                    origin: Origin::Env(var),
+                    table,
+                });
+            }
+        }
+        Ok(layers)
+    });
+    ENV_LAYERS.as_deref().map_err(Clone::clone)
+}
+
+/// Read and validate a config file (None if it doesn't exist)
+fn read_table(path: &Path, label: &str) -> Result<Option<Table>, String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    parse_str(&content, label)?;
+    content
+        .parse::<Table>()
+        .map(Some)
+        .map_err(|e| format!("{}: {}", label, e.message().trim()))
+}
+
+/// Parse config content; errors name the file, line and column
+pub fn parse_str(content: &str, label: &str) -> Result<Config, String> {
+    toml::from_str(content).map_err(|e| {
+        let message = e.message().trim().replace('\n', "; ");
+        match e.span() {
+            Some(span) => {
+                let before = &content[..span.start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                format!("{} line {}, column {}: {}", label, line, column, message)
+            }
+            None => format!("{}: {}", label, message),
+        }
+    })
+}
+
+/// Built-in defaults as a table
+fn defaults() -> Table {
+    to_table(&Config::default())
+}
+
+/// Every key with a sample value of its type (optional fields included)
+fn schema() -> &'static Table {
+    static SCHEMA: LazyLock<Table> = LazyLock::new(|| {
+        let mut sample = Config::default();
+        sample.dive.current = Some(String::new());
+        sample.operations.extract_paused_until = Some(Utc::now());
+        sample.operations.compile_paused_until = Some(Utc::now());
+        to_table(&sample)
+    });
+    &SCHEMA
+}
+
+fn to_table(config: &Config) -> Table {
+    match Value::try_from(config) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+/// Settable keys in dotted form (e.g. operations.extract), in schema order
+pub fn keys() -> Vec<String> {
+    static KEYS: LazyLock<Vec<String>> = LazyLock::new(|| {
+        let mut keys = Vec::new();
+        collect_leaves(schema(), "", &mut |key, _| keys.push(key));
+        keys.retain(|key| !INTERNAL_KEYS.contains(&key.as_str()));
+        keys
+    });
+    KEYS.clone()
+}
+
+/// The schema key describing `key`: entries of named sections map to the
+/// first default entry (categories.glossary.priority -> categories.guardrails.priority)
+fn schema_key(key: &str) -> String {
+    let parts: Vec<&str> = key.splitn(3, '.').collect();
+    if let [section, _, field] = parts[..]
+        && NAMED_SECTIONS.contains(&section)
+        && let Some(Value::Table(entries)) = schema().get(section)
+        && let Some(sample) = entries.keys().next()
+    {
+        return format!("{}.{}.{}", section, sample, field);
+    }
+    key.to_string()
+}
+
+/// Environment variable overriding a key (operations.extract -> WM_OPERATIONS_EXTRACT)
+fn env_var(key: &str) -> String {
+    format!("WM_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// Problems with the config files and environment: parse errors, bad values, unknown keys
+pub fn problems(ws: &Workspace) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut files = vec![(project_path(ws), CONFIG_FILE.to_string())];
+    if let Some(path) = global_path() {
+        let label = path.display().to_string();
+        files.push((path, label));
+    }
+
+    for (path, label) in files {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Err(e) = parse_str(&content, &label) {
+            problems.push(format!("{} (extract is treated as paused until fixed)", e));
+            continue;
+        }
+        for key in unknown_keys(&content) {
+            problems.push(format!("{}: unknown key '{}' (ignored)", label, key));
+        }
+    }
+
+    for key in keys() {
+        let var = env_var(&key);
+        if let Ok(raw) = std::env::var(&var)
+            && !raw.is_empty()
+            && let Err(e) = parse_value(&key, &raw)
+        {
+            problems.push(format!(
+                "{}: {} (extract is treated as paused until fixed)",
+                var, e
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Dotted paths of keys in `content` that Config doesn't define
+fn unknown_keys(content: &str) -> Vec<String> {
+    let Ok(actual) = content.parse::<Table>() else {
+        return Vec::new();
+    };
+    let mut unknown = Vec::new();
+    collect_unknown(&actual, schema(), "", &mut unknown);
+    unknown
+}
+
+fn collect_unknown(actual: &Table, known: &Table, prefix: &str, unknown: &mut Vec<String>) {
+    for (key, value) in actual {
+        let path = format!("{}{}", prefix, key);
+        match (value, known.get(key)) {
+            (_, None) => unknown.push(path),
+            (Value::Table(entries), Some(Value::Table(known)))
+                if NAMED_SECTIONS.contains(&path.as_str()) =>
+            {
+                let Some(Value::Table(sample)) = known.values().next() else {
+                    continue;
+                };
+                for (name, entry) in entries {
+                    let entry_path = format!("{}.{}", path, name);
+                    match entry {
+                        Value::Table(entry) => {
+                            collect_unknown(entry, sample, &format!("{}.", entry_path), unknown)
+                        }
+                        _ => unknown.push(entry_path),
+                    }
+                }
+            }
+            (Value::Table(actual), Some(Value::Table(known))) => {
+                collect_unknown(actual, known, &format!("{}.", path), unknown)
+            }
+            _ => {}
+        }
+    }
+}
+
+// ============================================================================
+// Table helpers
+// ============================================================================
+
+/// Deep-merge `overlay` into `base`
+fn merge(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Visit every non-table value with its dotted key
+fn collect_leaves(table: &Table, prefix: &str, visit: &mut dyn FnMut(String, &Value)) {
+    for (key, value) in table {
+        let path = format!("{}{}", prefix, key);
+        match value {
+            Value::Table(inner) => collect_leaves(inner, &format!("{}.", path), visit),
+            _ => visit(path, value),
+        }
+    }
+}
+
+fn get_path<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (section, rest) = match key.split_once('.') {
+        Some((section, rest)) => (section, Some(rest)),
+        None => (key, None),
+    };
+    match (table.get(section)?, rest) {
+        (value, None) => Some(value),
+        (Value::Table(inner), Some(rest)) => get_path(inner, rest),
+        _ => None,
+    }
+}
+
+fn set_path(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        None => {
+            table.insert(key.to_string(), value);
+        }
+        Some((section, rest)) => {
+            let inner = table
+                .entry(section)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !inner.is_table() {
+                *inner = Value::Table(Table::new());
+            }
+            if let Value::Table(inner) = inner {
+                set_path(inner, rest, value);
+            }
+        }
+    }
+}
+
+/// Remove a key, dropping sections left empty; returns whether it was set
+fn remove_path(table: &mut Table, key: &str) -> bool {
+    match key.split_once('.') {
+        None => table.remove(key).is_some(),
+        Some((section, rest)) => {
+            let Some(Value::Table(inner)) = table.get_mut(section) else {
+                return false;
+            };
+            let removed = remove_path(inner, rest);
+            if inner.is_empty() {
+                table.remove(section);
+            }
+            removed
+        }
+    }
+}
+
+/// Parse a command-line or environment value for `key` using the key's type
+pub fn parse_value(key: &str, raw: &str) -> Result<Value, String> {
+    let sample = get_path(schema(), &schema_key(key))
+        .filter(|_| !INTERNAL_KEYS.contains(&key))
+        .cloned()
+        .ok_or_else(|| format!("Unknown config key '{}'. See 'wm config list'.", key))?;
+    let raw = raw.trim();
+
+    let value = match sample {
+        Value::Boolean(_) => match raw.to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Value::Boolean(true),
+            "false" | "no" | "off" | "0" => Value::Boolean(false),
+            _ => return Err(format!("{} must be true or false, got '{}'", key, raw)),
+        },
+        Value::Integer(_) => raw
+            .parse::<u32>()
+            .map(|n| Value::Integer(n.into()))
+            .map_err(|_| format!("{} must be a non-negative integer, got '{}'", key, raw))?,
+        Value::Float(_) => raw
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .map(Value::Float)
+            .ok_or_else(|| format!("{} must be a non-negative number, got '{}'", key, raw))?,
+        // Lists are given comma-separated: "psql *, vault *"
+        Value::Array(_) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        _ => Value::String(raw.to_string()),
+    };
+    Ok(value)
+}
+
+// ============================================================================
+// Writing
+// ============================================================================
+
+/// Read-modify-write a config file's table under its lock
+/// Refuses to touch a file that doesn't parse, and validates the result.
+fn update_file(
+    path: &Path,
+    label: &str,
+    change: impl FnOnce(&mut Table) -> Result<(), String>,
+) -> Result<(), String> {
+    let _lock =
+        lock::acquire(path).map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+    let mut table = read_table(path, label)?.unwrap_or_default();
+    change(&mut table)?;
+
+    let content = toml::to_string_pretty(&table)
+        .map_err(|e| format!("Failed to serialize {}: {}", label, e))?;
+    parse_str(&content, label)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let written = state::write_atomic(path, &content)
+        .map_err(|e| format!("Failed to write {}: {}", label, e));
+    loaded().clear();
+    written
+}
+
+/// Apply a change to the project config, writing only the keys it changed
+pub fn update_project(ws: &Workspace, change: impl FnOnce(&mut Config)) -> Result<Config, String> {
+    let mut updated = None;
+    update_file(&project_path(ws), CONFIG_FILE, |table| {
+        let before: Config = Value::Table(table.clone())
+            .try_into()
+            .map_err(|e: toml::de::Error| e.message().trim().to_string())?;
+        let mut after = before.clone();
+        change(&mut after);
+
+        let (old, new) = (to_table(&before), to_table(&after));
+        let mut changed = Vec::new();
+        collect_leaves(&old, "", &mut |key, value| {
+            if get_path(&new, &key) != Some(value) {
+                changed.push(key);
+            }
+        });
+        collect_leaves(&new, "", &mut |key, value| {
+            if get_path(&old, &key) != Some(value) && !changed.contains(&key) {
+                changed.push(key);
+            }
+        });
+
+        for key in changed {
+            match get_path(&new, &key) {
+                Some(value) => set_path(table, &key, value.clone()),
+                None => {
+                    remove_path(table, &key);
+                }
+            }
+        }
+        updated = Some(after);
+        Ok(())
+    })?;
+    Ok(updated.unwrap_or_default())
+}
+
+/// Config file written by `wm config set/unset` (project unless global)
+fn target(ws: &Workspace, global: bool) -> Result<(PathBuf, String), String> {
+    if global {
+        let path = global_path().ok_or("Could not determine the user config directory")?;
+        let label = path.display().to_string();
+        Ok((path, label))
+    } else {
+        if !state::is_initialized(ws) {
+            return Err("Not initialized. Run 'wm init' first (or use --global).".to_string());
+        }
+        Ok((project_path(ws), CONFIG_FILE.to_string()))
+    }
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Effective value of a key and the layer it came from (None if unset)
+pub fn resolve(ws: &Workspace, key: &str) -> Result<Option<(Value, Origin)>, String> {
+    ensure_key(key)?;
+    Ok(layers(ws)?
+        .into_iter()
+        .rev()
+        .find_map(|layer| get_path(&layer.table, key).map(|value| (value.clone(), layer.origin))))
+}
+
+/// Check that a key can be read or written with wm config
+fn ensure_key(key: &str) -> Result<(), String> {
+    if keys().contains(&schema_key(key)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown config key '{}'. See 'wm config list'.",
+            key
+        ))
+    }
+}
+
+/// Render a value for display (strings unquoted)
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Run wm config get <key>
+pub fn run_get(ws: &Workspace, key: &str) -> Result<(), String> {
+    match resolve(ws, key)? {
+        Some((value, _)) => {
+            println!("{}", display(&value));
+            Ok(())
+        }
+        None => Err(format!("{} is not set", key)),
+    }
+}
+
+/// Run wm config set <key> <value>
+pub fn run_set(ws: &Workspace, key: &str, raw: &str, global: bool) -> Result<(), String> {
+    let value = parse_value(key, raw)?;
+    let (path, label) = target(ws, global)?;
+    update_file(&path, &label, |table| {
+        set_path(table, key, value.clone());
+        Ok(())
+    })?;
+
+    println!("{} = {} ({})", key, display(&value), label);
+    warn_if_shadowed(ws, key, &path);
+    Ok(())
+}
+
+/// Run wm config unset <key>
+pub fn run_unset(ws: &Workspace, key: &str, global: bool) -> Result<(), String> {
+    ensure_key(key)?;
+    let (path, label) = target(ws, global)?;
+    let mut removed = false;
+    update_file(&path, &label, |table| {
+        removed = remove_path(table, key);
+        Ok(())
+    })?;
+
+    if removed {
+        println!("Unset {} in {}", key, label);
+    } else {
+        println!("{} was not set in {}", key, label);
+    }
+    warn_if_shadowed(ws, key, &path);
+    Ok(())
+}
+
+/// Point out when a higher layer still overrides what was just written
+fn warn_if_shadowed(ws: &Workspace, key: &str, written: &Path) {
+    if let Ok(Some((_, origin))) = resolve(ws, key) {
+        let shadowed = match &origin {
+            Origin::Env(_) => true,
+            Origin::Project(path) => path != written,
+            _ => false,
+        };
+        if shadowed {
+            eprintln!("Note: {} is still overridden by {}", key, origin);
+        }
+    }
+}
+
+/// Run wm config list [--show-origin]
+pub fn run_list(ws: &Workspace, show_origin: bool) -> Result<(), String> {
+    let layers = layers(ws)?;
+    // Named entries defined in files (e.g. a custom category) besides the defaults
+    let mut listed = keys();
+    for layer in &layers {
+        collect_leaves(&layer.table, "", &mut |key, _| {
+            let named = NAMED_SECTIONS
+                .iter()
+                .any(|section| key.starts_with(&format!("{}.", section)));
+            if named && !listed.contains(&key) {
+                listed.push(key);
+            }
+        });
+    }
+
+    for key in listed {
+        let Some((value, origin)) = layers
+            .iter()
+            .rev()
+            .find_map(|layer| get_path(&layer.table, &key).map(|value| (value, &layer.origin)))
+        else {
+            continue;
+        };
+
+        if show_origin {
+            println!("{:<40} {} = {}", origin.to_string(), key, value);
+        } else {
+            println!("{} = {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_str_reports_position() {
+        let err = parse_str("[operations]\nextract = nope\n", CONFIG_FILE).unwrap_err();
+        assert!(err.starts_with("config.toml line 2, column 11:"), "{}", err);
+        assert!(parse_str("", CONFIG_FILE).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let content =
+            "version = 1\ntypo = 1\n[operations]\nextrct = false\n[dive]\ncurrent = \"x\"\n";
+        assert_eq!(
+            unknown_keys(content),
+            vec!["operations.extrct".to_string(), "typo".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_named_section_entries() {
+        let content = "[categories.glossary]\ndescription = \"Terms\"\nprioity = 5\n";
+        assert_eq!(
+            unknown_keys(content),
+            vec!["categories.glossary.prioity".to_string()]
+        );
+        assert_eq!(
+            parse_value("categories.glossary.priority", "5"),
+            Ok(Value::Integer(5))
+        );
+        assert!(parse_value("categories.glossary.always", "maybe").is_err());
+        assert!(parse_value("categories.glossary.nope", "x").is_err());
+    }
+
+    #[test]
+    fn test_parse_value_uses_key_type() {
+        assert_eq!(
+            parse_value("operations.extract", "off"),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(
+            parse_value("history.retention", "10"),
+            Ok(Value::Integer(10))
+        );
+        assert!(parse_value("history.retention", "-1").is_err());
+        assert_eq!(
+            parse_value("confidence.inject_threshold", "0.5"),
+            Ok(Value::Float(0.5))
+        );
+        assert!(parse_value("confidence.inject_threshold", "high").is_err());
+        assert!(parse_value("operations.extract", "maybe").is_err());
+        assert!(parse_value("version", "2").is_err());
+        assert!(parse_value("nope", "x").is_err());
+    }
+
+    #[test]
+    fn test_merge_and_remove_path() {
+        let mut base: Table = "[operations]\nextract = true\ncompile = true\n"
+            .parse()
+            .unwrap();
+        let overlay: Table = "[operations]\nextract = false\n".parse().unwrap();
+        merge(&mut base, &overlay);
+        assert_eq!(
+            get_path(&base, "operations.extract"),
+            Some(&Value::Boolean(false))
+        );
+        assert_eq!(
+            get_path(&base, "operations.compile"),
+            Some(&Value::Boolean(true))
+        );
+
+        let mut table = overlay;
+        assert!(remove_path(&mut table, "operations.extract"));
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_update_project_writes_only_changed_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        fs::create_dir(ws.wm_dir()).unwrap();
+        fs::write(project_path(ws), "version = 1\n").unwrap();
+
+        update_project(ws, |config| config.history.retention = 7).unwrap();
+        let written: Table = fs::read_to_string(project_path(ws))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let expected: Table = "version = 1\n[history]\nretention = 7\n".parse().unwrap();
+        assert_eq!(written, expected);
+        assert_eq!(load(ws).unwrap().history.retention, 7);
+    }
+
+    #[test]
+    fn test_load_sees_changes_from_other_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        fs::create_dir(ws.wm_dir()).unwrap();
+
+        fs::write(project_path(ws), "[dive]\ncurrent = \"a\"\n").unwrap();
+        assert_eq!(load(ws).unwrap().dive.current.as_deref(), Some("a"));
+        assert_eq!(load(ws).unwrap().dive.current.as_deref(), Some("a"));
+
+        fs::write(project_path(ws), "[dive]\ncurrent = \"other\"\n").unwrap();
+        assert_eq!(load(ws).unwrap().dive.current.as_deref(), Some("other"));
+    }
+}
@@ -4,27 +4,79 @@
 //! Processes all sessions in ~/.claude/projects/<project-id>/ and extracts
 //! tacit knowledge in two passes:
 //! - Pass 1: Extract knowledge from each session (yz-fsws)
-//! - Pass 2: Categorize into guardrails vs metis (yz-u164)
+//! - Pass 2: Categorize into the configured categories, guardrails vs metis
+//!   by default (yz-u164; see category.rs)
 //!
 //! AIDEV-NOTE: Pass 1 processes all sessions and accumulates raw extractions.
 //! Each session's extraction is cached to support incremental runs (--force overrides).
 //! The raw extractions are written to .wm/distill/raw_extractions.md for Pass 2.
 
+use crate::category::{self, Category};
 use crate::codex;
-use crate::llm;
+use crate::compile;
+use crate::confidence;
+use crate::error::Error;
+use crate::evidence::{self, Evidence};
+use crate::history;
+use crate::ignore::IgnoreRules;
+use crate::insight::{self, CategorizationOutput, ExtractionOutput, Insight};
+use crate::lint;
+use crate::llm::{self, ErrorKind, LlmError};
+use crate::lock::{self, FileLock};
 use crate::oh;
+use crate::prompts;
 use crate::session;
 use crate::state;
-use crate::transcript::{format_context, read_transcript};
+use crate::transcript::{format_context, ignored_tool_ids, read_transcript, session_facts};
 use crate::types::{CodexSessionInfo, SessionInfo, SessionLike};
+use crate::usage::Budget;
+use crate::workspace::Workspace;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// Directory for distillation output
 const DISTILL_DIR: &str = "distill";
 
+/// Directory for manually added items, which distill never rewrites
+pub const MANUAL_DIR: &str = "manual";
+
+/// A line of progress output from a distill run
+///
+/// The CLI prints these as they arrive; library callers receive them through
+/// the callback passed to `Workspace::distill` and can log or drop them.
+#[derive(Debug, Clone, Copy)]
+pub enum Progress<'a> {
+    /// Status line (stdout in the CLI)
+    Status(&'a str),
+    /// Non-fatal problem, such as a failed session or best-effort step (stderr in the CLI)
+    Problem(&'a str),
+}
+
+/// Print progress the way the CLI shows it
+pub fn print_progress(progress: Progress<'_>) {
+    match progress {
+        Progress::Status(line) => println!("{}", line),
+        Progress::Problem(line) => eprintln!("{}", line),
+    }
+}
+
+/// Report a formatted status line through a progress callback
+macro_rules! status {
+    ($progress:expr, $($arg:tt)*) => {
+        $progress(Progress::Status(&format!($($arg)*)))
+    };
+}
+
+/// Report a formatted problem line through a progress callback
+macro_rules! problem {
+    ($progress:expr, $($arg:tt)*) => {
+        $progress(Progress::Problem(&format!($($arg)*)))
+    };
+}
+
 /// Options for the distill command
+#[derive(Debug, Clone, Default)]
 pub struct DistillOptions {
     /// Preview what would be extracted without writing
     pub dry_run: bool,
@@ -43,6 +95,43 @@ pub struct DistillOptions {
 
     /// Process Codex sessions instead of Claude Code sessions
     pub codex: bool,
+
+    /// Stop extracting once LLM calls in this run have cost this much (USD)
+    pub max_cost: Option<f64>,
+
+    /// Stop extracting once LLM calls in this run have used this many tokens
+    pub max_tokens: Option<u64>,
+}
+
+/// Summary of a distill run, returned to library callers
+#[derive(Debug, Clone, Default)]
+pub struct DistillReport {
+    /// Sessions discovered for the project (or filter)
+    pub sessions_found: usize,
+
+    /// Sessions freshly extracted this run
+    pub processed: usize,
+
+    /// Sessions whose cached extraction was reused
+    pub cached: usize,
+
+    /// Sessions whose extraction failed (see .wm/distill/errors.log)
+    pub failed: usize,
+
+    /// Failed sessions by error category (timeout, rate limited, ...)
+    pub failures: BTreeMap<ErrorKind, usize>,
+
+    /// Items written by Pass 2, by category name (empty for dry runs)
+    pub items: BTreeMap<String, Vec<String>>,
+
+    /// User-level items merged into the user-global store, by category name
+    pub user_items: BTreeMap<String, Vec<String>>,
+
+    /// Sessions left unextracted because the budget ran out
+    pub over_budget: usize,
+
+    /// Whether max_cost/max_tokens stopped the run (Pass 2 is then skipped)
+    pub budget_exhausted: bool,
 }
 
 /// Cached extraction result for a session
@@ -60,6 +149,19 @@ struct SessionExtraction {
     /// The extracted content (if has_knowledge is true)
     content: String,
 
+    /// Structured insights behind `content` (empty for marker-format extractions)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    insights: Vec<Insight>,
+
+    /// Where each item of `content` came from, in order (see evidence.rs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    evidence: Vec<Evidence>,
+
+    /// When the session was last active, for confidence decay
+    /// (None in caches written before confidence scoring; extracted_at is used)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_time: Option<DateTime<Utc>>,
+
     /// File size at extraction time (to detect changes)
     /// AIDEV-NOTE: Sessions are append-only JSONL, so size increase = new content.
     /// This heuristic would break for editable files but works for transcripts.
@@ -67,280 +169,725 @@ struct SessionExtraction {
 }
 
 /// Run the distill command
-pub fn run(options: DistillOptions) -> Result<(), String> {
-    if !state::is_initialized() {
-        return Err("Not initialized. Run 'wm init' first.".to_string());
+pub fn run(
+    ws: &Workspace,
+    options: DistillOptions,
+    progress: &dyn Fn(Progress<'_>),
+) -> crate::Result<DistillReport> {
+    if !state::is_initialized(ws) {
+        return Err(Error::NotInitialized(state::wm_dir(ws)));
     }
 
     // Validate options
     if options.push_to_oh && options.context_id.is_none() {
-        return Err("--context-id is required when using --push-to-oh".to_string());
+        return Err(Error::Operation(
+            "--context-id is required when using --push-to-oh".to_string(),
+        ));
+    }
+    if options
+        .max_cost
+        .is_some_and(|max| max.is_nan() || max <= 0.0)
+    {
+        return Err(Error::Operation(
+            "--max-cost must be a positive amount in USD".to_string(),
+        ));
+    }
+    if options.max_tokens == Some(0) {
+        return Err(Error::Operation(
+            "--max-tokens must be positive".to_string(),
+        ));
     }
 
     // Branch based on session source
     if options.codex {
-        run_codex_distill(options)
+        run_codex_distill(ws, progress, options)
     } else {
-        run_claude_distill(options)
+        run_claude_distill(ws, progress, options)
     }
 }
 
 /// Run distill for Claude Code sessions
-fn run_claude_distill(options: DistillOptions) -> Result<(), String> {
+fn run_claude_distill(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    options: DistillOptions,
+) -> crate::Result<DistillReport> {
     // Discover sessions, optionally filtered by project
     let sessions = if let Some(ref project_filter) = options.project {
-        discover_sessions_by_project_filter(project_filter)?
+        discover_sessions_by_project_filter(progress, project_filter)?
     } else {
         // Default: current project only
-        let project_path = session::current_project_path();
+        let project_path = ws.root().to_path_buf();
         session::discover_sessions(&project_path)?
     };
 
     if sessions.is_empty() {
         if let Some(ref filter) = options.project {
-            println!("No sessions found for projects matching '{}'.", filter);
+            status!(
+                progress,
+                "No sessions found for projects matching '{}'.",
+                filter
+            );
         } else {
-            println!("No sessions found for project.");
+            status!(progress, "No sessions found for project.");
         }
-        return Ok(());
+        return Ok(DistillReport::default());
     }
 
     if let Some(ref filter) = options.project {
-        println!(
+        status!(
+            progress,
             "Found {} Claude session(s) matching project filter '{}'",
             sessions.len(),
             filter
         );
     } else {
-        println!("Found {} Claude session(s)", sessions.len());
+        status!(progress, "Found {} Claude session(s)", sessions.len());
     }
 
     if options.dry_run {
-        println!("\n[DRY RUN] Would process:");
-        let cache = load_cache("cache.json");
+        status!(progress, "\n[DRY RUN] Would process:");
+        let cache = load_cache(ws, "cache.json");
         for session in &sessions {
             let status = extraction_status(session, &cache, options.force);
-            println!("  {} [{}]", session.display_info(), status);
+            status!(progress, "  {} [{}]", session.display_info(), status);
         }
-        return Ok(());
+        return Ok(DistillReport {
+            sessions_found: sessions.len(),
+            ..DistillReport::default()
+        });
     }
 
     // Pass 1: Extract knowledge from each session
-    println!("\n=== Pass 1: Extracting knowledge from Claude sessions ===\n");
-    let extractions = run_pass1_generic(&sessions, options.force, "cache.json", extract_claude)?;
-
-    run_pass2_and_push(extractions, options)
+    status!(
+        progress,
+        "\n=== Pass 1: Extracting knowledge from Claude sessions ===\n"
+    );
+    let budget = Budget::new(options.max_cost, options.max_tokens);
+    let (extractions, report) = run_pass1_generic(
+        ws,
+        progress,
+        &sessions,
+        options.force,
+        "cache.json",
+        &budget,
+        extract_claude,
+    )?;
+
+    run_pass2_and_push(ws, progress, extractions, report, &budget, options)
 }
 
 /// Run distill for Codex sessions
-fn run_codex_distill(options: DistillOptions) -> Result<(), String> {
+fn run_codex_distill(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    options: DistillOptions,
+) -> crate::Result<DistillReport> {
     // Discover Codex sessions, optionally filtered by project path
     let sessions = codex::discover_sessions(options.project.as_deref())?;
 
     if sessions.is_empty() {
         if let Some(ref filter) = options.project {
-            println!(
+            status!(
+                progress,
                 "No Codex sessions found for projects matching '{}'.",
                 filter
             );
         } else {
-            println!("No Codex sessions found.");
+            status!(progress, "No Codex sessions found.");
         }
-        return Ok(());
+        return Ok(DistillReport::default());
     }
 
     if let Some(ref filter) = options.project {
-        println!(
+        status!(
+            progress,
             "Found {} Codex session(s) matching project filter '{}'",
             sessions.len(),
             filter
         );
     } else {
-        println!("Found {} Codex session(s)", sessions.len());
+        status!(progress, "Found {} Codex session(s)", sessions.len());
     }
 
     if options.dry_run {
-        println!("\n[DRY RUN] Would process:");
-        let cache = load_cache(CODEX_CACHE_FILE);
+        status!(progress, "\n[DRY RUN] Would process:");
+        let cache = load_cache(ws, CODEX_CACHE_FILE);
         for session in &sessions {
             let status = extraction_status(session, &cache, options.force);
-            println!("  {} [{}]", session.display_info(), status);
+            status!(progress, "  {} [{}]", session.display_info(), status);
         }
-        return Ok(());
+        return Ok(DistillReport {
+            sessions_found: sessions.len(),
+            ..DistillReport::default()
+        });
     }
 
     // Pass 1: Extract knowledge from each Codex session
-    println!("\n=== Pass 1: Extracting knowledge from Codex sessions ===\n");
-    let extractions =
-        run_pass1_generic(&sessions, options.force, CODEX_CACHE_FILE, extract_codex)?;
-
-    run_pass2_and_push(extractions, options)
+    status!(
+        progress,
+        "\n=== Pass 1: Extracting knowledge from Codex sessions ===\n"
+    );
+    let budget = Budget::new(options.max_cost, options.max_tokens);
+    let (extractions, report) = run_pass1_generic(
+        ws,
+        progress,
+        &sessions,
+        options.force,
+        CODEX_CACHE_FILE,
+        &budget,
+        extract_codex,
+    )?;
+
+    run_pass2_and_push(ws, progress, extractions, report, &budget, options)
 }
 
 /// Run Pass 2 and optionally push to OH (shared by both Claude and Codex paths)
 fn run_pass2_and_push(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
     extractions: Vec<SessionExtraction>,
+    mut report: DistillReport,
+    budget: &Budget,
     options: DistillOptions,
-) -> Result<(), String> {
+) -> crate::Result<DistillReport> {
+    // AIDEV-NOTE: Pass 2 rewrites guardrails.md/metis.md from all extractions,
+    // so with sessions still missing it would drop their knowledge. Stop here;
+    // Pass 1 results are cached and the next run picks up where this one ended.
+    if let Some(reason) = budget.exhausted() {
+        report.budget_exhausted = true;
+        status!(progress, "\nBudget reached ({}).", reason);
+        if report.over_budget > 0 {
+            status!(
+                progress,
+                "{} session(s) not extracted yet; skipping Pass 2. Run 'wm distill' again to continue.",
+                report.over_budget
+            );
+        } else {
+            status!(
+                progress,
+                "Skipping Pass 2. Run 'wm distill' again to categorize (extractions are cached)."
+            );
+        }
+        report_spent(progress, budget);
+        return Ok(report);
+    }
+
     // Accumulate raw extractions
-    let raw_content = accumulate_extractions(&extractions);
+    let (raw_content, pass1) = accumulate_extractions(&extractions);
 
     if raw_content.is_empty() {
-        println!("\nNo knowledge extracted from any session.");
-        return Ok(());
+        status!(progress, "\nNo knowledge extracted from any session.");
+        return Ok(report);
     }
 
     // Write raw extractions for Pass 2
-    write_raw_extractions(&raw_content)?;
-    println!(
+    write_raw_extractions(ws, &raw_content)?;
+    status!(
+        progress,
         "\nPass 1 complete: {} session(s) with knowledge extracted.",
         extractions.iter().filter(|e| e.has_knowledge).count()
     );
-    println!(
+    status!(
+        progress,
         "Raw extractions written to .wm/{}/raw_extractions.md",
         DISTILL_DIR
     );
 
-    // Pass 2: Categorize into guardrails vs metis
-    println!("\n=== Pass 2: Categorizing into guardrails vs metis ===\n");
-    let categorized = run_pass2(&raw_content)?;
+    // Pass 2: Categorize into the configured categories
+    let categories = category::all(ws);
+    status!(
+        progress,
+        "\n=== Pass 2: Categorizing into {} ===\n",
+        category::names(&categories)
+    );
+    let categorized = run_pass2(ws, progress, budget, &raw_content, &categories)?;
+    update_confidence(ws, progress, &categorized, &extractions);
+    update_evidence(ws, progress, &categorized, &pass1);
+    if state::read_config(ws).lint.after_distill {
+        check_conflicts(ws, progress, budget);
+    }
 
     // Push to Open Horizons if requested
     if options.push_to_oh {
         let context_id = options.context_id.as_ref().unwrap(); // Already validated above
-        push_to_oh(context_id, &categorized)?;
+        push_to_oh(ws, progress, context_id, &categorized)?;
     }
 
-    Ok(())
+    report.items = categorized.items;
+    report.user_items = categorized.user_items;
+    report_spent(progress, budget);
+    Ok(report)
+}
+
+/// Record which sessions support each item Pass 2 wrote (best-effort)
+fn update_confidence(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    categorized: &CategorizationResult,
+    extractions: &[SessionExtraction],
+) {
+    let sources: Vec<confidence::Source> = extractions
+        .iter()
+        .filter(|e| e.has_knowledge)
+        .map(|e| confidence::Source {
+            session_id: e.session_id.clone(),
+            time: e.session_time.unwrap_or(e.extracted_at),
+            insights: compile::split_items(&e.content),
+        })
+        .collect();
+    let items: Vec<(&str, &str)> = categorized
+        .items
+        .iter()
+        .flat_map(|(name, items)| items.iter().map(move |item| (name.as_str(), item.as_str())))
+        .collect();
+
+    if let Err(e) = confidence::update(ws, &items, &sources) {
+        problem!(progress, "Warning: {}", e);
+        state::log(
+            ws,
+            "distill",
+            &format!("Failed to update confidence: {}", e),
+        );
+    }
+}
+
+/// Record the transcript evidence behind each item Pass 2 wrote (best-effort)
+fn update_evidence(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    categorized: &CategorizationResult,
+    pass1: &[Evidence],
+) {
+    let items: Vec<(&str, &str, Vec<Evidence>)> = categorized
+        .items
+        .iter()
+        .flat_map(|(name, items)| {
+            items.iter().map(move |item| {
+                let refs = categorized.sources.get(item).map_or(&[][..], Vec::as_slice);
+                (
+                    name.as_str(),
+                    item.as_str(),
+                    evidence::supporting(item, refs, pass1),
+                )
+            })
+        })
+        .collect();
+
+    if let Err(e) = evidence::update(ws, items) {
+        problem!(progress, "Warning: {}", e);
+        state::log(ws, "distill", &format!("Failed to update evidence: {}", e));
+    }
+}
+
+/// Optional step after Pass 2: report conflicting items (best-effort)
+fn check_conflicts(ws: &Workspace, progress: &dyn Fn(Progress<'_>), budget: &Budget) {
+    status!(progress, "\n=== Checking for conflicting items ===\n");
+    match lint::check(ws, Some(budget), progress) {
+        Ok(conflicts) => progress(Progress::Status(lint::format_report(&conflicts).trim_end())),
+        Err(e) => {
+            problem!(progress, "Warning: conflict check failed: {}", e);
+            state::log(ws, "distill", &format!("Conflict check failed: {}", e));
+        }
+    }
+}
+
+/// Report what this run's LLM calls used (see 'wm usage' for history)
+fn report_spent(progress: &dyn Fn(Progress<'_>), budget: &Budget) {
+    let spent = budget.spent();
+    if spent.calls > 0 {
+        status!(
+            progress,
+            "\nLLM usage: {} call(s), {} tokens, ${:.4}",
+            spent.calls,
+            spent.tokens(),
+            spent.cost_usd
+        );
+    }
 }
 
 /// Result of Pass 2 categorization
+#[derive(Debug, Default)]
 pub struct CategorizationResult {
-    pub guardrails: Vec<String>,
-    pub metis: Vec<String>,
+    /// Project items by category name
+    pub items: BTreeMap<String, Vec<String>>,
+
+    /// Personal items that apply across projects (user-global store), by category name
+    pub user_items: BTreeMap<String, Vec<String>>,
+
+    /// Raw extraction numbers (`[#n]`) each item was distilled from
+    pub sources: BTreeMap<String, Vec<usize>>,
+}
+
+impl CategorizationResult {
+    /// Project items of a category (empty if none)
+    pub fn items(&self, name: &str) -> &[String] {
+        self.items.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// User-level items of a category (empty if none)
+    pub fn user_items(&self, name: &str) -> &[String] {
+        self.user_items
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, category: &Category, user: bool, item: String, sources: Vec<usize>) {
+        if !sources.is_empty() {
+            self.sources.insert(item.clone(), sources);
+        }
+        let bucket = if user {
+            &mut self.user_items
+        } else {
+            &mut self.items
+        };
+        bucket.entry(category.name.clone()).or_default().push(item);
+    }
 }
 
-/// Run Pass 2: categorize raw extractions into guardrails vs metis
+/// Run Pass 2: categorize raw extractions into the configured categories
 /// Returns the categorization result for optional OH push.
-fn run_pass2(raw_extractions: &str) -> Result<CategorizationResult, String> {
-    let result = call_categorization_llm(raw_extractions)?;
-
-    let guardrail_count = result.guardrails.len();
-    let metis_count = result.metis.len();
-
-    // Write guardrails
-    if !result.guardrails.is_empty() {
-        let content = format_categorized_output("Guardrails", &result.guardrails);
-        write_categorized_file("guardrails.md", &content)?;
-        println!(
-            "  ✓ {} guardrail(s) written to .wm/{}/guardrails.md",
-            guardrail_count, DISTILL_DIR
+fn run_pass2(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    budget: &Budget,
+    raw_extractions: &str,
+    categories: &[Category],
+) -> crate::Result<CategorizationResult> {
+    let resolutions = lint::load(ws).resolutions;
+    let mut result =
+        call_categorization_llm(ws, budget, raw_extractions, categories, &resolutions)?;
+
+    // Leave out items the user dropped with 'wm lint --resolve'
+    let emptied = drop_resolved(&mut result, &lint::dropped(&resolutions));
+
+    // Write one file per category
+    // AIDEV-NOTE: A category the LLM returned nothing for keeps its previous
+    // file, as before. One whose items were all dropped is rewritten empty, or
+    // the dropped items would live on in the old file.
+    for category in categories {
+        let items = result.items(&category.name);
+        if items.is_empty() && emptied.contains(&category.name) {
+            let content = format_categorized_output(&category.title, items);
+            write_categorized_file(ws, &category.filename, &content, "distill")?;
+            status!(
+                progress,
+                "  ✓ All {} items were dropped by lint resolutions; cleared .wm/{}/{}",
+                category.name,
+                DISTILL_DIR,
+                category.filename
+            );
+            continue;
+        }
+        if items.is_empty() {
+            status!(progress, "  ○ No {} identified", category.name);
+            continue;
+        }
+        let content = format_categorized_output(&category.title, items);
+        write_categorized_file(ws, &category.filename, &content, "distill")?;
+        status!(
+            progress,
+            "  ✓ {} {} item(s) written to .wm/{}/{}",
+            items.len(),
+            category.name,
+            DISTILL_DIR,
+            category.filename
         );
-    } else {
-        println!("  ○ No guardrails identified");
     }
 
-    // Write metis
-    if !result.metis.is_empty() {
-        let content = format_categorized_output("Metis", &result.metis);
-        write_categorized_file("metis.md", &content)?;
-        println!(
-            "  ✓ {} metis item(s) written to .wm/{}/metis.md",
-            metis_count, DISTILL_DIR
+    // Merge user-scoped items into the user-global store
+    for category in categories {
+        let items = result.user_items(&category.name);
+        if items.is_empty() {
+            continue;
+        }
+        let added = merge_user_items(&category.filename, &category.title, items)?;
+        status!(
+            progress,
+            "  ✓ {} user-level item(s) merged into user-global {}/{} ({} new)",
+            items.len(),
+            DISTILL_DIR,
+            category.filename,
+            added
         );
-    } else {
-        println!("  ○ No metis items identified");
     }
 
-    println!(
-        "\nPass 2 complete: {} guardrail(s), {} metis item(s)",
-        guardrail_count, metis_count
+    let counts: Vec<String> = categories
+        .iter()
+        .map(|c| format!("{} {}", result.items(&c.name).len(), c.name))
+        .collect();
+    status!(
+        progress,
+        "\nPass 2 complete: {}, {} user-level item(s)",
+        counts.join(", "),
+        result.user_items.values().map(Vec::len).sum::<usize>()
     );
 
     Ok(result)
 }
 
-/// Call LLM to categorize extractions into guardrails vs metis
-fn call_categorization_llm(raw_extractions: &str) -> Result<CategorizationResult, String> {
-    // AIDEV-NOTE: Categorization distinguishes between:
-    // - Guardrails: Hard constraints that must NEVER be violated (binary enforcement)
-    // - Metis: Wisdom/patterns about HOW to work effectively (contextual guidance)
-    // The key difference: guardrails are rules, metis is advice.
-    let system_prompt = r#"You are categorizing tacit knowledge into two types:
-
-**GUARDRAILS** - Hard constraints that must NEVER be violated:
-- Prohibitions: "Never do X", "Always do Y before Z"
-- Safety rules: Things that could cause data loss, security issues, or broken builds
-- Project-specific requirements that are non-negotiable
-- Examples: "Never commit .env files", "Always run tests before pushing", "Never delete migrations"
-
-**METIS** - Wisdom and patterns about HOW to work effectively:
-- Preferences: How the user likes things done
-- Patterns: Approaches that work well in this codebase
-- Context: Understanding about why things are the way they are
-- Soft guidance that may have exceptions
-- Examples: "Prefer functional approaches", "User likes concise commit messages", "Check existing patterns first"
-
-OUTPUT FORMAT:
-
-GUARDRAILS:
-- Item 1
-- Item 2
-...
+/// Remove dropped items (normalized) from project and user-level items
+/// Returns the categories whose project items were all dropped.
+fn drop_resolved(
+    result: &mut CategorizationResult,
+    dropped: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    let keep = |item: &String| !dropped.contains(&compile::normalize_item(item));
+    let mut emptied = BTreeSet::new();
+    for (name, items) in result.items.iter_mut() {
+        let had_items = !items.is_empty();
+        items.retain(keep);
+        if had_items && items.is_empty() {
+            emptied.insert(name.clone());
+        }
+    }
+    for items in result.user_items.values_mut() {
+        items.retain(keep);
+    }
+    emptied
+}
 
-METIS:
-- Item 1
-- Item 2
-...
+/// Merge items into a user-global distilled file, skipping ones already present
+/// AIDEV-NOTE: Unlike project files, user-global files are never regenerated -
+/// other projects contribute to them too - so distill only ever adds.
+/// Returns the number of items added.
+pub fn merge_user_items(filename: &str, title: &str, items: &[String]) -> crate::Result<usize> {
+    let dir = state::global_path(DISTILL_DIR)
+        .ok_or_else(|| "Could not determine user-global directory".to_string())?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+
+    let path = dir.join(filename);
+    let _lock =
+        lock::acquire(&path).map_err(|e| Error::io(format!("Failed to lock {}", filename), e))?;
+    let existing_content = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut existing: Vec<String> = compile::split_items(&existing_content);
+    let before = existing.len();
 
-Rules:
+    for item in items {
+        let normalized = compile::normalize_item(item);
+        if !existing
+            .iter()
+            .any(|e| compile::normalize_item(e) == normalized)
+        {
+            existing.push(item.clone());
+        }
+    }
+
+    let added = existing.len() - before;
+    if added > 0 {
+        state::write_atomic(&path, &format_categorized_output(title, &existing))
+            .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+    }
+
+    Ok(added)
+}
+
+/// The configured categories and their descriptions, for Pass 2
+fn categories_prompt(categories: &[Category]) -> String {
+    let mut output = String::from("CATEGORIES:\n");
+    for category in categories {
+        output.push_str(&format!(
+            "\n**{}** - {}\n",
+            category.marker(),
+            category.description
+        ));
+    }
+    output
+}
+
+/// Pass 2 output instructions for the marker format (fallback)
+/// One `NAME:` section per category, in category order.
+fn categorization_marker_format(categories: &[Category]) -> String {
+    let mut output = String::from("OUTPUT FORMAT:\n");
+    for category in categories {
+        output.push_str(&format!(
+            "\n{}:\n- Item 1\n- Item 2\n...\n",
+            category.marker()
+        ));
+    }
+    format!("{}\n{}", output, CATEGORIZATION_MARKER_RULES)
+}
+
+const CATEGORIZATION_MARKER_RULES: &str = r#"Rules:
 1. Each item should be self-contained and actionable
 2. Preserve the original meaning but clarify if needed
-3. If an item could be both, choose based on severity (safety-critical = guardrail)
+3. If an item could fit more than one category, choose the one whose description fits best (safety-critical = the strictest category)
 4. It's OK to have empty sections if nothing fits that category
-5. Combine duplicates, but don't lose distinct nuances"#;
+5. Combine duplicates, but don't lose distinct nuances
+6. Prefix an item with [user] if it is a personal preference or habit of the user that would apply in ANY project (e.g. "[user] Prefers small, focused commits"). Leave items about this codebase, its tools or its domain unprefixed.
+7. Keep any trailing [scope: ...] tag exactly as written; when combining duplicates, merge their scopes (an unscoped duplicate makes the result unscoped).
+8. Start each item with the [#n] numbers of the input insights it came from (e.g. "- [#3, #7] Prefer pnpm"; "- [user] [#2] Prefers small, focused commits")."#;
+
+/// Pass 2 output instructions for structured (JSON) output
+const CATEGORIZATION_JSON_FORMAT: &str = r#"OUTPUT FORMAT:
 
+Respond with JSON matching the provided schema: {"items": [...]}, one object per item:
+- text: the item, self-contained and actionable (it may span several sentences)
+- category: the name of the category it belongs to (see CATEGORIES, in lowercase)
+- user: true if it is a personal preference or habit of the user that would apply in ANY project (e.g. "Prefers small, focused commits"); false for items about this codebase, its tools or its domain
+- scope: if the input item ends with a [scope: ...] tag, its paths (without the tag); otherwise []
+- sources: the [#n] numbers of the input insights it came from (e.g. [3, 7])
+
+Rules:
+1. Preserve the original meaning but clarify if needed
+2. If an item could fit more than one category, choose the one whose description fits best (safety-critical = the strictest category)
+3. Combine duplicates, but don't lose distinct nuances; merge their scopes (an unscoped duplicate makes the result unscoped)
+4. An empty items list is fine if nothing is worth keeping"#;
+
+/// Call LLM to categorize extractions into the configured categories
+/// AIDEV-NOTE: By default categorization distinguishes between:
+/// - Guardrails: Hard constraints that must NEVER be violated (binary enforcement)
+/// - Metis: Wisdom/patterns about HOW to work effectively (contextual guidance)
+///
+/// The key difference: guardrails are rules, metis is advice. Custom categories
+/// bring their own descriptions (categories_prompt), placed between the
+/// template and the output format. Conflicts resolved with `wm lint` follow the
+/// insights in the message.
+fn call_categorization_llm(
+    ws: &Workspace,
+    budget: &Budget,
+    raw_extractions: &str,
+    categories: &[Category],
+    resolutions: &[lint::Resolution],
+) -> Result<CategorizationResult, LlmError> {
+    let prompt = format!(
+        "{}\n\n{}",
+        prompts::load(ws, "distill-categorize").trim_end(),
+        categories_prompt(categories)
+    );
     let message = format!(
-        "Categorize these extracted insights:\n\n{}\n\nOUTPUT:",
-        raw_extractions
+        "Categorize these extracted insights:\n\n{}\n\n{}OUTPUT:",
+        raw_extractions,
+        lint::resolutions_prompt(resolutions)
     );
 
-    let result_str = llm::call_claude(system_prompt, &message)?;
-    parse_categorization_response(&result_str)
+    if llm::structured_output_available(ws) {
+        let system_prompt = format!("{}\n{}", prompt, CATEGORIZATION_JSON_FORMAT);
+        match llm::call_claude_json::<CategorizationOutput>(
+            ws,
+            "distill",
+            None,
+            Some(budget),
+            &system_prompt,
+            &message,
+            &insight::categorization_schema(&category_names(categories)),
+        ) {
+            Ok(output) => {
+                return Ok(categorize_insights(
+                    insight::validate(output.items),
+                    categories,
+                ));
+            }
+            Err(e) if e.kind == ErrorKind::Unsupported => {
+                state::log(
+                    ws,
+                    "distill",
+                    "Structured output unsupported, using markers",
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let system_prompt = format!("{}\n{}", prompt, categorization_marker_format(categories));
+    let result_str = llm::call_claude(ws, "distill", None, Some(budget), &system_prompt, &message)?;
+    parse_categorization_response(&result_str, categories)
+        .map_err(|e| LlmError::new(ErrorKind::InvalidResponse, e))
 }
 
-/// Parse the categorization response into guardrails and metis
-fn parse_categorization_response(response: &str) -> Result<CategorizationResult, String> {
-    let mut guardrails = Vec::new();
-    let mut metis = Vec::new();
-    let mut current_section: Option<&str> = None;
+/// Category names, for the JSON schemas
+fn category_names(categories: &[Category]) -> Vec<&str> {
+    categories.iter().map(|c| c.name.as_str()).collect()
+}
+
+/// Sort Pass 2 insights into project and user-level items per category
+/// Items naming no configured category go to the last one (metis by default).
+fn categorize_insights(items: Vec<Insight>, categories: &[Category]) -> CategorizationResult {
+    let mut result = CategorizationResult::default();
+    let Some(fallback) = category::fallback(categories) else {
+        return result;
+    };
+    for item in items {
+        let category = item
+            .category
+            .as_deref()
+            .and_then(|label| category::find(categories, label))
+            .unwrap_or(fallback);
+        let (text, mut sources) = split_refs(&item.to_item());
+        sources.extend(&item.sources);
+        result.push(category, item.user, text, sources);
+    }
+    result
+}
+
+/// Parse the marker-format categorization response (one `NAME:` section per category)
+fn parse_categorization_response(
+    response: &str,
+    categories: &[Category],
+) -> Result<CategorizationResult, String> {
+    let mut result = CategorizationResult::default();
+    let mut current_section: Option<&Category> = None;
 
     for line in response.lines() {
         let trimmed = line.trim();
 
-        // Check for section headers
-        if trimmed.starts_with("GUARDRAILS:") || trimmed == "GUARDRAILS" {
-            current_section = Some("guardrails");
-            continue;
-        }
-        if trimmed.starts_with("METIS:") || trimmed == "METIS" {
-            current_section = Some("metis");
-            continue;
+        // Check for section headers ("GUARDRAILS:", "## METIS", "**KNOWN_PITFALLS:**")
+        if !trimmed.starts_with(['-', '*', '•']) || trimmed.starts_with("**") {
+            let header = trimmed.trim_start_matches('#').trim().trim_matches('*');
+            let header = header.split_once(':').map_or(header, |(name, _)| name);
+            if let Some(category) = category::find(categories, header) {
+                current_section = Some(category);
+                continue;
+            }
         }
 
         // Parse bullet points
-        if let Some(section) = current_section
+        if let Some(category) = current_section
             && let Some(item) = parse_bullet_item(trimmed)
         {
-            match section {
-                "guardrails" => guardrails.push(item),
-                "metis" => metis.push(item),
-                _ => {}
-            }
+            let (item, mut sources) = split_refs(&item);
+            let (item, user_scoped) = split_user_scope(&item);
+            let (item, more) = split_refs(&item);
+            sources.extend(more);
+            result.push(category, user_scoped, item, sources);
         }
     }
 
-    Ok(CategorizationResult { guardrails, metis })
+    Ok(result)
+}
+
+/// Strip leading `[#3, #7]` references to raw extractions, returning their numbers
+fn split_refs(item: &str) -> (String, Vec<usize>) {
+    let trimmed = item.trim_start();
+    let Some(body) = trimmed.strip_prefix("[#") else {
+        return (item.to_string(), Vec::new());
+    };
+    let Some((refs, rest)) = body.split_once(']') else {
+        return (item.to_string(), Vec::new());
+    };
+    let numbers: Option<Vec<usize>> = refs
+        .split(',')
+        .map(|r| r.trim().trim_start_matches('#').parse().ok())
+        .collect();
+    match numbers {
+        Some(numbers) => (rest.trim().to_string(), numbers),
+        None => (item.to_string(), Vec::new()),
+    }
+}
+
+/// Strip a leading [user] scope tag, reporting whether it was present
+fn split_user_scope(item: &str) -> (String, bool) {
+    let lower = item.to_lowercase();
+    if lower.starts_with("[user]") {
+        (
+            item.get("[user]".len()..).unwrap_or("").trim().to_string(),
+            true,
+        )
+    } else {
+        (item.to_string(), false)
+    }
 }
 
 /// Parse a bullet point item, returning None for empty or non-bullet lines
@@ -379,48 +926,117 @@ fn format_categorized_output(title: &str, items: &[String]) -> String {
 }
 
 /// Write a categorized output file
-fn write_categorized_file(filename: &str, content: &str) -> Result<(), String> {
-    let distill_dir = state::wm_path(DISTILL_DIR);
-    std::fs::create_dir_all(&distill_dir)
-        .map_err(|e| format!("Failed to create distill directory: {}", e))?;
+fn write_categorized_file(
+    ws: &Workspace,
+    filename: &str,
+    content: &str,
+    command: &str,
+) -> crate::Result<()> {
+    let _lock = lock_categorized_file(ws, filename)?;
+    Ok(history::write_tracked(
+        ws,
+        &format!("{}/{}", DISTILL_DIR, filename),
+        content,
+        command,
+    )?)
+}
 
-    let path = distill_dir.join(filename);
-    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", filename, e))?;
+/// Lock a categorized file for writing
+fn lock_categorized_file(ws: &Workspace, filename: &str) -> crate::Result<FileLock> {
+    lock::acquire(&state::wm_path(ws, DISTILL_DIR).join(filename))
+        .map_err(|e| Error::io(format!("Failed to lock {}", filename), e))
+}
 
-    Ok(())
+/// Append a manually added insight (MCP `add_insight`) to .wm/manual/<filename>
+/// AIDEV-NOTE: Manual items live outside .wm/distill/ because Pass 2 rewrites
+/// those files from transcripts; compile merges them back in (see
+/// compile::read_distilled_file), so they survive every distill run.
+pub fn append_manual_item(
+    ws: &Workspace,
+    filename: &str,
+    title: &str,
+    item: &str,
+) -> crate::Result<()> {
+    let dir = state::wm_path(ws, MANUAL_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+    let path = dir.join(filename);
+    let _lock =
+        lock::acquire(&path).map_err(|e| Error::io(format!("Failed to lock {}", filename), e))?;
+    let mut content = std::fs::read_to_string(&path).unwrap_or_default();
+
+    if content.trim().is_empty() {
+        content = format_categorized_output(title, &[]);
+    } else if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("- {}\n", item.trim()));
+
+    Ok(history::write_tracked(
+        ws,
+        &format!("{}/{}", MANUAL_DIR, filename),
+        &content,
+        "add-insight",
+    )?)
 }
 
 /// Push categorized items to Open Horizons
-fn push_to_oh(context_id: &str, categorized: &CategorizationResult) -> Result<(), String> {
-    if categorized.guardrails.is_empty() && categorized.metis.is_empty() {
-        println!("\n=== Push to OH ===\n");
-        println!("  ○ Nothing to push (no candidates)");
+fn push_to_oh(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
+    context_id: &str,
+    categorized: &CategorizationResult,
+) -> crate::Result<()> {
+    // Open Horizons only knows guardrail and metis candidates
+    let guardrails = categorized.items("guardrails");
+    let metis = categorized.items("metis");
+    if guardrails.is_empty() && metis.is_empty() {
+        status!(progress, "\n=== Push to OH ===\n");
+        status!(progress, "  ○ Nothing to push (no candidates)");
         return Ok(());
     }
 
-    println!("\n=== Push to Open Horizons ===\n");
-    println!("  Context: {}", context_id);
+    status!(progress, "\n=== Push to Open Horizons ===\n");
+    status!(progress, "  Context: {}", context_id);
 
-    let result = oh::push_candidates(context_id, &categorized.guardrails, &categorized.metis)?;
+    let result = oh::push_candidates(ws, context_id, guardrails, metis)?;
+    let other: usize = categorized
+        .items
+        .iter()
+        .filter(|(name, _)| !matches!(name.as_str(), "guardrails" | "metis"))
+        .map(|(_, items)| items.len())
+        .sum();
+    if other > 0 {
+        status!(
+            progress,
+            "  ○ {} item(s) in other categories not pushed (OH takes guardrails and metis)",
+            other
+        );
+    }
 
     // Report results
     if result.guardrails_pushed > 0 {
-        println!("  ✓ {} guardrail(s) pushed", result.guardrails_pushed);
+        status!(
+            progress,
+            "  ✓ {} guardrail(s) pushed",
+            result.guardrails_pushed
+        );
     }
     if result.metis_pushed > 0 {
-        println!("  ✓ {} metis item(s) pushed", result.metis_pushed);
+        status!(progress, "  ✓ {} metis item(s) pushed", result.metis_pushed);
     }
 
     // Report errors
     if !result.errors.is_empty() {
-        println!("  ✗ {} item(s) failed:", result.errors.len());
+        status!(progress, "  ✗ {} item(s) failed:", result.errors.len());
         for (content, error) in &result.errors {
-            println!("    - \"{}\": {}", content, error);
+            status!(progress, "    - \"{}\": {}", content, error);
         }
     }
 
     let total_pushed = result.guardrails_pushed + result.metis_pushed;
-    println!(
+    status!(
+        progress,
         "\nOH push complete: {} item(s) pushed, {} error(s)",
         total_pushed,
         result.errors.len()
@@ -428,7 +1044,9 @@ fn push_to_oh(context_id: &str, categorized: &CategorizationResult) -> Result<()
 
     // Return error if all items failed
     if total_pushed == 0 && !result.errors.is_empty() {
-        return Err("All items failed to push to OH".to_string());
+        return Err(Error::Operation(
+            "All items failed to push to OH".to_string(),
+        ));
     }
 
     Ok(())
@@ -467,17 +1085,22 @@ fn needs_extraction<S: SessionLike>(
 /// Generic Pass 1: extract knowledge from sessions
 ///
 /// Takes an extractor function that handles the specific session type.
+/// Returns the extractions plus session counts for the run report.
 fn run_pass1_generic<S: SessionLike>(
+    ws: &Workspace,
+    progress: &dyn Fn(Progress<'_>),
     sessions: &[S],
     force: bool,
     cache_file: &str,
-    extractor: fn(&S) -> Result<SessionExtraction, String>,
-) -> Result<Vec<SessionExtraction>, String> {
-    let mut cache = load_cache(cache_file);
+    budget: &Budget,
+    extractor: fn(&Workspace, &Budget, &S) -> Result<SessionExtraction, LlmError>,
+) -> crate::Result<(Vec<SessionExtraction>, DistillReport)> {
+    let mut cache = load_cache(ws, cache_file);
     let mut results = Vec::new();
     let mut processed = 0;
     let mut skipped = 0;
-    let mut failed = 0;
+    let mut failures: BTreeMap<ErrorKind, usize> = BTreeMap::new();
+    let mut over_budget = 0;
 
     for session in sessions {
         // Check if we can use cached extraction
@@ -485,51 +1108,87 @@ fn run_pass1_generic<S: SessionLike>(
             && !needs_extraction(session, &cache)
             && let Some(cached) = cache.get(session.session_id())
         {
-            println!("  {} [cached]", session.session_id());
-            results.push(cached.clone());
+            status!(progress, "  {} [cached]", session.session_id());
+            let mut cached = cached.clone();
+            cached.session_time = Some(session.modified_at());
+            results.push(cached);
             skipped += 1;
             continue;
         }
 
+        if let Some(reason) = budget.exhausted() {
+            if over_budget == 0 {
+                status!(
+                    progress,
+                    "  Budget reached ({}), not extracting further sessions",
+                    reason
+                );
+            }
+            over_budget += 1;
+            continue;
+        }
+
         // Extract from this session
-        println!("  {} extracting...", session.session_id());
-        match extractor(session) {
-            Ok(extraction) => {
+        status!(progress, "  {} extracting...", session.session_id());
+        match extractor(ws, budget, session) {
+            Ok(mut extraction) => {
+                extraction.session_time = Some(session.modified_at());
                 let status = if extraction.has_knowledge {
                     "✓ knowledge found"
                 } else {
                     "○ no knowledge"
                 };
-                println!("    {}", status);
+                status!(progress, "    {}", status);
 
                 cache.insert(session.session_id().to_string(), extraction.clone());
                 results.push(extraction);
                 processed += 1;
             }
             Err(e) => {
-                eprintln!("    ✗ error: {}", e);
-                log_extraction_error(session.session_id(), &e);
-                failed += 1;
+                problem!(progress, "    ✗ error: {}", e);
+                log_extraction_error(ws, session.session_id(), &e.to_string());
+                *failures.entry(e.kind).or_default() += 1;
             }
         }
     }
 
-    save_cache(&cache, cache_file)?;
+    save_cache(ws, &cache, cache_file)?;
 
+    let failed = failures.values().sum();
     let mut summary_parts = vec![format!("{} session(s) processed", processed)];
     if skipped > 0 {
         summary_parts.push(format!("{} from cache", skipped));
     }
     if failed > 0 {
-        summary_parts.push(format!("{} failed", failed));
+        let kinds: Vec<String> = failures
+            .iter()
+            .map(|(kind, n)| format!("{} {}", n, kind.label()))
+            .collect();
+        summary_parts.push(format!("{} failed ({})", failed, kinds.join(", ")));
+    }
+    if over_budget > 0 {
+        summary_parts.push(format!("{} left over budget", over_budget));
     }
-    println!("\n{}", summary_parts.join(", "));
+    status!(progress, "\n{}", summary_parts.join(", "));
 
     if failed > 0 {
-        println!("See .wm/{}/errors.log for failure details", DISTILL_DIR);
+        status!(
+            progress,
+            "See .wm/{}/errors.log for failure details",
+            DISTILL_DIR
+        );
     }
 
-    Ok(results)
+    let report = DistillReport {
+        sessions_found: sessions.len(),
+        processed,
+        cached: skipped,
+        failed,
+        failures,
+        over_budget,
+        ..DistillReport::default()
+    };
+    Ok((results, report))
 }
 
 // =============================================================================
@@ -537,14 +1196,23 @@ fn run_pass1_generic<S: SessionLike>(
 // =============================================================================
 
 /// Extract knowledge from a Claude Code session
-fn extract_claude(session: &SessionInfo) -> Result<SessionExtraction, String> {
+fn extract_claude(
+    ws: &Workspace,
+    budget: &Budget,
+    session: &SessionInfo,
+) -> Result<SessionExtraction, LlmError> {
     state::log(
+        ws,
         "distill",
         &format!("Extracting from session {}", session.session_id),
     );
 
-    let entries = read_transcript(&session.transcript_path)
-        .map_err(|e| format!("Failed to read transcript: {}", e))?;
+    let entries = read_transcript(&session.transcript_path).map_err(|e| {
+        LlmError::new(
+            ErrorKind::Other,
+            format!("Failed to read transcript: {}", e),
+        )
+    })?;
 
     // AIDEV-NOTE: Use .as_str() for proper Option<&str> comparison
     let session_messages: Vec<_> = entries
@@ -557,8 +1225,30 @@ fn extract_claude(session: &SessionInfo) -> Result<SessionExtraction, String> {
         return Ok(empty_extraction(&session.session_id, session.size_bytes));
     }
 
-    let formatted = format_context(&session_messages);
-    extract_from_formatted(&session.session_id, &formatted, session.size_bytes)
+    let rules = IgnoreRules::load(ws);
+    if let Some(reason) = rules.session_exclusion(&session_facts(&entries, &session.session_id)) {
+        state::log(
+            ws,
+            "distill",
+            &format!("Session {} ignored ({})", session.session_id, reason),
+        );
+        return Ok(empty_extraction(&session.session_id, session.size_bytes));
+    }
+
+    let formatted = format_context(&session_messages, &ignored_tool_ids(&entries, &rules));
+    let mut extraction = extract_from_formatted(
+        ws,
+        budget,
+        &session.session_id,
+        &formatted,
+        session.size_bytes,
+    )?;
+    extraction.evidence = pass1_evidence(
+        &extraction,
+        Some(&session.transcript_path),
+        &evidence::claude_messages(&entries, &session.session_id),
+    );
+    Ok(extraction)
 }
 
 // =============================================================================
@@ -566,27 +1256,85 @@ fn extract_claude(session: &SessionInfo) -> Result<SessionExtraction, String> {
 // =============================================================================
 
 /// Extract knowledge from a Codex session
-fn extract_codex(session: &CodexSessionInfo) -> Result<SessionExtraction, String> {
+fn extract_codex(
+    ws: &Workspace,
+    budget: &Budget,
+    session: &CodexSessionInfo,
+) -> Result<SessionExtraction, LlmError> {
     state::log(
+        ws,
         "distill",
         &format!("Extracting from Codex session {}", session.session_id),
     );
 
-    let entries = codex::read_codex_session(&session.session_path)
-        .map_err(|e| format!("Failed to read Codex session: {}", e))?;
+    let entries = codex::read_codex_session(&session.session_path).map_err(|e| {
+        LlmError::new(
+            ErrorKind::Other,
+            format!("Failed to read Codex session: {}", e),
+        )
+    })?;
 
     if !entries.iter().any(|e| e.is_relevant()) {
         return Ok(empty_extraction(&session.session_id, session.size_bytes));
     }
 
-    let formatted = codex::format_context(&entries);
-    extract_from_formatted(&session.session_id, &formatted, session.size_bytes)
+    let rules = IgnoreRules::load(ws);
+    let mut facts = codex::session_facts(&entries);
+    if facts.id.is_empty() {
+        facts.id = &session.session_id;
+    }
+    if let Some(reason) = rules.session_exclusion(&facts) {
+        state::log(
+            ws,
+            "distill",
+            &format!("Codex session {} ignored ({})", session.session_id, reason),
+        );
+        return Ok(empty_extraction(&session.session_id, session.size_bytes));
+    }
+
+    let formatted = codex::format_context(&entries, &codex::ignored_call_ids(&entries, &rules));
+    let mut extraction = extract_from_formatted(
+        ws,
+        budget,
+        &session.session_id,
+        &formatted,
+        session.size_bytes,
+    )?;
+    extraction.evidence = pass1_evidence(
+        &extraction,
+        Some(&session.session_path),
+        &evidence::codex_messages(&entries),
+    );
+    Ok(extraction)
 }
 
 // =============================================================================
 // Shared Extraction Helpers
 // =============================================================================
 
+/// Evidence for each item of an extraction, locating quotes in `messages`
+/// Marker-format extractions have no quotes, so their evidence names the session only.
+fn pass1_evidence(
+    extraction: &SessionExtraction,
+    transcript: Option<&std::path::Path>,
+    messages: &[evidence::Message],
+) -> Vec<Evidence> {
+    let items = compile::split_items(&extraction.content);
+    let structured = extraction.insights.len() == items.len();
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let quote = extraction
+                .insights
+                .get(i)
+                .filter(|_| structured)
+                .and_then(|insight| insight.evidence.as_deref());
+            Evidence::new(&extraction.session_id, item, quote, transcript, messages)
+        })
+        .collect()
+}
+
 /// Create an empty extraction result
 fn empty_extraction(session_id: &str, file_size_bytes: u64) -> SessionExtraction {
     SessionExtraction {
@@ -594,27 +1342,35 @@ fn empty_extraction(session_id: &str, file_size_bytes: u64) -> SessionExtraction
         extracted_at: Utc::now(),
         has_knowledge: false,
         content: String::new(),
+        insights: Vec::new(),
+        evidence: Vec::new(),
+        session_time: None,
         file_size_bytes,
     }
 }
 
 /// Extract from formatted transcript content
 fn extract_from_formatted(
+    ws: &Workspace,
+    budget: &Budget,
     session_id: &str,
     formatted: &str,
     file_size_bytes: u64,
-) -> Result<SessionExtraction, String> {
+) -> Result<SessionExtraction, LlmError> {
     if formatted.trim().is_empty() {
         return Ok(empty_extraction(session_id, file_size_bytes));
     }
 
-    let result = call_extraction_llm(formatted)?;
+    let result = call_extraction_llm(ws, budget, session_id, formatted)?;
 
     Ok(SessionExtraction {
         session_id: session_id.to_string(),
         extracted_at: Utc::now(),
         has_knowledge: result.has_knowledge,
         content: result.content,
+        insights: result.insights,
+        evidence: Vec::new(),
+        session_time: None,
         file_size_bytes,
     })
 }
@@ -623,24 +1379,13 @@ fn extract_from_formatted(
 struct ExtractionResult {
     has_knowledge: bool,
     content: String,
+
+    /// Structured insights (empty when the marker format was used)
+    insights: Vec<Insight>,
 }
 
-/// Call LLM to extract tacit knowledge from transcript
-fn call_extraction_llm(transcript: &str) -> Result<ExtractionResult, String> {
-    // AIDEV-NOTE: Distill extraction prompt differs from per-turn extract:
-    // - We're looking at a complete session, not incremental updates
-    // - Focus on extracting standalone insights that can be categorized later
-    // - No existing state to merge with - each session is independent
-    let system_prompt = r#"You are extracting tacit knowledge from an AI coding session transcript.
-
-Tacit knowledge is wisdom about HOW to work effectively, not WHAT was done. Look for:
-- User preferences revealed through corrections or choices
-- Patterns in how problems were approached
-- Constraints discovered through friction
-- Decisions and their rationale (WHY, not just WHAT)
-- Quality standards implicit in feedback
-
-OUTPUT FORMAT:
+/// Pass 1 output instructions for the marker format (fallback)
+const EXTRACTION_MARKER_FORMAT: &str = r#"OUTPUT FORMAT:
 
 If you found tacit knowledge worth capturing, respond:
 HAS_KNOWLEDGE: YES
@@ -655,41 +1400,146 @@ Each insight should be:
 - About HOW to work, not WHAT happened
 - Useful for future AI sessions
 
+If an insight only applies to part of the repository (one package, service or
+directory), end it with a scope tag listing those paths relative to the project
+root, based on the files in the TOOLS lines:
+- Run migrations through sqlx-cli, never by hand [scope: backend/, crates/db/]
+Leave insights that apply project-wide untagged.
+
 If nothing worth capturing, respond:
 HAS_KNOWLEDGE: NO
 
 Most sessions have little or no tacit knowledge. That's normal."#;
 
-    let message = format!("TRANSCRIPT:\n{}\n\nOUTPUT:", transcript);
+/// Pass 1 output instructions for structured (JSON) output
+const EXTRACTION_JSON_FORMAT: &str = r#"OUTPUT FORMAT:
+
+Respond with JSON matching the provided schema. Set has_knowledge to false and
+leave insights empty if nothing is worth capturing. Otherwise add one object per
+insight:
+- text: the insight, self-contained (understandable without the transcript),
+  about HOW to work rather than WHAT happened; it may span several sentences
+- category: the name of the category below it most likely belongs to (in
+  lowercase; a hint, the final sorting happens later)
+- confidence: 0.0-1.0, how clearly the session supports it (an explicit
+  correction is high; a single inferred habit is low)
+- rationale: why it matters, in one sentence
+- evidence: a short verbatim quote from a USER message that motivated it
+- scope: if it only applies to part of the repository (one package, service or
+  directory), those paths relative to the project root, based on the files in
+  the TOOLS lines; [] if it applies project-wide
 
-    let result_str = llm::call_claude(system_prompt, &message)?;
-    let response = llm::parse_marker_response(&result_str, "HAS_KNOWLEDGE");
+Most sessions have little or no tacit knowledge. That's normal."#;
+
+/// Call LLM to extract tacit knowledge from transcript
+/// AIDEV-NOTE: Distill extraction prompt differs from per-turn extract:
+/// - We're looking at a complete session, not incremental updates
+/// - Focus on extracting standalone insights that can be categorized later
+/// - No existing state to merge with - each session is independent
+fn call_extraction_llm(
+    ws: &Workspace,
+    budget: &Budget,
+    session_id: &str,
+    transcript: &str,
+) -> Result<ExtractionResult, LlmError> {
+    let prompt = prompts::load(ws, "distill-extract");
+    let message = format!(
+        "PROJECT ROOT: {}\n\nTRANSCRIPT:\n{}\n\nOUTPUT:",
+        ws.root().to_path_buf().display(),
+        transcript
+    );
+
+    if llm::structured_output_available(ws) {
+        let categories = category::all(ws);
+        let system_prompt = format!(
+            "{}\n{}\n\n{}",
+            prompt,
+            EXTRACTION_JSON_FORMAT,
+            categories_prompt(&categories)
+        );
+        match llm::call_claude_json::<ExtractionOutput>(
+            ws,
+            "distill",
+            Some(session_id),
+            Some(budget),
+            &system_prompt,
+            &message,
+            &insight::extraction_schema(&category_names(&categories)),
+        ) {
+            Ok(output) => {
+                let insights = insight::validate(output.insights);
+                let content: Vec<String> = insights
+                    .iter()
+                    .map(|i| format!("- {}", i.to_item()))
+                    .collect();
+                return Ok(ExtractionResult {
+                    has_knowledge: output.has_knowledge && !insights.is_empty(),
+                    content: content.join("\n"),
+                    insights,
+                });
+            }
+            Err(e) if e.kind == ErrorKind::Unsupported => {
+                state::log(
+                    ws,
+                    "distill",
+                    "Structured output unsupported, using markers",
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let system_prompt = format!("{}\n{}", prompt, EXTRACTION_MARKER_FORMAT);
+    let result_str = llm::call_claude(
+        ws,
+        "distill",
+        Some(session_id),
+        Some(budget),
+        &system_prompt,
+        &message,
+    )?;
+    let response = llm::parse_marker_response(ws, &result_str, "HAS_KNOWLEDGE");
 
     Ok(ExtractionResult {
         has_knowledge: response.is_positive,
         content: response.content,
+        insights: Vec::new(),
     })
 }
 
-/// Accumulate extractions into a single markdown document
-fn accumulate_extractions(extractions: &[SessionExtraction]) -> String {
+/// Accumulate extractions into a single markdown document for Pass 2
+/// Items are numbered (`- [#3] ...`) so Pass 2 can cite them; the returned
+/// evidence is indexed by those numbers (minus one).
+fn accumulate_extractions(extractions: &[SessionExtraction]) -> (String, Vec<Evidence>) {
     let mut output = String::new();
+    let mut numbered: Vec<Evidence> = Vec::new();
 
     for extraction in extractions {
-        if extraction.has_knowledge && !extraction.content.trim().is_empty() {
-            output.push_str(&format!(
-                "## Session: {}\n\n{}\n\n",
-                extraction.session_id, extraction.content
-            ));
+        if !extraction.has_knowledge || extraction.content.trim().is_empty() {
+            continue;
         }
+        let items = compile::split_items(&extraction.content);
+        // Caches from before evidence tracking name the session only
+        let session_evidence = if extraction.evidence.len() == items.len() {
+            extraction.evidence.clone()
+        } else {
+            pass1_evidence(extraction, None, &[])
+        };
+
+        output.push_str(&format!("## Session: {}\n\n", extraction.session_id));
+        for (item, evidence) in items.iter().zip(session_evidence) {
+            numbered.push(evidence);
+            output.push_str(&format!("- [#{}] {}\n", numbered.len(), item));
+        }
+        output.push('\n');
     }
 
-    output.trim().to_string()
+    (output.trim().to_string(), numbered)
 }
 
 /// Load extraction cache from disk
-fn load_cache(filename: &str) -> HashMap<String, SessionExtraction> {
-    let cache_path = state::wm_path(DISTILL_DIR).join(filename);
+fn load_cache(ws: &Workspace, filename: &str) -> HashMap<String, SessionExtraction> {
+    let cache_path = state::wm_path(ws, DISTILL_DIR).join(filename);
 
     std::fs::read_to_string(&cache_path)
         .ok()
@@ -698,40 +1548,59 @@ fn load_cache(filename: &str) -> HashMap<String, SessionExtraction> {
 }
 
 /// Save extraction cache to disk
-fn save_cache(cache: &HashMap<String, SessionExtraction>, filename: &str) -> Result<(), String> {
-    let distill_dir = state::wm_path(DISTILL_DIR);
+/// AIDEV-NOTE: Another distill may have saved the cache since we loaded it, so
+/// under the lock we re-read it and keep whichever extraction of each session
+/// is newer rather than overwriting the file with our stale copy.
+fn save_cache(
+    ws: &Workspace,
+    cache: &HashMap<String, SessionExtraction>,
+    filename: &str,
+) -> crate::Result<()> {
+    let distill_dir = state::wm_path(ws, DISTILL_DIR);
     std::fs::create_dir_all(&distill_dir)
-        .map_err(|e| format!("Failed to create distill directory: {}", e))?;
+        .map_err(|e| Error::io("Failed to create distill directory", e))?;
 
     let cache_path = distill_dir.join(filename);
-    let content = serde_json::to_string_pretty(cache)
+    let _lock = lock::acquire(&cache_path).map_err(|e| Error::io("Failed to lock cache", e))?;
+
+    let mut merged = load_cache(ws, filename);
+    for (session_id, extraction) in cache {
+        let newer = merged
+            .get(session_id)
+            .is_none_or(|existing| existing.extracted_at < extraction.extracted_at);
+        if newer {
+            merged.insert(session_id.clone(), extraction.clone());
+        }
+    }
+
+    let content = serde_json::to_string_pretty(&merged)
         .map_err(|e| format!("Failed to serialize cache: {}", e))?;
 
-    std::fs::write(cache_path, content).map_err(|e| format!("Failed to write cache: {}", e))?;
+    state::write_atomic(&cache_path, &content)
+        .map_err(|e| Error::io("Failed to write cache", e))?;
 
     Ok(())
 }
 
 /// Write raw extractions to file
-fn write_raw_extractions(content: &str) -> Result<(), String> {
-    let distill_dir = state::wm_path(DISTILL_DIR);
+fn write_raw_extractions(ws: &Workspace, content: &str) -> crate::Result<()> {
+    let distill_dir = state::wm_path(ws, DISTILL_DIR);
     std::fs::create_dir_all(&distill_dir)
-        .map_err(|e| format!("Failed to create distill directory: {}", e))?;
+        .map_err(|e| Error::io("Failed to create distill directory", e))?;
 
     let path = distill_dir.join("raw_extractions.md");
-    std::fs::write(&path, content)
-        .map_err(|e| format!("Failed to write raw extractions: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| Error::io("Failed to write raw extractions", e))?;
 
     Ok(())
 }
 
 /// Log an extraction error to the errors log file
-fn log_extraction_error(session_id: &str, error: &str) {
+fn log_extraction_error(ws: &Workspace, session_id: &str, error: &str) {
     use chrono::Local;
     use std::fs::OpenOptions;
     use std::io::Write;
 
-    let distill_dir = state::wm_path(DISTILL_DIR);
+    let distill_dir = state::wm_path(ws, DISTILL_DIR);
 
     // Ensure directory exists
     if std::fs::create_dir_all(&distill_dir).is_err() {
@@ -763,7 +1632,10 @@ const CODEX_CACHE_FILE: &str = "codex_cache.json";
 // =============================================================================
 
 /// Discover sessions from projects matching a filter string
-fn discover_sessions_by_project_filter(filter: &str) -> Result<Vec<SessionInfo>, String> {
+fn discover_sessions_by_project_filter(
+    progress: &dyn Fn(Progress<'_>),
+    filter: &str,
+) -> Result<Vec<SessionInfo>, String> {
     if filter.trim().is_empty() {
         return Err("Project filter cannot be empty".to_string());
     }
@@ -779,13 +1651,18 @@ fn discover_sessions_by_project_filter(filter: &str) -> Result<Vec<SessionInfo>,
 
     // If multiple matches, show which projects we're processing
     if matching_projects.len() > 1 {
-        println!("Matched {} projects:", matching_projects.len());
+        status!(progress, "Matched {} projects:", matching_projects.len());
         for p in &matching_projects {
-            println!("  {} ({} sessions)", p.project_id, p.session_count);
+            status!(
+                progress,
+                "  {} ({} sessions)",
+                p.project_id,
+                p.session_count
+            );
         }
-        println!();
+        status!(progress, "");
     } else {
-        println!("Project: {}", matching_projects[0].project_id);
+        status!(progress, "Project: {}", matching_projects[0].project_id);
     }
 
     // Collect sessions from all matching projects
@@ -796,7 +1673,7 @@ fn discover_sessions_by_project_filter(filter: &str) -> Result<Vec<SessionInfo>,
     }
 
     // Sort by modification time, newest first
-    all_sessions.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    all_sessions.sort_by_key(|s| std::cmp::Reverse(s.modified_at));
 
     Ok(all_sessions)
 }
@@ -805,6 +1682,12 @@ fn discover_sessions_by_project_filter(filter: &str) -> Result<Vec<SessionInfo>,
 mod tests {
     use super::*;
 
+    fn default_categories() -> Vec<Category> {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        category::from_config(ws, &Default::default())
+    }
+
     #[test]
     fn test_parse_categorization_response_basic() {
         let response = r#"GUARDRAILS:
@@ -815,19 +1698,22 @@ METIS:
 - Prefer functional approaches when possible
 - Check existing patterns before adding new code"#;
 
-        let result = parse_categorization_response(response).unwrap();
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
 
-        assert_eq!(result.guardrails.len(), 2);
-        assert_eq!(result.guardrails[0], "Never commit .env files");
-        assert_eq!(result.guardrails[1], "Always run tests before pushing");
+        assert_eq!(result.items("guardrails").len(), 2);
+        assert_eq!(result.items("guardrails")[0], "Never commit .env files");
+        assert_eq!(
+            result.items("guardrails")[1],
+            "Always run tests before pushing"
+        );
 
-        assert_eq!(result.metis.len(), 2);
+        assert_eq!(result.items("metis").len(), 2);
         assert_eq!(
-            result.metis[0],
+            result.items("metis")[0],
             "Prefer functional approaches when possible"
         );
         assert_eq!(
-            result.metis[1],
+            result.items("metis")[1],
             "Check existing patterns before adding new code"
         );
     }
@@ -841,13 +1727,19 @@ METIS:
 METIS:
 - User preference: concise messages"#;
 
-        let result = parse_categorization_response(response).unwrap();
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
 
-        assert_eq!(result.guardrails.len(), 2);
-        assert_eq!(result.guardrails[0], "Never do this: commit secrets");
+        assert_eq!(result.items("guardrails").len(), 2);
+        assert_eq!(
+            result.items("guardrails")[0],
+            "Never do this: commit secrets"
+        );
 
-        assert_eq!(result.metis.len(), 1);
-        assert_eq!(result.metis[0], "User preference: concise messages");
+        assert_eq!(result.items("metis").len(), 1);
+        assert_eq!(
+            result.items("metis")[0],
+            "User preference: concise messages"
+        );
     }
 
     #[test]
@@ -857,10 +1749,10 @@ METIS:
 METIS:
 - Only metis here"#;
 
-        let result = parse_categorization_response(response).unwrap();
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
 
-        assert_eq!(result.guardrails.len(), 0);
-        assert_eq!(result.metis.len(), 1);
+        assert_eq!(result.items("guardrails").len(), 0);
+        assert_eq!(result.items("metis").len(), 1);
     }
 
     #[test]
@@ -871,10 +1763,167 @@ METIS:
 METIS:
 * Another asterisk item"#;
 
-        let result = parse_categorization_response(response).unwrap();
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
+
+        assert_eq!(result.items("guardrails").len(), 1);
+        assert_eq!(result.items("guardrails")[0], "Item with asterisk");
+    }
+
+    #[test]
+    fn test_parse_categorization_response_user_scope() {
+        let response = r#"GUARDRAILS:
+- [user] Never push without asking
+- Never edit migrations
+
+METIS:
+- [USER] Prefers small, focused commits
+- Uses sqlx for queries"#;
+
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
+
+        assert_eq!(result.items("guardrails"), vec!["Never edit migrations"]);
+        assert_eq!(
+            result.user_items("guardrails"),
+            vec!["Never push without asking"]
+        );
+        assert_eq!(result.items("metis"), vec!["Uses sqlx for queries"]);
+        assert_eq!(
+            result.user_items("metis"),
+            vec!["Prefers small, focused commits"]
+        );
+    }
+
+    #[test]
+    fn test_parse_categorization_response_refs() {
+        let response = r#"GUARDRAILS:
+- [#1, #4] Never edit migrations
+
+METIS:
+- [user] [#2] Prefers small, focused commits
+- [#x] Not a reference"#;
+
+        let result = parse_categorization_response(response, &default_categories()).unwrap();
+
+        assert_eq!(result.items("guardrails"), vec!["Never edit migrations"]);
+        assert_eq!(result.sources["Never edit migrations"], vec![1, 4]);
+        assert_eq!(
+            result.user_items("metis"),
+            vec!["Prefers small, focused commits"]
+        );
+        assert_eq!(result.sources["Prefers small, focused commits"], vec![2]);
+        assert_eq!(result.items("metis"), vec!["[#x] Not a reference"]);
+    }
+
+    #[test]
+    fn test_categorize_insights() {
+        let item = |text: &str, category: &str, user: bool, scope: &[&str]| Insight {
+            text: text.to_string(),
+            category: Some(category.to_string()),
+            confidence: None,
+            rationale: None,
+            evidence: None,
+            scope: scope.iter().map(|s| s.to_string()).collect(),
+            user,
+            sources: Vec::new(),
+        };
+        let result = categorize_insights(
+            vec![
+                item("Never delete\nmigrations", "guardrail", false, &["db/"]),
+                item("Prefers small commits", "metis", true, &[]),
+                item("Check existing patterns first", "Metis", false, &[]),
+                item("Dates are stored in UTC", "trivia", false, &[]),
+            ],
+            &default_categories(),
+        );
+
+        assert_eq!(
+            result.items("guardrails"),
+            vec!["Never delete migrations [scope: db/]"]
+        );
+        assert_eq!(result.user_items("metis"), vec!["Prefers small commits"]);
+        assert_eq!(
+            result.items("metis"),
+            vec!["Check existing patterns first", "Dates are stored in UTC"]
+        );
+        assert!(result.user_items("guardrails").is_empty());
+    }
+
+    #[test]
+    fn test_drop_resolved_filters_user_items_and_reports_emptied() {
+        let mut result = CategorizationResult::default();
+        let categories = default_categories();
+        let (guardrails, metis) = (&categories[0], &categories[1]);
+        result.push(guardrails, false, "Never force push".to_string(), vec![]);
+        result.push(metis, false, "Use tabs".to_string(), vec![]);
+        result.push(metis, true, "use tabs".to_string(), vec![]);
+        result.push(metis, true, "Prefers small commits".to_string(), vec![]);
+
+        let dropped = BTreeSet::from([compile::normalize_item("Use tabs")]);
+        let emptied = drop_resolved(&mut result, &dropped);
+
+        assert_eq!(emptied, BTreeSet::from(["metis".to_string()]));
+        assert!(result.items("metis").is_empty());
+        assert_eq!(result.items("guardrails"), vec!["Never force push"]);
+        assert_eq!(result.user_items("metis"), vec!["Prefers small commits"]);
+    }
+
+    #[test]
+    fn test_categorize_insights_falls_back_to_metis() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let mut config = crate::types::CategoriesConfig::default();
+        // Default priority (50) sorts after metis
+        config.0.insert(
+            "glossary".to_string(),
+            toml::from_str("description = \"Project terms\"").unwrap(),
+        );
+        let categories = category::from_config(ws, &config);
+        assert_eq!(categories.last().unwrap().name, "glossary");
+
+        let insight = Insight {
+            text: "Dates are stored in UTC".to_string(),
+            category: Some("trivia".to_string()),
+            confidence: None,
+            rationale: None,
+            evidence: None,
+            scope: Vec::new(),
+            user: false,
+            sources: Vec::new(),
+        };
+        let result = categorize_insights(vec![insight], &categories);
+        assert_eq!(result.items("metis"), vec!["Dates are stored in UTC"]);
+        assert!(result.items("glossary").is_empty());
+    }
+
+    #[test]
+    fn test_parse_categorization_response_custom_categories() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let mut config = crate::types::CategoriesConfig::default();
+        config.0.insert(
+            "known-pitfalls".to_string(),
+            crate::types::CategoryConfig {
+                description: "Traps that cost time".to_string(),
+                priority: 15,
+                filename: String::new(),
+                always: true,
+                decay: true,
+            },
+        );
+        let categories = category::from_config(ws, &config);
+        let response = "GUARDRAILS:\n- Never force push\n\n## KNOWN_PITFALLS:\n- Dates are UTC\n- [user] Forgets to rebase\n\nMETIS:\n- Prefer small commits";
+
+        let result = parse_categorization_response(response, &categories).unwrap();
+        assert_eq!(result.items("guardrails"), vec!["Never force push"]);
+        assert_eq!(result.items("known-pitfalls"), vec!["Dates are UTC"]);
+        assert_eq!(
+            result.user_items("known-pitfalls"),
+            vec!["Forgets to rebase"]
+        );
+        assert_eq!(result.items("metis"), vec!["Prefer small commits"]);
 
-        assert_eq!(result.guardrails.len(), 1);
-        assert_eq!(result.guardrails[0], "Item with asterisk");
+        let format = categorization_marker_format(&categories);
+        assert!(format.contains("GUARDRAILS:\n") && format.contains("KNOWN_PITFALLS:\n"));
     }
 
     #[test]
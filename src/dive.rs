@@ -3,21 +3,25 @@
 //! Supports multiple named preps (like git branches) stored in .wm/dives/
 //! with a "current" prep tracked in config.
 
+use crate::error::Error;
 use crate::state;
+use crate::workspace::Workspace;
 use std::fs;
 
 // ============================================================================
 // Named prep management
 // ============================================================================
 
-/// List all dive preps, marking the current one
-pub fn list() -> Result<(), String> {
-    if !state::is_initialized() {
-        return Err("Not initialized. Run 'wm init' first.".to_string());
-    }
+/// A named dive prep and whether it is the active one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DivePrep {
+    pub name: String,
+    pub is_current: bool,
+}
 
-    let preps = state::list_dive_preps().map_err(|e| format!("Failed to list preps: {}", e))?;
-    let current = state::current_dive();
+/// List all dive preps, marking the current one
+pub fn list(ws: &Workspace) -> Result<(), String> {
+    let preps = list_preps(ws)?;
 
     if preps.is_empty() {
         println!("No dive preps found. Create one with 'wm dive new <name>'");
@@ -25,91 +29,105 @@ pub fn list() -> Result<(), String> {
     }
 
     for prep in preps {
-        let marker = if current.as_ref() == Some(&prep) {
-            "* "
-        } else {
-            "  "
-        };
-        println!("{}{}", marker, prep);
+        let marker = if prep.is_current { "* " } else { "  " };
+        println!("{}{}", marker, prep.name);
     }
 
     Ok(())
 }
 
+/// List all dive preps without printing
+pub fn list_preps(ws: &Workspace) -> crate::Result<Vec<DivePrep>> {
+    ensure_initialized(ws)?;
+
+    let current = state::current_dive(ws);
+    let names = state::list_dive_preps(ws).map_err(|e| Error::io("Failed to list preps", e))?;
+
+    Ok(names
+        .into_iter()
+        .map(|name| DivePrep {
+            is_current: current.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
 /// Create a new named dive prep
-pub fn new(name: &str, content: Option<&str>) -> Result<(), String> {
-    if !state::is_initialized() {
-        return Err("Not initialized. Run 'wm init' first.".to_string());
-    }
+pub fn new(ws: &Workspace, name: &str, content: Option<&str>) -> Result<(), String> {
+    create(ws, name, content).map_err(|e| match e {
+        Error::AlreadyExists(_) => format!(
+            "Prep '{}' already exists. Use 'wm dive switch {}' to activate it.",
+            name, name
+        ),
+        other => other.to_string(),
+    })?;
+
+    println!("✓ Created dive prep '{}' at .wm/dives/{}.md", name, name);
+    println!("  Switch to it: wm dive switch {}", name);
+
+    Ok(())
+}
+
+/// Create a new named dive prep without printing
+/// Uses a skeleton template when no content is given.
+pub fn create(ws: &Workspace, name: &str, content: Option<&str>) -> crate::Result<()> {
+    ensure_initialized(ws)?;
 
     // Validate name (kebab-case, no special chars)
     if !is_valid_prep_name(name) {
-        return Err(format!(
-            "Invalid prep name '{}'. Use lowercase letters, numbers, and hyphens only.",
-            name
-        ));
+        return Err(Error::InvalidName {
+            kind: "prep",
+            name: name.to_string(),
+            rule: "Use lowercase letters, numbers, and hyphens only.",
+        });
     }
 
-    let path = state::dive_prep_path(name);
+    let path = state::dive_prep_path(ws, name);
     if path.exists() {
-        return Err(format!(
-            "Prep '{}' already exists. Use 'wm dive switch {}' to activate it.",
-            name, name
-        ));
+        return Err(Error::AlreadyExists(format!("Prep '{}'", name)));
     }
 
-    state::ensure_dive_dir().map_err(|e| format!("Failed to create dives directory: {}", e))?;
+    state::ensure_dive_dir(ws).map_err(|e| Error::io("Failed to create dives directory", e))?;
 
     let default_content = format!(
         "# Dive: {}\n\nIntent: \n\n## Focus\n\n## Constraints\n",
         name
     );
     let initial_content = content.unwrap_or(&default_content);
-    fs::write(&path, initial_content).map_err(|e| format!("Failed to create prep: {}", e))?;
-
-    println!("✓ Created dive prep '{}' at .wm/dives/{}.md", name, name);
-    println!("  Switch to it: wm dive switch {}", name);
-
-    Ok(())
+    fs::write(&path, initial_content).map_err(|e| Error::io("Failed to create prep", e))
 }
 
 /// Switch to a named dive prep (set as current)
-pub fn switch(name: &str) -> Result<(), String> {
-    if !state::is_initialized() {
-        return Err("Not initialized. Run 'wm init' first.".to_string());
-    }
-
-    let path = state::dive_prep_path(name);
-    if !path.exists() {
-        return Err(format!(
+pub fn switch(ws: &Workspace, name: &str) -> Result<(), String> {
+    switch_to(ws, name).map_err(|e| match e {
+        Error::NotFound(_) => format!(
             "Prep '{}' not found. Create it with 'wm dive new {}'",
             name, name
-        ));
-    }
-
-    state::set_current_dive(Some(name)).map_err(|e| format!("Failed to update config: {}", e))?;
+        ),
+        other => other.to_string(),
+    })?;
 
     println!("✓ Switched to dive prep '{}'", name);
 
     Ok(())
 }
 
-/// Delete a named dive prep
-pub fn delete(name: &str) -> Result<(), String> {
-    if !state::is_initialized() {
-        return Err("Not initialized. Run 'wm init' first.".to_string());
-    }
+/// Set a named dive prep as current without printing
+/// Used by `switch`, the MCP server (where stdout is the protocol channel) and the library API.
+pub fn switch_to(ws: &Workspace, name: &str) -> crate::Result<()> {
+    ensure_initialized(ws)?;
 
-    let path = state::dive_prep_path(name);
+    let path = state::dive_prep_path(ws, name);
     if !path.exists() {
-        return Err(format!("Prep '{}' not found.", name));
+        return Err(Error::NotFound(format!("Prep '{}'", name)));
     }
 
-    fs::remove_file(&path).map_err(|e| format!("Failed to delete prep: {}", e))?;
+    state::set_current_dive(ws, Some(name)).map_err(|e| Error::io("Failed to update config", e))
+}
 
-    // If this was the current prep, clear it
-    if state::current_dive().as_deref() == Some(name) {
-        state::set_current_dive(None).map_err(|e| format!("Failed to update config: {}", e))?;
+/// Delete a named dive prep
+pub fn delete(ws: &Workspace, name: &str) -> Result<(), String> {
+    if remove(ws, name)? {
         println!("✓ Deleted dive prep '{}' (was current, now cleared)", name);
     } else {
         println!("✓ Deleted dive prep '{}'", name);
@@ -118,9 +136,30 @@ pub fn delete(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Delete a named dive prep without printing
+/// Returns true if it was the current prep (which is then cleared).
+pub fn remove(ws: &Workspace, name: &str) -> crate::Result<bool> {
+    ensure_initialized(ws)?;
+
+    let path = state::dive_prep_path(ws, name);
+    if !path.exists() {
+        return Err(Error::NotFound(format!("Prep '{}'", name)));
+    }
+
+    fs::remove_file(&path).map_err(|e| Error::io("Failed to delete prep", e))?;
+
+    // If this was the current prep, clear it
+    if state::current_dive(ws).as_deref() == Some(name) {
+        state::set_current_dive(ws, None).map_err(|e| Error::io("Failed to update config", e))?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
 /// Save current dive_context.md as a named prep
-pub fn save(name: &str) -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn save(ws: &Workspace, name: &str) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
@@ -133,12 +172,12 @@ pub fn save(name: &str) -> Result<(), String> {
     }
 
     // Read from legacy location
-    let legacy_path = state::wm_path("dive_context.md");
+    let legacy_path = state::wm_path(ws, "dive_context.md");
     let content = fs::read_to_string(&legacy_path)
-        .or_else(|_| fs::read_to_string(state::wm_path("OH_context.md")))
+        .or_else(|_| fs::read_to_string(state::wm_path(ws, "OH_context.md")))
         .map_err(|_| "No dive context found. Use /dive-prep to create one first.".to_string())?;
 
-    let target_path = state::dive_prep_path(name);
+    let target_path = state::dive_prep_path(ws, name);
     if target_path.exists() {
         return Err(format!(
             "Prep '{}' already exists. Delete it first or choose a different name.",
@@ -146,12 +185,13 @@ pub fn save(name: &str) -> Result<(), String> {
         ));
     }
 
-    state::ensure_dive_dir().map_err(|e| format!("Failed to create dives directory: {}", e))?;
+    state::ensure_dive_dir(ws).map_err(|e| format!("Failed to create dives directory: {}", e))?;
 
     fs::write(&target_path, &content).map_err(|e| format!("Failed to save prep: {}", e))?;
 
     // Set as current
-    state::set_current_dive(Some(name)).map_err(|e| format!("Failed to update config: {}", e))?;
+    state::set_current_dive(ws, Some(name))
+        .map_err(|e| format!("Failed to update config: {}", e))?;
 
     println!("✓ Saved current dive context as '{}' (now active)", name);
 
@@ -159,12 +199,12 @@ pub fn save(name: &str) -> Result<(), String> {
 }
 
 /// Show current prep name
-pub fn current() -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn current(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
-    match state::current_dive() {
+    match state::current_dive(ws) {
         Some(name) => println!("{}", name),
         None => println!("(none - using legacy dive_context.md if present)"),
     }
@@ -173,22 +213,22 @@ pub fn current() -> Result<(), String> {
 }
 
 /// Show dive prep content (current or specific)
-pub fn show(name: Option<&str>) -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn show(ws: &Workspace, name: Option<&str>) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
     let content = match name {
         Some(n) => {
             // Show specific prep
-            let path = state::dive_prep_path(n);
+            let path = state::dive_prep_path(ws, n);
             fs::read_to_string(&path).map_err(|_| format!("Prep '{}' not found.", n))?
         }
         None => {
             // Show current prep or legacy fallback
-            match state::current_dive() {
+            match state::current_dive(ws) {
                 Some(current_name) => {
-                    let path = state::dive_prep_path(&current_name);
+                    let path = state::dive_prep_path(ws, &current_name);
                     fs::read_to_string(&path).map_err(|_| {
                         format!(
                             "Current prep '{}' not found (may have been deleted).",
@@ -198,8 +238,8 @@ pub fn show(name: Option<&str>) -> Result<(), String> {
                 }
                 None => {
                     // Legacy fallback
-                    fs::read_to_string(state::wm_path("dive_context.md"))
-                        .or_else(|_| fs::read_to_string(state::wm_path("OH_context.md")))
+                    fs::read_to_string(state::wm_path(ws, "dive_context.md"))
+                        .or_else(|_| fs::read_to_string(state::wm_path(ws, "OH_context.md")))
                         .map_err(|_| "No dive context loaded. Use 'wm dive new <name>' or /dive-prep to create one.".to_string())?
                 }
             }
@@ -215,8 +255,8 @@ pub fn show(name: Option<&str>) -> Result<(), String> {
 // ============================================================================
 
 /// Load a dive pack from OH and optionally save as named prep
-pub fn load(pack_id: &str, save_as: Option<&str>) -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn load(ws: &Workspace, pack_id: &str, save_as: Option<&str>) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
@@ -273,12 +313,13 @@ pub fn load(pack_id: &str, save_as: Option<&str>) -> Result<(), String> {
             ));
         }
 
-        state::ensure_dive_dir().map_err(|e| format!("Failed to create dives directory: {}", e))?;
+        state::ensure_dive_dir(ws)
+            .map_err(|e| format!("Failed to create dives directory: {}", e))?;
 
-        let path = state::dive_prep_path(name);
+        let path = state::dive_prep_path(ws, name);
         fs::write(&path, rendered_md).map_err(|e| format!("Failed to write dive prep: {}", e))?;
 
-        state::set_current_dive(Some(name))
+        state::set_current_dive(ws, Some(name))
             .map_err(|e| format!("Failed to update config: {}", e))?;
 
         println!(
@@ -288,7 +329,7 @@ pub fn load(pack_id: &str, save_as: Option<&str>) -> Result<(), String> {
         );
     } else {
         // Legacy: write to dive_context.md
-        let dive_context_path = state::wm_path("dive_context.md");
+        let dive_context_path = state::wm_path(ws, "dive_context.md");
         fs::write(&dive_context_path, rendered_md)
             .map_err(|e| format!("Failed to write dive_context.md: {}", e))?;
 
@@ -303,19 +344,19 @@ pub fn load(pack_id: &str, save_as: Option<&str>) -> Result<(), String> {
 }
 
 /// Clear the current dive context
-pub fn clear() -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn clear(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
     // Clear current prep setting
-    let had_current = state::current_dive().is_some();
+    let had_current = state::current_dive(ws).is_some();
     if had_current {
-        state::set_current_dive(None).map_err(|e| format!("Failed to update config: {}", e))?;
+        state::set_current_dive(ws, None).map_err(|e| format!("Failed to update config: {}", e))?;
     }
 
     // Also remove legacy file if present
-    let dive_context_path = state::wm_path("dive_context.md");
+    let dive_context_path = state::wm_path(ws, "dive_context.md");
     let had_legacy = dive_context_path.exists();
     if had_legacy {
         fs::remove_file(&dive_context_path)
@@ -360,6 +401,15 @@ pub fn prep(intent: Option<&str>) -> Result<(), String> {
 // Helpers
 // ============================================================================
 
+/// Return NotInitialized unless .wm/ exists
+fn ensure_initialized(ws: &Workspace) -> crate::Result<()> {
+    if state::is_initialized(ws) {
+        Ok(())
+    } else {
+        Err(Error::NotInitialized(state::wm_dir(ws)))
+    }
+}
+
 /// Validate prep name (kebab-case: lowercase letters, numbers, hyphens)
 fn is_valid_prep_name(name: &str) -> bool {
     if name.is_empty() || name.len() > 64 {
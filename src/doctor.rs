@@ -0,0 +1,415 @@
+//! wm doctor - diagnose why memory isn't being captured or injected
+//!
+//! Runs a fixed list of checks and reports each as pass/warn/fail. Warnings
+//! are things that limit wm (nothing distilled yet, compile paused); failures
+//! stop it from working at all (unparseable config, no LLM backend).
+
+use crate::category;
+use crate::codex;
+use crate::migrate;
+use crate::session;
+use crate::shared;
+use crate::state;
+use crate::types::Operation;
+use crate::workspace::Workspace;
+use chrono::Utc;
+use std::path::Path;
+use std::process::Command;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+/// A named check result
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Run wm doctor (`pinned`: the root came from --project-dir or the environment)
+pub fn run(ws: &Workspace, pinned: bool) -> Result<(), String> {
+    let checks = run_checks(ws, pinned);
+
+    for check in &checks {
+        println!(
+            "[{}] {:<10} {}",
+            check.status.label(),
+            check.name,
+            check.detail
+        );
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!();
+    if failed > 0 {
+        return Err(format!("{} check(s) failed, {} warning(s)", failed, warned));
+    }
+    println!("No failures ({} warning(s)).", warned);
+    Ok(())
+}
+
+/// Run all checks in order
+/// AIDEV-NOTE: Checks that need .wm/ are skipped (not failed) when the store is
+/// missing, so the one actionable failure isn't buried under follow-on noise.
+pub fn run_checks(ws: &Workspace, pinned: bool) -> Vec<Check> {
+    let mut checks = vec![check_store(ws, pinned)];
+
+    if state::is_initialized(ws) {
+        checks.extend(check_config(ws));
+        checks.push(check_layout(ws));
+        checks.push(check_operations(ws));
+        checks.extend(check_knowledge_files(ws));
+    }
+
+    checks.push(check_llm());
+    checks.push(check_claude_sessions(ws));
+    checks.push(check_codex_sessions(ws));
+    checks.push(check_hooks(ws));
+    checks.push(check_oh());
+    checks
+}
+
+fn check_store(ws: &Workspace, pinned: bool) -> Check {
+    if !state::is_initialized(ws) {
+        return Check::new(
+            "store",
+            Status::Fail,
+            format!(
+                "No .wm/ found from {} (run 'wm init')",
+                std::env::current_dir().unwrap_or_default().display()
+            ),
+        );
+    }
+
+    let source = if pinned {
+        "explicit project dir"
+    } else {
+        "found from current directory"
+    };
+    Check::new(
+        "store",
+        Status::Pass,
+        format!("{} ({})", state::wm_dir(ws).display(), source),
+    )
+}
+
+fn check_config(ws: &Workspace) -> Vec<Check> {
+    if let Err(e) = state::parse_config(ws) {
+        return vec![Check::new(
+            "config",
+            Status::Fail,
+            format!("{} (extract is treated as paused)", e),
+        )];
+    }
+
+    let unknown: Vec<Check> = state::config_problems(ws)
+        .into_iter()
+        .map(|problem| Check::new("config", Status::Warn, problem))
+        .collect();
+    if !unknown.is_empty() {
+        unknown
+    } else if state::wm_path(ws, "config.toml").exists() {
+        vec![Check::new("config", Status::Pass, "config.toml parses")]
+    } else {
+        vec![Check::new(
+            "config",
+            Status::Pass,
+            "No config.toml (using defaults)",
+        )]
+    }
+}
+
+fn check_layout(ws: &Workspace) -> Check {
+    let version = migrate::store_version(ws);
+    if version > migrate::CURRENT_VERSION {
+        Check::new(
+            "layout",
+            Status::Fail,
+            format!(
+                "Layout v{} is newer than this wm supports (v{}) - upgrade wm",
+                version,
+                migrate::CURRENT_VERSION
+            ),
+        )
+    } else if version < migrate::CURRENT_VERSION {
+        Check::new(
+            "layout",
+            Status::Warn,
+            format!(
+                "Layout v{} (current v{}) - see 'wm migrate --dry-run'",
+                version,
+                migrate::CURRENT_VERSION
+            ),
+        )
+    } else {
+        Check::new("layout", Status::Pass, format!("Layout v{}", version))
+    }
+}
+
+fn check_operations(ws: &Workspace) -> Check {
+    let config = state::read_config(ws);
+    let now = Utc::now();
+    let paused: Vec<&str> = Operation::ALL
+        .into_iter()
+        .filter(|op| config.operations.pause(*op, now).is_some())
+        .map(|op| op.name())
+        .collect();
+
+    if paused.is_empty() {
+        Check::new("operations", Status::Pass, "extract and compile running")
+    } else {
+        Check::new(
+            "operations",
+            Status::Warn,
+            format!("Paused: {} (see 'wm status')", paused.join(", ")),
+        )
+    }
+}
+
+/// One check per knowledge file: exists, size and line count
+fn check_knowledge_files(ws: &Workspace) -> Vec<Check> {
+    let mut files = vec![("state", state::wm_path(ws, "state.md"))];
+    let categories = category::all(ws);
+    for category in &categories {
+        // Check names are static; custom categories are told apart by their path
+        let name = match category.name.as_str() {
+            "guardrails" => "guardrails",
+            "metis" => "metis",
+            _ => "category",
+        };
+        files.push((name, state::wm_path(ws, "distill").join(&category.filename)));
+    }
+    for category in &categories {
+        if shared::shared_path(ws, &category.filename).exists() {
+            files.push(("shared", shared::shared_path(ws, &category.filename)));
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(name, path)| {
+            let relative = path
+                .strip_prefix(state::wm_dir(ws))
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            match std::fs::read_to_string(&path) {
+                Ok(content) if !content.trim().is_empty() => Check::new(
+                    name,
+                    Status::Pass,
+                    format!(
+                        "{} ({} bytes, {} lines)",
+                        relative,
+                        content.len(),
+                        content.lines().count()
+                    ),
+                ),
+                Ok(_) => Check::new(name, Status::Warn, format!("{} is empty", relative)),
+                Err(_) if name == "state" => {
+                    Check::new(name, Status::Warn, format!("{} missing", relative))
+                }
+                Err(_) => Check::new(
+                    name,
+                    Status::Warn,
+                    format!("{} missing (run 'wm distill')", relative),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// The Claude CLI is the only LLM backend; `--version` checks it without a model call
+fn check_llm() -> Check {
+    match Command::new("claude").arg("--version").output() {
+        Ok(output) if output.status.success() => Check::new(
+            "llm",
+            Status::Pass,
+            format!(
+                "claude CLI {}",
+                String::from_utf8_lossy(&output.stdout).trim()
+            ),
+        ),
+        Ok(output) => Check::new(
+            "llm",
+            Status::Fail,
+            format!(
+                "claude --version failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Err(e) => Check::new(
+            "llm",
+            Status::Fail,
+            format!(
+                "claude CLI not runnable ({}) - extract, distill and compress need it",
+                e
+            ),
+        ),
+    }
+}
+
+fn check_claude_sessions(ws: &Workspace) -> Check {
+    let project = ws.root().to_path_buf();
+    match session::get_project_dir(&project) {
+        Some(dir) => {
+            let count = session::discover_sessions_in_dir(&dir)
+                .map(|s| s.len())
+                .unwrap_or(0);
+            Check::new(
+                "claude",
+                Status::Pass,
+                format!("{} session(s) in {}", count, dir.display()),
+            )
+        }
+        None => Check::new(
+            "claude",
+            Status::Warn,
+            format!(
+                "No Claude Code sessions for {} (expected ~/.claude/projects/{})",
+                project.display(),
+                session::compute_project_id(&project)
+            ),
+        ),
+    }
+}
+
+fn check_codex_sessions(ws: &Workspace) -> Check {
+    let Some(dir) = codex::session::codex_sessions_dir().filter(|d| d.exists()) else {
+        return Check::new(
+            "codex",
+            Status::Pass,
+            "Codex not in use (no ~/.codex/sessions)",
+        );
+    };
+
+    let project = ws.root().to_path_buf();
+    let filter = project.to_string_lossy().to_string();
+    match codex::session::discover_sessions(Some(&filter)) {
+        Ok(sessions) if !sessions.is_empty() => Check::new(
+            "codex",
+            Status::Pass,
+            format!("{} session(s) in {}", sessions.len(), dir.display()),
+        ),
+        Ok(_) => Check::new(
+            "codex",
+            Status::Warn,
+            format!("No Codex sessions with cwd under {}", project.display()),
+        ),
+        Err(e) => Check::new("codex", Status::Warn, e),
+    }
+}
+
+fn check_hooks(ws: &Workspace) -> Check {
+    let project = ws.root().to_path_buf();
+    let home = dirs::home_dir().unwrap_or_default();
+    let settings = [
+        project.join(".claude/settings.json"),
+        project.join(".claude/settings.local.json"),
+        home.join(".claude/settings.json"),
+    ];
+
+    if let Some(path) = settings
+        .iter()
+        .find(|p| std::fs::read_to_string(p).is_ok_and(|c| settings_register_wm(&c)))
+    {
+        return Check::new(
+            "hooks",
+            Status::Pass,
+            format!("wm hooks in {}", path.display()),
+        );
+    }
+
+    let installed = home.join(".claude/plugins/installed_plugins.json");
+    if plugin_installed(&installed) {
+        return Check::new("hooks", Status::Pass, "wm plugin installed");
+    }
+
+    Check::new(
+        "hooks",
+        Status::Warn,
+        "wm plugin not found - install it with 'claude plugin install' (see README)",
+    )
+}
+
+/// Whether Claude settings JSON registers a `wm hook ...` command
+fn settings_register_wm(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|json| json.get("hooks").map(|hooks| hooks.to_string()))
+        .is_some_and(|hooks| hooks.contains("wm hook"))
+}
+
+/// Whether Claude's installed plugin registry lists a plugin named wm
+fn plugin_installed(path: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return false;
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return false;
+    };
+    json.get("plugins")
+        .and_then(|p| p.as_object())
+        .is_some_and(|plugins| plugins.keys().any(|k| k == "wm" || k.starts_with("wm@")))
+}
+
+fn check_oh() -> Check {
+    if std::env::var("OH_API_KEY").is_ok_and(|k| !k.is_empty()) {
+        Check::new("oh", Status::Pass, "OH_API_KEY set")
+    } else {
+        Check::new(
+            "oh",
+            Status::Warn,
+            "OH_API_KEY not set (only needed for 'wm distill --push-to-oh' and dive packs)",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_register_wm() {
+        let settings = r#"{"hooks":{"UserPromptSubmit":[{"hooks":[{"type":"command","command":"wm hook compile --session-id x"}]}]}}"#;
+        assert!(settings_register_wm(settings));
+        assert!(!settings_register_wm(r#"{"model":"wm hook"}"#));
+        assert!(!settings_register_wm("not json"));
+    }
+
+    #[test]
+    fn test_plugin_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("installed_plugins.json");
+        std::fs::write(&path, r#"{"plugins":{"wm@cloud-atlas":{}}}"#).unwrap();
+        assert!(plugin_installed(&path));
+        std::fs::write(&path, r#"{"plugins":{"other@x":{}}}"#).unwrap();
+        assert!(!plugin_installed(&path));
+    }
+}
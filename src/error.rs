@@ -0,0 +1,132 @@
+//! Error type for the wm library API
+//!
+//! AIDEV-NOTE: Most internals still return `Result<_, String>` for the CLI.
+//! Library-facing functions return this enum; `From<Error> for String` lets
+//! CLI wrappers keep using `?` while subsystems migrate. LLM failures and lock
+//! timeouts keep their own variants so callers can decide whether to retry.
+
+use crate::llm::{ErrorKind, LlmError};
+use crate::lock::LockTimeout;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Errors returned by the wm library API
+#[derive(Debug)]
+pub enum Error {
+    /// No .wm/ directory at the expected location
+    NotInitialized(PathBuf),
+
+    /// A .wm/ directory already exists
+    AlreadyInitialized(PathBuf),
+
+    /// A name failed validation
+    InvalidName {
+        /// What was being named (e.g. "prep")
+        kind: &'static str,
+        name: String,
+        /// What a valid name looks like
+        rule: &'static str,
+    },
+
+    /// A named item does not exist
+    NotFound(String),
+
+    /// A named item already exists
+    AlreadyExists(String),
+
+    /// Filesystem failure, with what we were doing at the time
+    Io { context: String, source: io::Error },
+
+    /// Another wm process held a .wm/ lock for longer than we waited
+    LockTimeout { path: PathBuf, waited: Duration },
+
+    /// Configuration could not be read or written
+    Config(String),
+
+    /// An LLM call failed; `kind` tells transient failures from permanent ones
+    Llm { kind: ErrorKind, message: String },
+
+    /// Failure reported by a subsystem that still uses string errors (OH, session discovery)
+    Operation(String),
+}
+
+/// Result alias for the wm library API
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap an IO error with context describing the failed operation
+    /// Lock timeouts (see lock.rs) become `LockTimeout`.
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        if let Some(timeout) = source
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<LockTimeout>())
+        {
+            return Error::LockTimeout {
+                path: timeout.path.clone(),
+                waited: timeout.waited,
+            };
+        }
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotInitialized(_) => write!(f, "Not initialized. Run 'wm init' first."),
+            Error::AlreadyInitialized(path) => {
+                write!(f, "Already initialized: {} exists", path.display())
+            }
+            Error::InvalidName { kind, name, rule } => {
+                write!(f, "Invalid {} name '{}'. {}", kind, name, rule)
+            }
+            Error::NotFound(what) => write!(f, "{} not found.", what),
+            Error::AlreadyExists(what) => write!(f, "{} already exists.", what),
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+            Error::LockTimeout { path, waited } => write!(
+                f,
+                "Timed out after {}s waiting for lock on {} (another wm process is writing it)",
+                waited.as_secs(),
+                path.display()
+            ),
+            Error::Config(msg) => write!(f, "Config error: {}", msg),
+            Error::Llm { kind, message } => write!(f, "[{}] {}", kind.label(), message),
+            Error::Operation(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Operation(msg)
+    }
+}
+
+impl From<LlmError> for Error {
+    fn from(e: LlmError) -> Self {
+        Error::Llm {
+            kind: e.kind,
+            message: e.message,
+        }
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,531 @@
+//! Evidence linking distilled items back to transcripts
+//!
+//! Pass 1 asks for a short verbatim user quote behind each insight; wm finds
+//! that quote in the session's transcript and records the session ID, the
+//! transcript entry (UUID for Claude Code) and its timestamp. Pass 2 sees the
+//! insights numbered (`[#3]`) and reports which ones each item came from, so
+//! the evidence survives categorization. The result is kept per item in
+//! .wm/distill/evidence.json, and `wm why <item>` shows it together with the
+//! surrounding transcript excerpt.
+//!
+//! AIDEV-NOTE: When Pass 2 returns no references (an older prompt, or the
+//! marker format dropped them), items fall back to the Pass 1 insights they
+//! resemble (confidence::similar). Marker-format Pass 1 has no quotes, so its
+//! evidence names the session only.
+
+use crate::category;
+use crate::codex::{self, types::CodexEntry};
+use crate::compile;
+use crate::confidence;
+use crate::lock;
+use crate::session;
+use crate::state;
+use crate::transcript::{read_transcript, types::TranscriptEntry};
+use crate::types::{content_hash, strip_xml_tags};
+use crate::workspace::Workspace;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Evidence records within .wm/distill/
+pub const EVIDENCE_FILE: &str = "evidence.json";
+
+/// Longest message text shown in a transcript excerpt
+const EXCERPT_CHARS: usize = 300;
+
+/// Where one Pass 1 insight came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub session_id: String,
+
+    /// Transcript entry holding the quote (UUID for Claude Code sessions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+
+    /// When that entry was written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+
+    /// Short verbatim user quote that motivated the insight
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+
+    /// The Pass 1 insight, before categorization reworded it
+    pub insight: String,
+
+    /// Transcript file, for `wm why`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<PathBuf>,
+}
+
+/// A transcript message, reduced to what evidence needs
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub entry: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub user: bool,
+    pub text: String,
+}
+
+impl Evidence {
+    /// Evidence for a Pass 1 insight, locating `quote` among the user messages
+    pub fn new(
+        session_id: &str,
+        insight: &str,
+        quote: Option<&str>,
+        transcript: Option<&Path>,
+        messages: &[Message],
+    ) -> Self {
+        let quote = quote.map(str::trim).filter(|q| !q.is_empty());
+        let found = quote.and_then(|q| find_quote(messages, q));
+        Evidence {
+            session_id: session_id.to_string(),
+            entry: found.and_then(|m| m.entry.clone()),
+            timestamp: found.and_then(|m| m.timestamp),
+            quote: quote.map(str::to_string),
+            insight: insight.to_string(),
+            transcript: transcript.map(Path::to_path_buf),
+        }
+    }
+}
+
+fn parse_time(timestamp: Option<&str>) -> Option<DateTime<Utc>> {
+    timestamp
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&Utc))
+}
+
+/// User and assistant messages of a Claude Code session
+pub fn claude_messages(entries: &[TranscriptEntry], session_id: &str) -> Vec<Message> {
+    entries
+        .iter()
+        .filter(|e| e.session_id() == Some(session_id))
+        .filter_map(|e| {
+            let (entry, text) = match e {
+                TranscriptEntry::User { uuid, .. } => (uuid, e.user_text()?),
+                TranscriptEntry::Assistant { uuid, .. } => (uuid, e.assistant_text()?),
+                _ => return None,
+            };
+            let text = strip_xml_tags(&text, "<system-reminder>", "</system-reminder>");
+            (!text.trim().is_empty()).then(|| Message {
+                entry: Some(entry.clone()),
+                timestamp: parse_time(e.timestamp()),
+                user: e.is_user(),
+                text: text.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// User and agent messages of a Codex session (entries have no IDs)
+pub fn codex_messages(entries: &[CodexEntry]) -> Vec<Message> {
+    entries
+        .iter()
+        .filter_map(|e| {
+            let (user, text) = match (e.user_message_text(), e.agent_message_text()) {
+                (Some(text), _) => (true, text),
+                (None, Some(text)) => (false, text),
+                _ => return None,
+            };
+            (!text.trim().is_empty()).then(|| Message {
+                entry: None,
+                timestamp: parse_time(Some(&e.timestamp)),
+                user,
+                text: text.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lowercase with whitespace collapsed, for quote matching
+fn squash(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The user message containing `quote` (its longest fragment if elided with "...")
+fn find_quote<'a>(messages: &'a [Message], quote: &str) -> Option<&'a Message> {
+    let fragment = quote
+        .trim_matches(['"', '\'', '“', '”'])
+        .split("...")
+        .flat_map(|part| part.split('…'))
+        .map(squash)
+        .max_by_key(|part| part.len())?;
+    if fragment.is_empty() {
+        return None;
+    }
+    messages
+        .iter()
+        .find(|m| m.user && squash(&m.text).contains(&fragment))
+}
+
+/// Evidence for a Pass 2 item: the Pass 1 insights it cites (1-based `refs`),
+/// or those it resembles when it cites none
+pub fn supporting(item: &str, refs: &[usize], pass1: &[Evidence]) -> Vec<Evidence> {
+    let cited: Vec<&Evidence> = refs
+        .iter()
+        .filter_map(|n| n.checked_sub(1).and_then(|i| pass1.get(i)))
+        .collect();
+    let matched: Vec<&Evidence> = if cited.is_empty() {
+        pass1
+            .iter()
+            .filter(|e| confidence::similar(&e.insight, item))
+            .collect()
+    } else {
+        cited
+    };
+
+    let mut evidence: Vec<Evidence> = Vec::new();
+    for e in matched {
+        if !evidence.contains(e) {
+            evidence.push(e.clone());
+        }
+    }
+    evidence
+}
+
+/// Evidence of one distilled item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Item as last written
+    pub text: String,
+
+    /// Category name it was last written to
+    pub category: String,
+
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+}
+
+/// All records, keyed by a hash of the normalized item text
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Store {
+    #[serde(default)]
+    pub items: BTreeMap<String, Record>,
+}
+
+impl Store {
+    /// Load .wm/distill/evidence.json (empty if missing or unreadable)
+    pub fn load(ws: &Workspace) -> Self {
+        std::fs::read_to_string(path(ws))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record for an item, if it has one
+    pub fn get(&self, item: &str) -> Option<&Record> {
+        self.items.get(&key(item))
+    }
+}
+
+fn path(ws: &Workspace) -> PathBuf {
+    state::wm_path(ws, "distill").join(EVIDENCE_FILE)
+}
+
+fn key(item: &str) -> String {
+    content_hash(&compile::normalize_item(item))
+}
+
+/// Record the evidence of each written item (category name, text, evidence)
+/// Records of items no longer in any category file are dropped.
+pub fn update(ws: &Workspace, items: Vec<(&str, &str, Vec<Evidence>)>) -> Result<(), String> {
+    let path = path(ws);
+    let _lock =
+        lock::acquire(&path).map_err(|e| format!("Failed to lock {}: {}", EVIDENCE_FILE, e))?;
+    let mut store = Store::load(ws);
+
+    for (category, text, evidence) in items {
+        store.items.insert(
+            key(text),
+            Record {
+                text: text.to_string(),
+                category: category.to_string(),
+                evidence,
+            },
+        );
+    }
+
+    let current: std::collections::BTreeSet<String> = category::all(ws)
+        .iter()
+        .flat_map(|category| confidence::local_items(ws, category))
+        .map(|item| key(&item))
+        .collect();
+    store.items.retain(|key, _| current.contains(key));
+
+    let json = serde_json::to_string_pretty(&store)
+        .map_err(|e| format!("Failed to serialize {}: {}", EVIDENCE_FILE, e))?;
+    state::write_atomic(&path, &json)
+        .map_err(|e| format!("Failed to write {}: {}", EVIDENCE_FILE, e))
+}
+
+// ============================================================================
+// Transcript excerpts
+// ============================================================================
+
+/// Transcript of a session: the recorded path, else a search of this project's
+/// Claude Code sessions and then all Codex sessions
+fn transcript_path(ws: &Workspace, evidence: &Evidence) -> Option<PathBuf> {
+    if let Some(path) = evidence.transcript.as_ref().filter(|p| p.exists()) {
+        return Some(path.clone());
+    }
+    let claude = session::discover_sessions(ws.root())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|s| s.session_id == evidence.session_id)
+        .map(|s| s.transcript_path);
+    claude.or_else(|| {
+        codex::discover_sessions(None)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|s| s.session_id == evidence.session_id)
+            .map(|s| s.session_path)
+    })
+}
+
+fn read_messages(path: &Path, session_id: &str) -> Vec<Message> {
+    let is_codex = codex::session::codex_sessions_dir().is_some_and(|dir| path.starts_with(dir));
+    if is_codex {
+        codex::read_codex_session(path)
+            .map(|entries| codex_messages(&entries))
+            .unwrap_or_default()
+    } else {
+        read_transcript(path)
+            .map(|entries| claude_messages(&entries, session_id))
+            .unwrap_or_default()
+    }
+}
+
+/// The messages around the evidence entry, the entry itself marked with ">"
+fn excerpt(messages: &[Message], evidence: &Evidence, context: usize) -> Option<String> {
+    let position = messages
+        .iter()
+        .position(|m| evidence.entry.is_some() && m.entry == evidence.entry)
+        .or_else(|| {
+            evidence
+                .timestamp
+                .and_then(|ts| messages.iter().position(|m| m.timestamp == Some(ts)))
+        })
+        .or_else(|| {
+            let quote = evidence.quote.as_deref()?;
+            let found = find_quote(messages, quote)?;
+            messages.iter().position(|m| std::ptr::eq(m, found))
+        })?;
+
+    let start = position.saturating_sub(context);
+    let end = (position + context + 1).min(messages.len());
+    let lines: Vec<String> = messages[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let text = squash_line(&m.text);
+            format!(
+                "{} {}: {}",
+                if start + i == position { ">" } else { " " },
+                if m.user { "USER" } else { "ASSISTANT" },
+                text
+            )
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// One line of at most EXCERPT_CHARS characters
+fn squash_line(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > EXCERPT_CHARS {
+        let cut: String = line.chars().take(EXCERPT_CHARS).collect();
+        format!("{}...", cut)
+    } else {
+        line
+    }
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Run wm why <item> - show the evidence behind a distilled item
+pub fn run_why(ws: &Workspace, query: &str, context: usize) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+
+    let categories = category::all(ws);
+    let items: Vec<(&category::Category, String)> = categories
+        .iter()
+        .flat_map(|c| {
+            confidence::local_items(ws, c)
+                .into_iter()
+                .map(move |item| (c, item))
+        })
+        .collect();
+    let wanted = compile::normalize_item(query);
+    let exact: Vec<&(&category::Category, String)> = items
+        .iter()
+        .filter(|(_, item)| compile::normalize_item(item) == wanted)
+        .collect();
+    let matches = if exact.is_empty() {
+        items
+            .iter()
+            .filter(|(_, item)| compile::normalize_item(item).contains(&wanted))
+            .collect()
+    } else {
+        exact
+    };
+
+    let (category, item) = match matches.as_slice() {
+        [] => {
+            return Err(format!(
+                "No distilled item matches '{}'. Items are in .wm/distill/ ({}).",
+                query,
+                category::names(&categories)
+            ));
+        }
+        [single] => *single,
+        several => {
+            let listing: Vec<String> = several
+                .iter()
+                .map(|(c, item)| format!("  [{}] {}", c.name, item))
+                .collect();
+            return Err(format!(
+                "'{}' matches {} items; use more of the text:\n{}",
+                query,
+                several.len(),
+                listing.join("\n")
+            ));
+        }
+    };
+
+    println!("{}", item);
+    println!("  {}, .wm/distill/{}", category.name, category.filename);
+
+    let store = Store::load(ws);
+    let evidence = store
+        .get(item)
+        .map(|r| r.evidence.as_slice())
+        .unwrap_or(&[]);
+    if evidence.is_empty() {
+        println!(
+            "\nNo evidence recorded. The item was added by hand or via MCP, distilled before evidence tracking (rerun 'wm distill --force'), or not traced to any session by Pass 2."
+        );
+        return Ok(());
+    }
+
+    let sessions: std::collections::BTreeSet<&str> =
+        evidence.iter().map(|e| e.session_id.as_str()).collect();
+    println!("\nEvidence from {} session(s):", sessions.len());
+
+    let mut transcripts: BTreeMap<String, Option<(PathBuf, Vec<Message>)>> = BTreeMap::new();
+    for (i, e) in evidence.iter().enumerate() {
+        let mut details = Vec::new();
+        if let Some(ts) = e.timestamp {
+            details.push(ts.format("%Y-%m-%d %H:%M").to_string());
+        }
+        if let Some(entry) = &e.entry {
+            details.push(format!("entry {}", entry));
+        }
+        if details.is_empty() {
+            println!("\n{}. Session {}", i + 1, e.session_id);
+        } else {
+            println!(
+                "\n{}. Session {} ({})",
+                i + 1,
+                e.session_id,
+                details.join(", ")
+            );
+        }
+        if let Some(quote) = &e.quote {
+            println!("   Quote: \"{}\"", quote.trim_matches('"'));
+        }
+        println!("   Insight: {}", e.insight);
+
+        let transcript = transcripts.entry(e.session_id.clone()).or_insert_with(|| {
+            transcript_path(ws, e).map(|path| {
+                let messages = read_messages(&path, &e.session_id);
+                (path, messages)
+            })
+        });
+        match transcript {
+            Some((path, messages)) => {
+                println!("   Transcript: {}", path.display());
+                if let Some(excerpt) = excerpt(messages, e, context) {
+                    for line in excerpt.lines() {
+                        println!("   {}", line);
+                    }
+                }
+            }
+            None => println!("   Transcript not found (deleted or from another machine)"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(entry: &str, user: bool, text: &str) -> Message {
+        Message {
+            entry: Some(entry.to_string()),
+            timestamp: None,
+            user,
+            text: text.to_string(),
+        }
+    }
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            message("u1", true, "Fix the formatting please"),
+            message("a1", false, "Please always use tabs? Sure."),
+            message("u2", true, "No - please  ALWAYS use tabs,\nnever spaces."),
+            message("a2", false, "Will do."),
+        ]
+    }
+
+    #[test]
+    fn test_evidence_locates_quote_in_user_messages() {
+        let messages = conversation();
+        let evidence = Evidence::new(
+            "s1",
+            "Use tabs",
+            Some("\"please always use tabs ... never spaces\""),
+            None,
+            &messages,
+        );
+        assert_eq!(evidence.entry.as_deref(), Some("u2"));
+
+        let unquoted = Evidence::new("s1", "Use tabs", None, None, &messages);
+        assert_eq!(unquoted.entry, None);
+        assert_eq!(unquoted.quote, None);
+    }
+
+    #[test]
+    fn test_excerpt_marks_the_evidence_entry() {
+        let messages = conversation();
+        let evidence = Evidence::new("s1", "Use tabs", Some("always use tabs"), None, &messages);
+        assert_eq!(
+            excerpt(&messages, &evidence, 1).unwrap(),
+            "  ASSISTANT: Please always use tabs? Sure.\n> USER: No - please ALWAYS use tabs, never spaces.\n  ASSISTANT: Will do."
+        );
+    }
+
+    #[test]
+    fn test_supporting_uses_refs_then_similarity() {
+        let pass1: Vec<Evidence> = ["Use tabs, not spaces", "Prefer pnpm over npm"]
+            .iter()
+            .map(|insight| Evidence::new("s1", insight, None, None, &[]))
+            .collect();
+
+        let cited = supporting("Indent with tabs", &[2, 2, 9], &pass1);
+        assert_eq!(cited.len(), 1);
+        assert_eq!(cited[0].insight, "Prefer pnpm over npm");
+
+        let similar = supporting("Use tabs, never spaces", &[], &pass1);
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].insight, "Use tabs, not spaces");
+    }
+}
@@ -7,12 +7,20 @@
 //! which broke on transcript rotation/compaction. Now uses proper JSONL parsing
 //! and session-id filtering like superego does.
 
-use crate::llm;
+use crate::history;
+use crate::ignore::IgnoreRules;
+use crate::insight::{self, StateOutput};
+use crate::llm::{self, ErrorKind};
+use crate::lock;
+use crate::prompts;
 use crate::state;
 use crate::transcript::{
-    format_context, get_messages_in_window, get_messages_since, read_transcript,
+    format_context, get_messages_in_window, get_messages_since, ignored_tool_ids, read_transcript,
+    session_facts,
 };
+use crate::workspace::Workspace;
 use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
 use std::path::Path;
 
 /// Carryover window: how many minutes before last_extracted to re-read for context
@@ -24,7 +32,11 @@ const CARRYOVER_WINDOW_MINUTES: i64 = 5;
 /// extract/compile can be triggered automatically by hooks (superego calls `wm extract &`),
 /// so they must not spam error logs in projects without .wm/. User-invoked commands like
 /// show/status still return Err to inform the user. See also: compile::run().
-pub fn run(transcript_path: Option<String>, session_id: Option<String>) -> Result<(), String> {
+pub fn run(
+    ws: &Workspace,
+    transcript_path: Option<String>,
+    session_id: Option<String>,
+) -> Result<(), String> {
     // AIDEV-NOTE: Deprecation warning - extract is being replaced by distill command
     // which uses batch processing with two passes (extraction then categorization).
     // See epic yz-90jh for the full distillation rewrite plan.
@@ -34,38 +46,39 @@ pub fn run(transcript_path: Option<String>, session_id: Option<String>) -> Resul
     );
     eprintln!();
 
-    if !state::is_initialized() {
+    if !state::is_initialized(ws) {
         eprintln!("Not initialized. Run 'wm init' first.");
         return Ok(());
     }
 
-    // Check if extract is paused
-    if !state::is_extract_enabled() {
-        state::log("extract", "Paused via config, skipping");
-        println!("Extract is paused. Use 'wm resume extract' to enable.");
+    // Check if extract is paused (project-wide or for this session)
+    let session = session_id.or_else(|| std::env::var("CLAUDE_SESSION_ID").ok());
+    state::parse_config(ws).map_err(|e| format!("{} - fix it before extracting", e))?;
+    if !state::is_extract_enabled(ws, session.as_deref()) {
+        state::log(ws, "extract", "Paused via config, skipping");
+        println!("Extract is paused. See 'wm status'; 'wm resume extract' to enable.");
         return Ok(());
     }
 
     let transcript = find_transcript(transcript_path)?;
-    let session = session_id.or_else(|| std::env::var("CLAUDE_SESSION_ID").ok());
-    extract_from_transcript(&transcript, session.as_deref())
+    extract_from_transcript(ws, &transcript, session.as_deref())
 }
 
 /// Run from hook (called by sg)
-pub fn run_hook() -> Result<(), String> {
-    if !state::is_initialized() {
+pub fn run_hook(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Ok(()); // Silent success
     }
 
-    // Check if extract is paused
-    if !state::is_extract_enabled() {
-        state::log("extract", "Paused via config, skipping");
+    // Check if extract is paused (project-wide or for this session)
+    let session_id = std::env::var("CLAUDE_SESSION_ID").ok();
+    if !state::is_extract_enabled(ws, session_id.as_deref()) {
+        state::log(ws, "extract", "Paused via config, skipping");
         return Ok(());
     }
 
     let transcript = find_transcript(None)?;
-    let session_id = std::env::var("CLAUDE_SESSION_ID").ok();
-    extract_from_transcript(&transcript, session_id.as_deref())
+    extract_from_transcript(ws, &transcript, session_id.as_deref())
 }
 
 /// Find the transcript file
@@ -111,16 +124,16 @@ fn find_transcript(explicit_path: Option<String>) -> Result<String, String> {
 }
 
 /// Get session-specific state directory
-fn session_state_dir(session_id: Option<&str>) -> std::path::PathBuf {
+fn session_state_dir(ws: &Workspace, session_id: Option<&str>) -> std::path::PathBuf {
     match session_id {
-        Some(sid) => state::wm_path(&format!("sessions/{}", sid)),
-        None => state::wm_path(""),
+        Some(sid) => state::wm_path(ws, &format!("sessions/{}", sid)),
+        None => state::wm_path(ws, ""),
     }
 }
 
 /// Read last_extracted timestamp from session state
-fn read_last_extracted(session_id: Option<&str>) -> Option<DateTime<Utc>> {
-    let state_dir = session_state_dir(session_id);
+fn read_last_extracted(ws: &Workspace, session_id: Option<&str>) -> Option<DateTime<Utc>> {
+    let state_dir = session_state_dir(ws, session_id);
     let state_path = state_dir.join("extraction_state.json");
 
     std::fs::read_to_string(state_path)
@@ -132,8 +145,12 @@ fn read_last_extracted(session_id: Option<&str>) -> Option<DateTime<Utc>> {
 }
 
 /// Write last_extracted timestamp to session state
-fn write_last_extracted(session_id: Option<&str>, timestamp: DateTime<Utc>) -> Result<(), String> {
-    let state_dir = session_state_dir(session_id);
+fn write_last_extracted(
+    ws: &Workspace,
+    session_id: Option<&str>,
+    timestamp: DateTime<Utc>,
+) -> Result<(), String> {
+    let state_dir = session_state_dir(ws, session_id);
 
     // Ensure directory exists
     std::fs::create_dir_all(&state_dir)
@@ -147,19 +164,57 @@ fn write_last_extracted(session_id: Option<&str>, timestamp: DateTime<Utc>) -> R
     let content = serde_json::to_string_pretty(&state)
         .map_err(|e| format!("Failed to serialize state: {}", e))?;
 
-    std::fs::write(state_path, content).map_err(|e| format!("Failed to write state: {}", e))?;
+    state::write_atomic(&state_path, &content)
+        .map_err(|e| format!("Failed to write state: {}", e))?;
 
     Ok(())
 }
 
+/// Three-way merge for state.md when another extraction wrote it concurrently
+/// Starts from `latest` and inserts lines `ours` added relative to `base`, each
+/// after the nearest preceding line it shares with `latest`. Lines `latest`
+/// removed stay removed.
+fn merge_state(base: &str, ours: &str, latest: &str) -> String {
+    let base_lines: HashSet<&str> = base.lines().map(str::trim).collect();
+    let mut result: Vec<&str> = latest.lines().collect();
+    let mut insert_at = 0;
+
+    for line in ours.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(pos) = result.iter().position(|r| r.trim() == trimmed) {
+            insert_at = pos + 1;
+            continue;
+        }
+        if base_lines.contains(trimmed) {
+            continue;
+        }
+        result.insert(insert_at, line);
+        insert_at += 1;
+    }
+
+    let mut merged = result.join("\n");
+    if latest.ends_with('\n') || ours.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged
+}
+
 /// Generative extraction with proper session filtering
 /// AIDEV-NOTE: This is the core extraction logic. Key changes from old impl:
 /// 1. Parse JSONL properly into typed entries
 /// 2. Filter by session_id to prevent cross-session bleed
 /// 3. Use timestamp-based cutoff instead of fragile byte position
 /// 4. Format context with deduplication (system reminders, tool summaries)
-fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> Result<(), String> {
+fn extract_from_transcript(
+    ws: &Workspace,
+    transcript_path: &str,
+    session_id: Option<&str>,
+) -> Result<(), String> {
     state::log(
+        ws,
         "extract",
         &format!(
             "Starting extraction from {} (session: {:?})",
@@ -171,21 +226,40 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
     let transcript_read_at = Utc::now();
 
     // Read current state markdown (or empty if first run)
-    let current_state = std::fs::read_to_string(state::wm_path("state.md")).unwrap_or_default();
+    let current_state = std::fs::read_to_string(state::wm_path(ws, "state.md")).unwrap_or_default();
 
     // Read last extraction timestamp for this session
-    let last_extracted = read_last_extracted(session_id);
-    state::log("extract", &format!("Last extracted: {:?}", last_extracted));
+    let last_extracted = read_last_extracted(ws, session_id);
+    state::log(
+        ws,
+        "extract",
+        &format!("Last extracted: {:?}", last_extracted),
+    );
 
     // Parse transcript JSONL
     let entries = read_transcript(Path::new(transcript_path))
         .map_err(|e| format!("Failed to read transcript: {}", e))?;
 
     state::log(
+        ws,
         "extract",
         &format!("Parsed {} transcript entries", entries.len()),
     );
 
+    let rules = IgnoreRules::load(ws);
+    if let Some(session_id) = session_id
+        && let Some(reason) = rules.session_exclusion(&session_facts(&entries, session_id))
+    {
+        state::log(
+            ws,
+            "extract",
+            &format!("Session ignored ({}), skipping", reason),
+        );
+        println!("Session is excluded from extraction: {}.", reason);
+        return Ok(());
+    }
+    let ignored = ignored_tool_ids(&entries, &rules);
+
     // AIDEV-NOTE: Carryover context - re-read N minutes before last_extracted
     // This provides continuity without unbounded context growth (same pattern as sg)
     let carryover_context = if let Some(cutoff) = last_extracted {
@@ -194,6 +268,7 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
 
         if !carryover_messages.is_empty() {
             state::log(
+                ws,
                 "extract",
                 &format!(
                     "Including {} carryover messages from past {} minutes",
@@ -201,7 +276,7 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
                     CARRYOVER_WINDOW_MINUTES
                 ),
             );
-            let formatted = format_context(&carryover_messages);
+            let formatted = format_context(&carryover_messages, &ignored);
             if !formatted.trim().is_empty() {
                 Some(formatted)
             } else {
@@ -218,45 +293,58 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
     let messages = get_messages_since(&entries, last_extracted, session_id);
 
     if messages.is_empty() {
-        state::log("extract", "No new messages for this session, skipping");
+        state::log(ws, "extract", "No new messages for this session, skipping");
         println!("No new transcript content to extract from.");
         return Ok(());
     }
 
     state::log(
+        ws,
         "extract",
         &format!("Processing {} new messages", messages.len()),
     );
 
     // Format messages for LLM (with deduplication)
-    let formatted_transcript = format_context(&messages);
+    let formatted_transcript = format_context(&messages, &ignored);
 
     if formatted_transcript.trim().is_empty() {
-        state::log("extract", "Formatted transcript is empty, skipping");
+        state::log(ws, "extract", "Formatted transcript is empty, skipping");
         println!("No extractable content in new messages.");
         return Ok(());
     }
 
     // Call LLM with current state + carryover + new transcript → get extraction result
     let extraction = call_generative_extraction(
+        ws,
         &current_state,
         &formatted_transcript,
         carryover_context.as_deref(),
+        session_id,
     )?;
 
     // Only write if there's new knowledge
     if extraction.has_knowledge {
-        // Write updated state markdown with atomic rename
-        // AIDEV-NOTE: Write to .tmp file then rename to prevent corruption
-        // if multiple sessions write concurrently (last writer wins, but no corruption)
-        let state_path = state::wm_path("state.md");
-        let tmp_path = state::wm_path("state.md.tmp");
-        std::fs::write(&tmp_path, &extraction.content)
-            .map_err(|e| format!("Failed to write state temp file: {}", e))?;
-        std::fs::rename(&tmp_path, &state_path)
-            .map_err(|e| format!("Failed to rename state file: {}", e))?;
+        // AIDEV-NOTE: The LLM call runs without the lock since it can take a while.
+        // Under the lock, re-read state.md: if another session wrote it meanwhile,
+        // merge our additions into the latest version instead of overwriting it.
+        let state_path = state::wm_path(ws, "state.md");
+        let _lock =
+            lock::acquire(&state_path).map_err(|e| format!("Failed to lock state.md: {}", e))?;
+        let latest = std::fs::read_to_string(&state_path).unwrap_or_default();
+        let content = if latest == current_state {
+            extraction.content
+        } else {
+            state::log(
+                ws,
+                "extract",
+                "state.md changed during extraction, merging into latest",
+            );
+            merge_state(&current_state, &extraction.content, &latest)
+        };
+        history::write_tracked(ws, "state.md", &content, "extract")?;
 
         state::log(
+            ws,
             "extract",
             &format!(
                 "Complete - {} messages processed, knowledge extracted",
@@ -270,6 +358,7 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
         );
     } else {
         state::log(
+            ws,
             "extract",
             &format!(
                 "Complete - {} messages processed, no new knowledge",
@@ -286,7 +375,7 @@ fn extract_from_transcript(transcript_path: &str, session_id: Option<&str>) -> R
     // Update last_extracted for this session regardless of whether we wrote
     // AIDEV-NOTE: Use transcript_read_at (captured before reading) to avoid
     // missing messages that arrived during LLM evaluation. Same fix as sg.
-    write_last_extracted(session_id, transcript_read_at)?;
+    write_last_extracted(ws, session_id, transcript_read_at)?;
 
     Ok(())
 }
@@ -297,54 +386,42 @@ struct ExtractionResult {
     content: String,
 }
 
+/// Output instructions for structured (JSON) output
+const EXTRACT_JSON_FORMAT: &str = r#"RESPONSE FORMAT:
+
+Respond with JSON matching the provided schema. If you found tacit knowledge
+worth capturing, set has_knowledge to true and state to the complete markdown
+content (existing state + new insights). If nothing is worth capturing, set
+has_knowledge to false and leave state empty."#;
+
+/// Output instructions for the marker format (fallback, parsed by parse_marker_response)
+const EXTRACT_FORMAT: &str = r#"RESPONSE FORMAT:
+
+If you found tacit knowledge worth capturing, respond:
+HAS_KNOWLEDGE: YES
+
+<your markdown content here - existing state + new insights>
+
+If nothing worth capturing, respond:
+HAS_KNOWLEDGE: NO"#;
+
 /// Call LLM with generative approach: current state + transcript → extraction result
 /// AIDEV-NOTE: carryover_context provides continuity by including recent messages
 /// from before the current extraction window (same pattern as sg)
 fn call_generative_extraction(
+    ws: &Workspace,
     current_state: &str,
     new_transcript: &str,
     carryover_context: Option<&str>,
+    session_id: Option<&str>,
 ) -> Result<ExtractionResult, String> {
     // AIDEV-NOTE: wm is the RECORDER role - captures learning without authority to enforce.
     // Learning stays "plastic" here until promoted to OH as guardrails/metis.
     // Focus on RATIONALE (why), not just decisions (what).
-    // AIDEV-NOTE: Uses text-based markers like sg does - LLMs reliably follow this format
-    // and lenient parsing handles markdown wrapping. JSON format was unreliable.
-    let system_prompt = r#"You are capturing tacit knowledge that will help future AI sessions.
-
-Tacit knowledge is the wisdom that emerges from HOW someone works, not what they explicitly say. The user might not realize they're teaching you these patterns.
-
-CAPTURE:
-- Rationale behind decisions (WHY this approach, not just WHAT was done)
-- Paths rejected and why (the judgment in pruning options)
-- Constraints discovered through friction
-- Preferences revealed by corrections
-- Patterns the user follows without stating
-
-EXAMPLES OF GOOD CAPTURE:
-- "Prefers asking before implementing when architecture is unclear"
-- "Values failing fast over silent error handling"
-- "Rejected X approach because Y - prefers Z pattern"
-
-DO NOT CAPTURE:
-- What happened ("Fixed X", "Updated Y")
-- Explicit requests or questions
-- Tool outputs or code snippets
-- Anything Claude said
-
-THE TEST: Would a new Claude session find this useful 6 months from now? Is it about HOW to work with this user/codebase, not WHAT happened today?
-
-Most sessions have no tacit insights worth capturing. That's normal.
-
-RESPONSE FORMAT:
-
-If you found tacit knowledge worth capturing, respond:
-HAS_KNOWLEDGE: YES
-
-<your markdown content here - existing state + new insights>
-
-If nothing worth capturing, respond:
-HAS_KNOWLEDGE: NO"#;
+    // AIDEV-NOTE: Asks for schema-validated JSON when the CLI supports it, so a
+    // state that itself contains marker-like lines can't be misparsed. Without
+    // structured output it falls back to text markers like sg does.
+    let prompt = prompts::load(ws, "extract");
 
     // Build message with optional carryover context
     let carryover_section = match carryover_context {
@@ -362,14 +439,17 @@ HAS_KNOWLEDGE: NO"#;
 
     // DEBUG: Log what we're sending
     state::log(
+        ws,
         "extract",
         &format!("Message length: {} bytes", message.len()),
     );
     state::log(
+        ws,
         "extract",
-        &format!("System prompt length: {} bytes", system_prompt.len()),
+        &format!("Prompt length: {} bytes", prompt.len()),
     );
     state::log(
+        ws,
         "extract",
         &format!(
             "Message preview (first 500): {}",
@@ -377,12 +457,65 @@ HAS_KNOWLEDGE: NO"#;
         ),
     );
 
+    if llm::structured_output_available(ws) {
+        let system_prompt = format!("{}\n{}", prompt, EXTRACT_JSON_FORMAT);
+        match llm::call_claude_json::<StateOutput>(
+            ws,
+            "extract",
+            session_id,
+            None,
+            &system_prompt,
+            &message,
+            &insight::state_schema(),
+        ) {
+            Ok(output) => {
+                return Ok(ExtractionResult {
+                    has_knowledge: output.has_knowledge && !output.state.trim().is_empty(),
+                    content: output.state.trim().to_string(),
+                });
+            }
+            Err(e) if e.kind == ErrorKind::Unsupported => {
+                state::log(
+                    ws,
+                    "extract",
+                    "Structured output unsupported, using markers",
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
     // Use shared LLM utilities
-    let result_str = llm::call_claude(system_prompt, &message)?;
-    let response = llm::parse_marker_response(&result_str, "HAS_KNOWLEDGE");
+    let system_prompt = format!("{}\n{}", prompt, EXTRACT_FORMAT);
+    let result_str = llm::call_claude(ws, "extract", session_id, None, &system_prompt, &message)?;
+    let response = llm::parse_marker_response(ws, &result_str, "HAS_KNOWLEDGE");
 
     Ok(ExtractionResult {
         has_knowledge: response.is_positive,
         content: response.content,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_state_keeps_both_writers_additions() {
+        let base = "# Prefs\n- small commits\n";
+        let latest = "# Prefs\n- small commits\n- from other session\n";
+        let ours = "# Prefs\n- small commits\n- from this session\n";
+        assert_eq!(
+            merge_state(base, ours, latest),
+            "# Prefs\n- small commits\n- from this session\n- from other session\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_state_respects_removals_in_latest() {
+        let base = "- old\n- keep\n";
+        let latest = "- keep\n";
+        let ours = "- old\n- keep\n- new\n";
+        assert_eq!(merge_state(base, ours, latest), "- keep\n- new\n");
+    }
+}
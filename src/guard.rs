@@ -0,0 +1,299 @@
+//! Machine-checkable guardrails enforced at tool-call time
+//!
+//! Guardrails in distill/guardrails.md are advisory text. Rules in
+//! .wm/guardrails.toml pair a command regex or path glob with an explanation,
+//! and the PreToolUse hook checks each Bash command / file edit against them.
+//!
+//! AIDEV-NOTE: Rules live in their own file rather than inline in guardrails.md
+//! because distill regenerates guardrails.md from scratch on every run.
+
+use crate::state;
+use crate::types::{HookResponse, HookSpecificOutput, glob_match};
+use crate::workspace::Workspace;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Rules file within .wm/
+const RULES_FILE: &str = "guardrails.toml";
+
+/// Tools whose `file_path` (or `notebook_path`) is checked against path rules
+const FILE_TOOLS: &[&str] = &["Edit", "Write", "MultiEdit", "NotebookEdit"];
+
+/// What to do when a rule matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardAction {
+    /// Deny the tool call; the message is shown to the agent
+    #[default]
+    Block,
+    /// Ask the user to confirm; the message explains why
+    Warn,
+}
+
+/// A single machine-checkable guardrail
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuardRule {
+    /// Regex matched against Bash commands
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Glob (relative to project root) matched against edited file paths
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Explanation surfaced when the rule fires
+    pub message: String,
+
+    #[serde(default)]
+    pub action: GuardAction,
+
+    /// `command` compiled once at load (None if absent or invalid)
+    #[serde(skip)]
+    command_regex: Option<Regex>,
+}
+
+/// On-disk format of .wm/guardrails.toml
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<GuardRule>,
+}
+
+/// Decision for a tool call that matched one or more rules
+#[derive(Debug, PartialEq, Eq)]
+pub struct GuardDecision {
+    pub action: GuardAction,
+    pub reason: String,
+}
+
+/// Load rules from .wm/guardrails.toml (empty if missing or unparseable)
+pub fn load_rules(ws: &Workspace) -> Vec<GuardRule> {
+    let path = state::wm_path(ws, RULES_FILE);
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+
+    match parse_rules(&content) {
+        Ok(rules) => {
+            for rule in &rules {
+                if let (Some(pattern), None) = (&rule.command, &rule.command_regex)
+                    && let Err(e) = Regex::new(pattern)
+                {
+                    state::log(
+                        ws,
+                        "guard",
+                        &format!("Invalid command regex {:?}: {}", pattern, e),
+                    );
+                }
+            }
+            rules
+        }
+        Err(e) => {
+            state::log(
+                ws,
+                "guard",
+                &format!("Failed to parse {}: {}", RULES_FILE, e),
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Parse rules and compile their command regexes (invalid ones never match)
+fn parse_rules(content: &str) -> Result<Vec<GuardRule>, toml::de::Error> {
+    let mut rules = toml::from_str::<RulesFile>(content)?.rules;
+    for rule in &mut rules {
+        rule.command_regex = rule.command.as_deref().and_then(|p| Regex::new(p).ok());
+    }
+    Ok(rules)
+}
+
+/// Run from PreToolUse hook - reads the tool call payload from stdin
+/// Never blocks on internal errors - a broken rules file must not stop all tools.
+pub fn run_hook(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Ok(());
+    }
+
+    let input = read_hook_input().unwrap_or_default();
+    let tool_name = input
+        .get("tool_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let tool_input = input
+        .get("tool_input")
+        .cloned()
+        .unwrap_or(serde_json::Value::Null);
+
+    let rules = load_rules(ws);
+    let Some(decision) = check(&rules, ws.root(), tool_name, &tool_input) else {
+        return Ok(());
+    };
+
+    state::log(
+        ws,
+        "guard",
+        &format!("{:?} {}: {}", decision.action, tool_name, decision.reason),
+    );
+
+    let permission_decision = match decision.action {
+        GuardAction::Block => "deny",
+        GuardAction::Warn => "ask",
+    };
+    let response = HookResponse {
+        hook_specific_output: Some(HookSpecificOutput {
+            hook_event_name: "PreToolUse".to_string(),
+            additional_context: None,
+            permission_decision: Some(permission_decision.to_string()),
+            permission_decision_reason: Some(decision.reason),
+        }),
+    };
+
+    let json = serde_json::to_string(&response).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Check a tool call against rules
+///
+/// Returns None if nothing matched. If several rules match, any block wins
+/// over warn and all matching messages are included in the reason.
+pub fn check(
+    rules: &[GuardRule],
+    root: &Path,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+) -> Option<GuardDecision> {
+    let command = (tool_name == "Bash")
+        .then(|| tool_input.get("command").and_then(|v| v.as_str()))
+        .flatten();
+    let file_path = FILE_TOOLS
+        .contains(&tool_name)
+        .then(|| {
+            tool_input
+                .get("file_path")
+                .or_else(|| tool_input.get("notebook_path"))
+                .and_then(|v| v.as_str())
+        })
+        .flatten()
+        .map(|path| relative_to_project(root, path));
+
+    let matched: Vec<&GuardRule> = rules
+        .iter()
+        .filter(|rule| {
+            let command_hit = match (&rule.command_regex, command) {
+                (Some(re), Some(cmd)) => re.is_match(cmd),
+                _ => false,
+            };
+            let path_hit = match (&rule.path, &file_path) {
+                (Some(pattern), Some(path)) => glob_match(pattern, path),
+                _ => false,
+            };
+            command_hit || path_hit
+        })
+        .collect();
+
+    if matched.is_empty() {
+        return None;
+    }
+
+    let action = if matched.iter().any(|r| r.action == GuardAction::Block) {
+        GuardAction::Block
+    } else {
+        GuardAction::Warn
+    };
+    let reason = matched
+        .iter()
+        .map(|r| format!("wm guardrail: {}", r.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(GuardDecision { action, reason })
+}
+
+/// Make an absolute file path relative to the project root for glob matching
+fn relative_to_project(root: &Path, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Read the PreToolUse payload from stdin
+fn read_hook_input() -> Option<serde_json::Value> {
+    use std::io::{self, Read};
+
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer).ok()?;
+    serde_json::from_str(&buffer).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules() -> Vec<GuardRule> {
+        parse_rules(
+            r#"
+[[rule]]
+command = 'git\s+push\s+.*(--force|-f\b)'
+message = "Never force push"
+
+[[rule]]
+path = "src/generated/**"
+message = "Generated code - edit the schema instead"
+action = "warn"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_blocks_command() {
+        let decision = check(
+            &rules(),
+            Path::new("/repo"),
+            "Bash",
+            &json!({"command": "git push origin main --force"}),
+        )
+        .unwrap();
+        assert_eq!(decision.action, GuardAction::Block);
+        assert_eq!(decision.reason, "wm guardrail: Never force push");
+    }
+
+    #[test]
+    fn test_check_warns_on_path() {
+        let decision = check(
+            &rules(),
+            Path::new("/repo"),
+            "Edit",
+            &json!({"file_path": "src/generated/api.rs"}),
+        )
+        .unwrap();
+        assert_eq!(decision.action, GuardAction::Warn);
+    }
+
+    #[test]
+    fn test_check_no_match() {
+        assert!(
+            check(
+                &rules(),
+                Path::new("/repo"),
+                "Bash",
+                &json!({"command": "git push"})
+            )
+            .is_none()
+        );
+        assert!(
+            check(
+                &rules(),
+                Path::new("/repo"),
+                "Read",
+                &json!({"file_path": "src/generated/a.rs"})
+            )
+            .is_none()
+        );
+    }
+}
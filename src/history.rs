@@ -0,0 +1,398 @@
+//! Snapshot history for knowledge files
+//!
+//! Every write to state.md and the distilled category files (distill/guardrails.md,
+//! distill/metis.md, ...) goes through `write_tracked`, which records the new
+//! content in .wm/history/ along with when it was written and by which command.
+//! `wm history`, `wm diff` and `wm restore` browse and roll back those snapshots.
+//!
+//! AIDEV-NOTE: Snapshots hold the content *after* each write. The first tracked
+//! write to a file that already has content also records that content as a
+//! "baseline" snapshot, so pre-history knowledge can always be restored.
+
+use crate::lock;
+use crate::state;
+use crate::workspace::Workspace;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// History directory within .wm/
+pub const HISTORY_DIR: &str = "history";
+
+/// Snapshot index within the history directory (one JSON object per line)
+const INDEX_FILE: &str = "index.jsonl";
+
+/// Unchanged lines shown around each change in `wm diff`
+const DIFF_CONTEXT: usize = 3;
+
+/// Metadata for one snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unique ID, also the snapshot's filename stem
+    pub id: String,
+
+    /// Tracked file, relative to .wm/
+    pub file: String,
+
+    /// Command that wrote this content
+    pub command: String,
+
+    pub timestamp: DateTime<Utc>,
+
+    /// Line count of the snapshot content
+    pub lines: usize,
+}
+
+fn history_dir(ws: &Workspace) -> PathBuf {
+    state::wm_path(ws, HISTORY_DIR)
+}
+
+fn snapshot_path(ws: &Workspace, id: &str) -> PathBuf {
+    history_dir(ws).join(format!("{}.md", id))
+}
+
+/// List all snapshots, oldest first
+pub fn list(ws: &Workspace) -> Vec<Snapshot> {
+    let content = fs::read_to_string(history_dir(ws).join(INDEX_FILE)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Find a snapshot by ID (or unique ID prefix)
+pub fn find(ws: &Workspace, id: &str) -> Result<Snapshot, String> {
+    let mut matches: Vec<Snapshot> = list(ws)
+        .into_iter()
+        .filter(|s| s.id.starts_with(id))
+        .collect();
+    if let Some(exact) = matches.iter().position(|s| s.id == id) {
+        return Ok(matches.swap_remove(exact));
+    }
+    if matches.len() > 1 {
+        return Err(format!(
+            "Snapshot '{}' is ambiguous ({} matches). Use more of the ID.",
+            id,
+            matches.len()
+        ));
+    }
+    matches
+        .pop()
+        .ok_or_else(|| format!("Snapshot '{}' not found. See 'wm history'.", id))
+}
+
+/// Read a snapshot's content
+pub fn read(ws: &Workspace, snapshot: &Snapshot) -> Result<String, String> {
+    fs::read_to_string(snapshot_path(ws, &snapshot.id))
+        .map_err(|e| format!("Failed to read snapshot {}: {}", snapshot.id, e))
+}
+
+/// Write a tracked knowledge file atomically and snapshot the result
+/// `file` is relative to .wm/; `command` names what produced the content.
+/// History failures are logged, never fatal - the write itself matters more.
+/// AIDEV-NOTE: Callers doing read-modify-write hold the lock on `file` itself;
+/// this only takes the history index lock, so the two never nest the same lock.
+pub fn write_tracked(
+    ws: &Workspace,
+    file: &str,
+    content: &str,
+    command: &str,
+) -> Result<(), String> {
+    let path = state::wm_path(ws, file);
+    let previous = fs::read_to_string(&path).ok();
+    let mut retention = state::read_config(ws).history.retention;
+
+    let _history_lock = if retention > 0 {
+        match lock::acquire(&history_dir(ws).join(INDEX_FILE)) {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                state::log(
+                    ws,
+                    "history",
+                    &format!("Skipping snapshot of {}: {}", file, e),
+                );
+                retention = 0;
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if retention > 0
+        && let Some(previous) = previous.as_deref()
+        && !previous.trim().is_empty()
+        && previous != content
+        && !list(ws).iter().any(|s| s.file == file)
+        && let Err(e) = record(ws, file, previous, "baseline")
+    {
+        state::log(
+            ws,
+            "history",
+            &format!("Failed to snapshot {}: {}", file, e),
+        );
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    state::write_atomic(&path, content).map_err(|e| format!("Failed to write {}: {}", file, e))?;
+
+    if retention > 0 && previous.as_deref() != Some(content) {
+        if let Err(e) = record(ws, file, content, command) {
+            state::log(
+                ws,
+                "history",
+                &format!("Failed to snapshot {}: {}", file, e),
+            );
+        }
+        if let Err(e) = prune(ws, retention) {
+            state::log(ws, "history", &format!("Failed to prune history: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+/// Add existing content to history without writing the file itself
+/// Used to bring pre-history backups under `wm history`.
+pub fn import(ws: &Workspace, file: &str, content: &str, command: &str) -> Result<(), String> {
+    let _lock = lock::acquire(&history_dir(ws).join(INDEX_FILE))
+        .map_err(|e| format!("Failed to lock history: {}", e))?;
+    record(ws, file, content, command)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to snapshot {}: {}", file, e))
+}
+
+/// Store a snapshot and append it to the index
+fn record(ws: &Workspace, file: &str, content: &str, command: &str) -> std::io::Result<Snapshot> {
+    fs::create_dir_all(history_dir(ws))?;
+
+    let timestamp = Utc::now();
+    let stem = file
+        .rsplit('/')
+        .next()
+        .unwrap_or(file)
+        .trim_end_matches(".md");
+    let base_id = format!("{}-{}", timestamp.format("%Y%m%dT%H%M%S%3fZ"), stem);
+    let mut id = base_id.clone();
+    let mut n = 2;
+    while snapshot_path(ws, &id).exists() {
+        id = format!("{}-{}", base_id, n);
+        n += 1;
+    }
+
+    fs::write(snapshot_path(ws, &id), content)?;
+
+    let snapshot = Snapshot {
+        id,
+        file: file.to_string(),
+        command: command.to_string(),
+        timestamp,
+        lines: content.lines().count(),
+    };
+    let mut index = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_dir(ws).join(INDEX_FILE))?;
+    writeln!(index, "{}", serde_json::to_string(&snapshot)?)?;
+
+    Ok(snapshot)
+}
+
+/// Keep only the newest `retention` snapshots of each file
+fn prune(ws: &Workspace, retention: usize) -> std::io::Result<()> {
+    let snapshots = list(ws);
+    let mut keep: Vec<&Snapshot> = Vec::new();
+
+    for snapshot in snapshots.iter().rev() {
+        let newer = keep.iter().filter(|s| s.file == snapshot.file).count();
+        if newer < retention {
+            keep.push(snapshot);
+        } else {
+            let _ = fs::remove_file(snapshot_path(ws, &snapshot.id));
+        }
+    }
+
+    if keep.len() == snapshots.len() {
+        return Ok(());
+    }
+
+    let mut index = String::new();
+    for snapshot in keep.iter().rev() {
+        index.push_str(&serde_json::to_string(snapshot)?);
+        index.push('\n');
+    }
+    fs::write(history_dir(ws).join(INDEX_FILE), index)
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Run wm history - list snapshots, newest first
+pub fn run_list(ws: &Workspace, file: Option<&str>) -> Result<(), String> {
+    ensure_initialized(ws)?;
+
+    let snapshots: Vec<Snapshot> = list(ws)
+        .into_iter()
+        .rev()
+        .filter(|s| file.is_none_or(|f| s.file == f || s.file.ends_with(&format!("/{}", f))))
+        .collect();
+
+    if snapshots.is_empty() {
+        println!("No snapshots yet.");
+        return Ok(());
+    }
+
+    for s in snapshots {
+        println!(
+            "{}  {}  {:<22} {:<10} {} lines",
+            s.id,
+            s.timestamp.format("%Y-%m-%d %H:%M"),
+            s.file,
+            s.command,
+            s.lines
+        );
+    }
+    Ok(())
+}
+
+/// Run wm diff <a> [b] - compare two snapshots (or a snapshot with the current file)
+pub fn run_diff(ws: &Workspace, a: &str, b: Option<&str>) -> Result<(), String> {
+    ensure_initialized(ws)?;
+
+    let old = find(ws, a)?;
+    let old_content = read(ws, &old)?;
+    let (new_label, new_content) = match b {
+        Some(b) => {
+            let new = find(ws, b)?;
+            (new.id.clone(), read(ws, &new)?)
+        }
+        None => (
+            format!("{} (current)", old.file),
+            fs::read_to_string(state::wm_path(ws, &old.file)).unwrap_or_default(),
+        ),
+    };
+
+    println!("--- {}", old.id);
+    println!("+++ {}", new_label);
+    let diff = diff_lines(&old_content, &new_content);
+    if diff.is_empty() {
+        println!("(no differences)");
+    } else {
+        print!("{}", diff);
+    }
+    Ok(())
+}
+
+/// Run wm restore <snapshot> - write a snapshot back to its file
+pub fn run_restore(ws: &Workspace, id: &str) -> Result<(), String> {
+    ensure_initialized(ws)?;
+
+    let snapshot = find(ws, id)?;
+    let content = read(ws, &snapshot)?;
+    write_tracked(
+        ws,
+        &snapshot.file,
+        &content,
+        &format!("restore {}", snapshot.id),
+    )?;
+
+    state::log(
+        ws,
+        "history",
+        &format!("Restored {} from {}", snapshot.file, snapshot.id),
+    );
+    println!("Restored .wm/{} from {}", snapshot.file, snapshot.id);
+    Ok(())
+}
+
+fn ensure_initialized(ws: &Workspace) -> Result<(), String> {
+    if state::is_initialized(ws) {
+        Ok(())
+    } else {
+        Err("Not initialized. Run 'wm init' first.".to_string())
+    }
+}
+
+/// Line diff with a few lines of context around each change
+fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    let near_change = |k: usize| {
+        changed
+            .iter()
+            .any(|&c| k + DIFF_CONTEXT >= c && k <= c + DIFF_CONTEXT)
+    };
+
+    let mut output = String::new();
+    let mut skipped = false;
+    for (k, (marker, line)) in ops.iter().enumerate() {
+        if near_change(k) {
+            if skipped && !output.is_empty() {
+                output.push_str("  ...\n");
+            }
+            skipped = false;
+            output.push_str(&format!("{} {}\n", marker, line));
+        } else {
+            skipped = true;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines_marks_changes() {
+        let diff = diff_lines("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(diff, "  a\n- b\n  c\n+ d\n");
+        assert!(diff_lines("same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn test_diff_lines_elides_distant_context() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new = old.replace("10\n", "ten\n");
+        let diff = diff_lines(&old, &new);
+        assert!(diff.starts_with("  7\n"));
+        assert!(diff.contains("- 10\n+ ten\n"));
+        assert!(!diff.contains("  ...\n"));
+        assert!(!diff.contains("  1\n"));
+    }
+}
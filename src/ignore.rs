@@ -0,0 +1,290 @@
+//! Exclusion rules for extraction
+//!
+//! Two sources decide what is never mined for knowledge:
+//!
+//! - `.wm/ignore`: gitignore-style path patterns. Tool calls that read or write
+//!   a matching file (or a shell command that names one) are shown without
+//!   their input, and their results are dropped from the prompt.
+//! - `[ignore]` in config: whole sessions (by ID, date, or a marker such as
+//!   `#wm:private` in the first user message) and shell commands by glob.
+//!
+//! AIDEV-NOTE: Rules are applied before format_context builds the prompt, in
+//! both the Claude and Codex readers, so ignored content never reaches the LLM.
+
+use crate::state;
+use crate::types::{IgnoreConfig, glob_match};
+use crate::workspace::Workspace;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use std::path::{Path, PathBuf};
+
+/// Path patterns file within .wm/
+pub const IGNORE_FILE: &str = "ignore";
+
+/// One line of .wm/ignore
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PathPattern {
+    glob: String,
+
+    /// `!pattern` re-includes paths an earlier pattern ignored
+    negated: bool,
+
+    /// Contains a `/` (other than a trailing one): matched from the project root
+    anchored: bool,
+
+    /// Trailing `/`: only matches directories (so, paths beneath them)
+    dir_only: bool,
+}
+
+impl PathPattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.trim().is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let glob = line.trim_start_matches('/').to_string();
+        (!glob.is_empty()).then_some(PathPattern {
+            glob,
+            negated,
+            anchored,
+            dir_only,
+        })
+    }
+
+    /// Whether a project-relative path is this pattern or lies beneath it
+    fn matches(&self, path: &str) -> bool {
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        // Candidate matches are prefixes of the path (a directory pattern covers
+        // everything beneath it); unanchored patterns may start at any component
+        let starts = if self.anchored { 0..1 } else { 0..parts.len() };
+        starts.into_iter().any(|start| {
+            (start + 1..=parts.len()).any(|end| {
+                (!self.dir_only || end < parts.len())
+                    && glob_match(&self.glob, &parts[start..end].join("/"))
+            })
+        })
+    }
+}
+
+/// Inclusive range of local dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateRange {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+impl DateRange {
+    /// Parse `YYYY-MM-DD` or `YYYY-MM-DD..YYYY-MM-DD`
+    fn parse(spec: &str) -> Option<Self> {
+        let date = |s: &str| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok();
+        match spec.split_once("..") {
+            Some((from, to)) => Some(DateRange {
+                from: date(from)?,
+                to: date(to)?,
+            }),
+            None => date(spec).map(|d| DateRange { from: d, to: d }),
+        }
+    }
+
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+}
+
+/// What a session looks like, for session-level rules
+#[derive(Debug, Default)]
+pub struct SessionFacts<'a> {
+    pub id: &'a str,
+    pub started: Option<DateTime<Utc>>,
+    pub first_user_message: Option<&'a str>,
+}
+
+/// Loaded exclusion rules
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    root: PathBuf,
+    paths: Vec<PathPattern>,
+    commands: Vec<String>,
+    sessions: Vec<String>,
+    dates: Vec<DateRange>,
+    markers: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Load .wm/ignore and the [ignore] config for the current project
+    pub fn load(ws: &Workspace) -> Self {
+        let content = std::fs::read_to_string(state::wm_path(ws, IGNORE_FILE)).unwrap_or_default();
+        let config = state::read_config(ws).ignore;
+        for spec in &config.dates {
+            if DateRange::parse(spec).is_none() {
+                state::log(ws, "ignore", &format!("Skipping invalid date '{}'", spec));
+            }
+        }
+        Self::new(ws.root().to_path_buf(), &content, &config)
+    }
+
+    /// Build from ignore-file content and config (invalid dates are skipped)
+    pub fn new(root: PathBuf, ignore_file: &str, config: &IgnoreConfig) -> Self {
+        let dates = config
+            .dates
+            .iter()
+            .filter_map(|spec| DateRange::parse(spec))
+            .collect();
+
+        IgnoreRules {
+            root,
+            paths: ignore_file.lines().filter_map(PathPattern::parse).collect(),
+            commands: config.commands.clone(),
+            sessions: config.sessions.clone(),
+            dates,
+            markers: config
+                .markers
+                .iter()
+                .filter(|m| !m.trim().is_empty())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Whether a file path (absolute or project-relative) is ignored
+    /// The last matching pattern wins, as in .gitignore.
+    pub fn ignores_path(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        self.paths
+            .iter()
+            .rev()
+            .find(|p| p.matches(&relative))
+            .is_some_and(|p| !p.negated)
+    }
+
+    /// Whether a shell command is ignored: it matches a command glob (where `*`
+    /// spans anything) or one of its arguments is an ignored path
+    pub fn ignores_command(&self, command: &str) -> bool {
+        let command = command.trim();
+        self.commands
+            .iter()
+            .any(|pattern| glob_match(&pattern.trim().replace('*', "**"), command))
+            || (!self.paths.is_empty()
+                && command
+                    .split(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '<' | '>'))
+                    .map(|word| word.trim_matches(['"', '\'']))
+                    .filter(|word| !word.is_empty() && !word.starts_with('-'))
+                    .any(|word| self.ignores_path(word)))
+    }
+
+    /// Why a session is excluded from extraction (None = not excluded)
+    pub fn session_exclusion(&self, session: &SessionFacts) -> Option<String> {
+        if let Some(pattern) = self
+            .sessions
+            .iter()
+            .find(|p| glob_match(p.trim(), session.id))
+        {
+            return Some(format!("session ID matches '{}'", pattern));
+        }
+
+        if let Some(started) = session.started {
+            let date = started.with_timezone(&Local).date_naive();
+            if self.dates.iter().any(|range| range.contains(date)) {
+                return Some(format!("session date {} is ignored", date));
+            }
+        }
+
+        let first = session.first_user_message?;
+        self.markers
+            .iter()
+            .find(|marker| first.contains(marker.as_str()))
+            .map(|marker| format!("first message contains '{}'", marker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(ignore_file: &str, config: IgnoreConfig) -> IgnoreRules {
+        IgnoreRules::new(PathBuf::from("/repo"), ignore_file, &config)
+    }
+
+    #[test]
+    fn test_ignores_path_gitignore_style() {
+        let rules = rules(
+            "# secrets\nsecrets/\n*.pem\n!public.pem\n/customer-data\n",
+            IgnoreConfig::default(),
+        );
+        assert!(rules.ignores_path("/repo/secrets/api.key"));
+        assert!(rules.ignores_path("app/secrets/api.key"));
+        assert!(!rules.ignores_path("secrets"));
+        assert!(rules.ignores_path("/repo/certs/server.pem"));
+        assert!(!rules.ignores_path("certs/public.pem"));
+        assert!(rules.ignores_path("customer-data/export.csv"));
+        assert!(!rules.ignores_path("app/customer-data/export.csv"));
+        assert!(!rules.ignores_path("/repo/src/main.rs"));
+    }
+
+    #[test]
+    fn test_ignores_command() {
+        let rules = rules(
+            ".env\n",
+            IgnoreConfig {
+                commands: vec!["psql *".to_string()],
+                ..IgnoreConfig::default()
+            },
+        );
+        assert!(rules.ignores_command("psql postgres://prod/db -c 'select 1'"));
+        assert!(rules.ignores_command("cat .env | grep KEY"));
+        assert!(!rules.ignores_command("cargo test"));
+    }
+
+    #[test]
+    fn test_session_exclusion() {
+        let rules = rules(
+            "",
+            IgnoreConfig {
+                sessions: vec!["4f1c*".to_string()],
+                dates: vec!["2026-03-01..2026-03-05".to_string()],
+                ..IgnoreConfig::default()
+            },
+        );
+        let facts = |id, started: &str, first| SessionFacts {
+            id,
+            started: DateTime::parse_from_rfc3339(started)
+                .ok()
+                .map(|t| t.with_timezone(&Utc)),
+            first_user_message: first,
+        };
+
+        assert!(
+            rules
+                .session_exclusion(&facts("4f1c-aa", "2026-04-01T12:00:00Z", None))
+                .is_some()
+        );
+        assert!(
+            rules
+                .session_exclusion(&facts("b", "2026-03-03T12:00:00Z", None))
+                .is_some()
+        );
+        assert!(
+            rules
+                .session_exclusion(&facts(
+                    "b",
+                    "2026-04-01T12:00:00Z",
+                    Some("review this #wm:private")
+                ))
+                .is_some()
+        );
+        assert!(
+            rules
+                .session_exclusion(&facts("b", "2026-04-01T12:00:00Z", Some("hello")))
+                .is_none()
+        );
+    }
+}
@@ -1,28 +1,63 @@
 //! Initialize .wm/ in current project
 
+use crate::error::{Error, Result};
+use crate::migrate;
+use crate::shared;
 use crate::state::{self, wm_dir, wm_path};
+use crate::workspace::Workspace;
 use std::fs;
+use std::path::PathBuf;
+
+/// Run wm init in `target` (the pinned project dir, else cwd)
+/// AIDEV-NOTE: Never creates the store in an ancestor found by walking up, but
+/// warns when an ancestor store exists, since the new nested store will shadow
+/// it for everything below.
+pub fn run(target: PathBuf) -> std::result::Result<(), String> {
+    if let Some(existing) = target.parent().and_then(state::find_store_root) {
+        eprintln!(
+            "Warning: an existing store was found at {}/.wm",
+            existing.display()
+        );
+        eprintln!(
+            "         Creating a nested store here will shadow it for {}",
+            target.display()
+        );
+    }
+
+    create(&Workspace::new(target.clone()))?;
 
-/// Run wm init
-pub fn run() -> Result<(), String> {
-    if state::is_initialized() {
-        return Err("Already initialized: .wm/ exists".to_string());
+    println!("Initialized .wm/ in {}", target.display());
+
+    Ok(())
+}
+
+/// Create the .wm/ store for a workspace without printing
+pub fn create(ws: &Workspace) -> Result<()> {
+    if state::is_initialized(ws) {
+        return Err(Error::AlreadyInitialized(wm_dir(ws)));
     }
 
     // Create .wm/ directory
-    fs::create_dir_all(wm_dir()).map_err(|e| format!("Failed to create .wm/: {}", e))?;
+    fs::create_dir_all(wm_dir(ws)).map_err(|e| Error::io("Failed to create .wm/", e))?;
 
     // Create empty state.md (freeform markdown for tacit knowledge)
-    fs::write(wm_path("state.md"), "").map_err(|e| format!("Failed to write state.md: {}", e))?;
+    fs::write(wm_path(ws, "state.md"), "").map_err(|e| Error::io("Failed to write state.md", e))?;
 
-    // Create checkpoint.json for tracking extraction progress
-    fs::write(wm_path("checkpoint.json"), "{\"position\": 0}")
-        .map_err(|e| format!("Failed to write checkpoint.json: {}", e))?;
+    // Record the layout version so future releases know what to migrate
+    // (only the version - everything else shows through from defaults and ~/.config/wm/)
+    state::update_config(ws, |config| config.version = migrate::CURRENT_VERSION)
+        .map_err(|e| Error::io("Failed to write config.toml", e))?;
 
     // Create empty working set
-    state::write_working_set("").map_err(|e| format!("Failed to write working_set.md: {}", e))?;
-
-    println!("Initialized .wm/ in current directory");
+    state::write_working_set(ws, "").map_err(|e| Error::io("Failed to write working_set.md", e))?;
+
+    // Keep per-user files out of git; only .wm/shared/ (and rules) are committed
+    fs::create_dir_all(wm_path(ws, shared::SHARED_DIR))
+        .map_err(|e| Error::io("Failed to create .wm/shared/", e))?;
+    fs::write(wm_path(ws, ".gitignore"), shared::GITIGNORE)
+        .map_err(|e| Error::io("Failed to write .wm/.gitignore", e))?;
+    fs::write(wm_path(ws, ".gitattributes"), shared::GITATTRIBUTES)
+        .map_err(|e| Error::io("Failed to write .wm/.gitattributes", e))?;
 
     Ok(())
 }
@@ -0,0 +1,235 @@
+//! Structured extraction output
+//!
+//! Extract and distill ask the LLM for JSON matching a schema instead of
+//! scraping `HAS_KNOWLEDGE:` markers and bullet prefixes, so multi-line items
+//! and numbered lists survive intact. Distill's Pass 1 returns an
+//! `ExtractionOutput`, Pass 2 a `CategorizationOutput`; both are lists of
+//! `Insight`s, validated with serde and then rendered as the one-line markdown
+//! items the rest of wm reads. Per-turn extract rewrites state.md as a whole,
+//! so its `StateOutput` is the new document rather than a list of insights.
+//!
+//! AIDEV-NOTE: The marker format (llm::parse_marker_response and
+//! distill::parse_categorization_response) remains the fallback for backends
+//! without structured output; see llm::structured_output_available.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// One piece of tacit knowledge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Insight {
+    /// The insight itself, self-contained (may span several sentences)
+    pub text: String,
+
+    /// Category name, e.g. guardrails or metis (a hint in Pass 1, the decision in Pass 2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// How clearly the session supports it, 0.0 to 1.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+
+    /// Why it matters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+
+    /// Short verbatim user quote that motivated it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<String>,
+
+    /// Project-relative paths it applies to (empty = project-wide)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scope: Vec<String>,
+
+    /// Personal preference of the user that applies in any project
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub user: bool,
+
+    /// Pass 2: numbers of the raw extractions (`[#n]`) it was distilled from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<usize>,
+}
+
+impl Insight {
+    /// Render as a one-line markdown item: text plus any [scope: ...] tag
+    pub fn to_item(&self) -> String {
+        let text = one_line(&self.text);
+        let scope: Vec<&str> = self
+            .scope
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if scope.is_empty() || text.contains("[scope:") {
+            text
+        } else {
+            format!("{} [scope: {}]", text, scope.join(", "))
+        }
+    }
+}
+
+/// Pass 1 result for one session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractionOutput {
+    #[serde(default)]
+    pub has_knowledge: bool,
+
+    #[serde(default)]
+    pub insights: Vec<Insight>,
+}
+
+/// Pass 2 result across sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategorizationOutput {
+    #[serde(default)]
+    pub items: Vec<Insight>,
+}
+
+/// Per-turn extract result: the updated state.md
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateOutput {
+    #[serde(default)]
+    pub has_knowledge: bool,
+
+    /// Complete new state (existing state plus new insights), markdown
+    #[serde(default)]
+    pub state: String,
+}
+
+/// Collapse whitespace (including newlines) so an item stays on one bullet line
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drop insights without text and clamp confidence into 0.0..=1.0
+pub fn validate(insights: Vec<Insight>) -> Vec<Insight> {
+    insights
+        .into_iter()
+        .filter(|i| !i.text.trim().is_empty())
+        .map(|mut i| {
+            i.confidence = i
+                .confidence
+                .filter(|c| c.is_finite())
+                .map(|c| c.clamp(0.0, 1.0));
+            i
+        })
+        .collect()
+}
+
+/// JSON schema for one insight (`category_required` for Pass 2)
+/// AIDEV-NOTE: Pass 2 items cite their Pass 1 insights in `sources` instead of
+/// repeating confidence, rationale and evidence; those are tracked from Pass 1
+/// (see evidence.rs).
+fn insight_schema(categories: &[&str], category_required: bool) -> Value {
+    let mut required = vec!["text"];
+    if category_required {
+        required.push("category");
+    }
+    let mut schema = json!({
+        "type": "object",
+        "properties": {
+            "text": {"type": "string"},
+            "category": {"type": "string", "enum": categories},
+            "scope": {"type": "array", "items": {"type": "string"}},
+            "user": {"type": "boolean"}
+        },
+        "required": required
+    });
+    if category_required {
+        schema["properties"]["sources"] = json!({"type": "array", "items": {"type": "integer"}});
+    } else {
+        for (field, spec) in [
+            (
+                "confidence",
+                json!({"type": "number", "minimum": 0, "maximum": 1}),
+            ),
+            ("rationale", json!({"type": "string"})),
+            ("evidence", json!({"type": "string"})),
+        ] {
+            schema["properties"][field] = spec;
+        }
+    }
+    schema
+}
+
+/// Schema for Pass 1 (ExtractionOutput), given the configured category names
+pub fn extraction_schema(categories: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "has_knowledge": {"type": "boolean"},
+            "insights": {"type": "array", "items": insight_schema(categories, false)}
+        },
+        "required": ["has_knowledge", "insights"]
+    })
+}
+
+/// Schema for per-turn extract (StateOutput)
+pub fn state_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "has_knowledge": {"type": "boolean"},
+            "state": {"type": "string"}
+        },
+        "required": ["has_knowledge", "state"]
+    })
+}
+
+/// Schema for Pass 2 (CategorizationOutput), given the configured category names
+pub fn categorization_schema(categories: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "items": {"type": "array", "items": insight_schema(categories, true)}
+        },
+        "required": ["items"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extraction_output_parses_multiline_items() {
+        let json = r#"{
+            "has_knowledge": true,
+            "insights": [
+                {"text": "Run migrations with sqlx-cli.\nNever edit applied migrations.",
+                 "category": "guardrail", "confidence": 1.4,
+                 "evidence": "don't touch old migrations", "scope": ["backend/"]},
+                {"text": "  "}
+            ]
+        }"#;
+        let output: ExtractionOutput = serde_json::from_str(json).unwrap();
+        let insights = validate(output.insights);
+
+        assert_eq!(insights.len(), 1);
+        assert_eq!(insights[0].confidence, Some(1.0));
+        assert_eq!(insights[0].category.as_deref(), Some("guardrail"));
+        assert_eq!(
+            insights[0].to_item(),
+            "Run migrations with sqlx-cli. Never edit applied migrations. [scope: backend/]"
+        );
+    }
+
+    #[test]
+    fn test_schemas_require_core_fields() {
+        let categories = ["guardrails", "metis", "glossary"];
+        assert_eq!(
+            extraction_schema(&categories)["required"][0],
+            "has_knowledge"
+        );
+        let item = &categorization_schema(&categories)["properties"]["items"]["items"];
+        assert_eq!(item["required"], json!(["text", "category"]));
+        assert_eq!(item["properties"]["category"]["enum"], json!(categories));
+        assert!(item["properties"].get("rationale").is_none());
+        let insight = &extraction_schema(&categories)["properties"]["insights"]["items"];
+        assert_eq!(insight["properties"]["evidence"]["type"], "string");
+        assert_eq!(
+            state_schema()["required"],
+            json!(["has_knowledge", "state"])
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! wm - working memory for AI coding assistants
+//!
+//! The `wm` binary is a thin CLI over this library. Embedders should start
+//! from [`Workspace`]:
+//!
+//! ```no_run
+//! use working_memory::Workspace;
+//!
+//! let ws = Workspace::open("/path/to/project")?;
+//! let working_set = ws.compile();
+//! println!("{}", working_set.content);
+//! # Ok::<(), working_memory::Error>(())
+//! ```
+//!
+//! Internal modules are private; `cli` backs the binary and is not part of the
+//! library API.
+
+mod audit;
+mod category;
+#[doc(hidden)]
+pub mod cli;
+mod codex;
+mod compile;
+mod compress;
+mod confidence;
+mod config;
+mod distill;
+mod dive;
+mod doctor;
+mod error;
+mod evidence;
+mod extract;
+mod guard;
+mod history;
+mod ignore;
+mod init;
+mod insight;
+mod lint;
+mod llm;
+mod lock;
+mod mcp;
+mod migrate;
+mod oh;
+mod pause;
+mod prompts;
+mod scope;
+mod session;
+mod shared;
+mod show;
+mod state;
+mod transcript;
+mod types;
+mod usage;
+mod workspace;
+
+pub use compile::WorkingSet;
+pub use distill::{DistillOptions, DistillReport, Progress};
+pub use dive::DivePrep;
+pub use error::{Error, Result};
+pub use llm::ErrorKind as LlmErrorKind;
+pub use types::{
+    AuditConfig, CategoriesConfig, CategoryConfig, ConfidenceConfig, Config, DiveConfig,
+    HistoryConfig, IgnoreConfig, LintConfig, LlmConfig, Operation, OperationsConfig, Pause,
+};
+pub use workspace::Workspace;
@@ -0,0 +1,902 @@
+//! Contradiction detection across knowledge files
+//!
+//! Knowledge piles up over months: metis may say "prefer anyhow for errors"
+//! next to a later "use thiserror enums, never anyhow", and both get injected.
+//! `wm lint` sends every item of the category files and state.md to the LLM,
+//! which reports pairs that contradict each other or where one supersedes the
+//! other. Each pair is shown with its provenance: the file, when history first
+//! saw it, and (for distilled items) the sessions that support it.
+//!
+//! Conflicts are saved in .wm/lint.json and resolved by number:
+//!
+//! ```text
+//! wm lint --resolve 1 --keep newer                  # drop the older item
+//! wm lint --resolve 2 --keep a                      # drop b (or --keep b)
+//! wm lint --resolve 3 --keep both --scope-a backend/ --scope-b web/
+//! ```
+//!
+//! AIDEV-NOTE: Distill Pass 2 regenerates the category files from every cached
+//! extraction, so a dropped item would come straight back. Resolutions are kept
+//! in lint.json; Pass 2 is told about them (resolutions_prompt) and items the
+//! user dropped are filtered from its output (dropped).
+
+use crate::category;
+use crate::compile;
+use crate::confidence;
+use crate::distill::{self, Progress};
+use crate::history;
+use crate::llm::{self, ErrorKind, LlmError};
+use crate::lock;
+use crate::prompts;
+use crate::scope;
+use crate::state;
+use crate::usage::Budget;
+use crate::workspace::Workspace;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::ops::Range;
+
+/// Saved conflicts and resolutions within .wm/
+pub const LINT_FILE: &str = "lint.json";
+
+/// Source label of state.md items
+const STATE_SOURCE: &str = "state";
+
+/// How two items conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// Following one means breaking the other
+    Contradicts,
+
+    /// One replaces the other (a changed tool, version or convention)
+    Supersedes,
+}
+
+impl ConflictKind {
+    fn parse(value: &str) -> Self {
+        if value.trim().to_lowercase().starts_with("supersede") {
+            ConflictKind::Supersedes
+        } else {
+            ConflictKind::Contradicts
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ConflictKind::Contradicts => "Contradiction",
+            ConflictKind::Supersedes => "Superseded",
+        }
+    }
+}
+
+/// A knowledge item and where it comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemRef {
+    /// File relative to .wm/ (e.g. distill/metis.md, state.md)
+    pub file: String,
+
+    /// Category name, or "state"
+    pub source: String,
+
+    pub text: String,
+
+    /// Earliest snapshot in history that contains the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<DateTime<Utc>>,
+
+    /// Command that wrote that snapshot (distill, extract, compress, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_by: Option<String>,
+
+    /// Sessions that re-derived it (distilled items only; see confidence.rs)
+    #[serde(default)]
+    pub sessions: usize,
+
+    /// Latest activity of a supporting session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reinforced: Option<DateTime<Utc>>,
+}
+
+impl ItemRef {
+    /// Latest known time the item was stated
+    pub fn stated_at(&self) -> Option<DateTime<Utc>> {
+        self.added.max(self.last_reinforced)
+    }
+
+    /// One-line provenance for reports
+    fn provenance(&self) -> String {
+        let mut parts = vec![format!(".wm/{}", self.file)];
+        if let Some(added) = self.added {
+            parts.push(format!(
+                "first seen {} ({})",
+                added.format("%Y-%m-%d"),
+                self.added_by.as_deref().unwrap_or("unknown")
+            ));
+        }
+        if self.sessions > 0 {
+            parts.push(format!("{} session(s)", self.sessions));
+        }
+        if let Some(last) = self.last_reinforced {
+            parts.push(format!("last reinforced {}", last.format("%Y-%m-%d")));
+        }
+        parts.join(", ")
+    }
+}
+
+/// A conflicting pair found by `wm lint`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+
+    /// The LLM's explanation
+    pub reason: String,
+
+    pub a: ItemRef,
+
+    pub b: ItemRef,
+
+    /// How it was resolved ("kept a", "kept both, scoped", ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+}
+
+/// One side of a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    A,
+    B,
+}
+
+impl Conflict {
+    /// The side stated more recently, if the dates tell
+    fn newer(&self) -> Option<Side> {
+        let (a, b) = (self.a.stated_at()?, self.b.stated_at()?);
+        match a.cmp(&b) {
+            std::cmp::Ordering::Greater => Some(Side::A),
+            std::cmp::Ordering::Less => Some(Side::B),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+/// A user decision, remembered for later lint runs and distill Pass 2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    pub resolved_at: DateTime<Utc>,
+
+    /// Items kept, as written after resolving (with any new scope tag)
+    pub kept: Vec<String>,
+
+    /// Item removed, if one was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dropped: Option<String>,
+}
+
+/// Contents of .wm/lint.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintState {
+    /// When `wm lint` last ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+
+    /// Conflicts found by that run, numbered from 1 in reports
+    #[serde(default)]
+    pub conflicts: Vec<Conflict>,
+
+    /// Every resolution so far, oldest first
+    #[serde(default)]
+    pub resolutions: Vec<Resolution>,
+}
+
+fn path(ws: &Workspace) -> std::path::PathBuf {
+    state::wm_path(ws, LINT_FILE)
+}
+
+/// Load .wm/lint.json (empty if missing or unreadable)
+pub fn load(ws: &Workspace) -> LintState {
+    fs::read_to_string(path(ws))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Read-modify-write .wm/lint.json under its lock
+fn update(ws: &Workspace, change: impl FnOnce(&mut LintState)) -> Result<(), String> {
+    let path = path(ws);
+    let _lock = lock::acquire(&path).map_err(|e| format!("Failed to lock {}: {}", LINT_FILE, e))?;
+    let mut lint_state = load(ws);
+    change(&mut lint_state);
+    let json = serde_json::to_string_pretty(&lint_state)
+        .map_err(|e| format!("Failed to serialize {}: {}", LINT_FILE, e))?;
+    state::write_atomic(&path, &json).map_err(|e| format!("Failed to write {}: {}", LINT_FILE, e))
+}
+
+// ============================================================================
+// Items and provenance
+// ============================================================================
+
+/// A bullet item in a markdown file: its line range and joined text
+#[derive(Debug)]
+struct Bullet {
+    lines: Range<usize>,
+    text: String,
+}
+
+/// Bullets of a knowledge file, continuation lines included (as split_items)
+fn bullets(content: &str) -> Vec<Bullet> {
+    let mut bullets: Vec<Bullet> = Vec::new();
+    let mut in_bullet = false;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            bullets.push(Bullet {
+                lines: i..i + 1,
+                text: item.trim().to_string(),
+            });
+            in_bullet = true;
+        } else if trimmed.is_empty() || trimmed.starts_with('#') {
+            in_bullet = false;
+        } else if in_bullet && let Some(last) = bullets.last_mut() {
+            last.lines.end = i + 1;
+            last.text.push(' ');
+            last.text.push_str(trimmed);
+        }
+    }
+
+    bullets
+}
+
+/// Replace the bullet holding `item` (None removes it); None if it isn't there
+fn rewrite_item(content: &str, item: &str, replacement: Option<&str>) -> Option<String> {
+    let wanted = compile::normalize_item(item);
+    let bullet = bullets(content)
+        .into_iter()
+        .find(|b| compile::normalize_item(&b.text) == wanted)?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<String> = lines[..bullet.lines.start]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    if let Some(replacement) = replacement {
+        let first = lines[bullet.lines.start];
+        let indent = &first[..first.len() - first.trim_start().len()];
+        let marker = &first.trim_start()[..2];
+        output.push(format!("{}{}{}", indent, marker, replacement.trim()));
+    }
+    output.extend(
+        lines[bullet.lines.end..]
+            .iter()
+            .map(|line| line.to_string()),
+    );
+
+    let mut rewritten = output.join("\n");
+    if content.ends_with('\n') {
+        rewritten.push('\n');
+    }
+    Some(rewritten)
+}
+
+/// Files to check (relative to .wm/) with their source labels
+fn sources(ws: &Workspace) -> Vec<(String, String)> {
+    let mut sources: Vec<(String, String)> = category::all(ws)
+        .into_iter()
+        .flat_map(|c| {
+            [
+                (format!("distill/{}", c.filename), c.name.clone()),
+                (format!("manual/{}", c.filename), c.name),
+            ]
+        })
+        .collect();
+    sources.push(("state.md".to_string(), STATE_SOURCE.to_string()));
+    sources
+}
+
+/// When each (file, normalized item) first appears in history, and by which command
+fn first_appearances(ws: &Workspace) -> HashMap<(String, String), (DateTime<Utc>, String)> {
+    let mut seen = HashMap::new();
+    for snapshot in history::list(ws) {
+        let Ok(content) = history::read(ws, &snapshot) else {
+            continue;
+        };
+        for bullet in bullets(&content) {
+            seen.entry((snapshot.file.clone(), compile::normalize_item(&bullet.text)))
+                .or_insert_with(|| (snapshot.timestamp, snapshot.command.clone()));
+        }
+    }
+    seen
+}
+
+/// Every item of the category files and state.md, with provenance
+fn collect_items(ws: &Workspace) -> Vec<ItemRef> {
+    let history = first_appearances(ws);
+    let store = confidence::Store::load(ws);
+    let mut items = Vec::new();
+
+    for (file, source) in sources(ws) {
+        let content = fs::read_to_string(state::wm_path(ws, &file)).unwrap_or_default();
+        for bullet in bullets(&content) {
+            let added = history.get(&(file.clone(), compile::normalize_item(&bullet.text)));
+            let record = store
+                .get(&bullet.text)
+                .filter(|record| !record.sessions.is_empty());
+            items.push(ItemRef {
+                file: file.clone(),
+                source: source.clone(),
+                added: added.map(|(time, _)| *time),
+                added_by: added.map(|(_, command)| command.clone()),
+                sessions: record.map_or(0, |r| r.sessions.len()),
+                last_reinforced: record.map(|r| r.last_reinforced),
+                text: bullet.text,
+            });
+        }
+    }
+    items
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+/// One conflict as reported by the LLM (1-based item numbers)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Finding {
+    a: usize,
+    b: usize,
+    kind: ConflictKind,
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LintOutput {
+    #[serde(default)]
+    conflicts: Vec<Finding>,
+}
+
+fn lint_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "conflicts": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "a": {"type": "integer", "minimum": 1},
+                        "b": {"type": "integer", "minimum": 1},
+                        "kind": {"type": "string", "enum": ["contradicts", "supersedes"]},
+                        "reason": {"type": "string"}
+                    },
+                    "required": ["a", "b", "kind", "reason"]
+                }
+            }
+        },
+        "required": ["conflicts"]
+    })
+}
+
+/// Output instructions for structured (JSON) output
+const LINT_JSON_FORMAT: &str = r#"OUTPUT FORMAT:
+
+Respond with JSON matching the provided schema: {"conflicts": [...]}, one object per conflicting pair:
+- a, b: the item numbers shown in brackets
+- kind: "contradicts" or "supersedes"
+- reason: one sentence on why they conflict
+
+An empty conflicts list is the right answer when the items agree."#;
+
+/// Output instructions for the marker format (fallback)
+const LINT_MARKER_FORMAT: &str = r#"OUTPUT FORMAT:
+
+If there are conflicts, respond:
+HAS_CONFLICTS: YES
+- <a> | <b> | <contradicts or supersedes> | <one sentence on why>
+
+(one line per conflicting pair, using the item numbers shown in brackets)
+
+If the items agree, respond:
+HAS_CONFLICTS: NO"#;
+
+/// Parse marker-format findings ("- 3 | 7 | supersedes | reason")
+fn parse_marker_findings(ws: &Workspace, response: &str) -> Vec<Finding> {
+    let marker = llm::parse_marker_response(ws, response, "HAS_CONFLICTS");
+    if !marker.is_positive {
+        return Vec::new();
+    }
+    let number = |field: &str| {
+        field
+            .trim()
+            .trim_matches(['[', ']', '#'])
+            .trim()
+            .parse::<usize>()
+            .ok()
+    };
+    marker
+        .content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*', '•']).trim();
+            let fields: Vec<&str> = line.splitn(4, '|').collect();
+            let [a, b, kind, reason] = fields.as_slice() else {
+                return None;
+            };
+            Some(Finding {
+                a: number(a)?,
+                b: number(b)?,
+                kind: ConflictKind::parse(kind),
+                reason: reason.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn call_lint_llm(
+    ws: &Workspace,
+    budget: Option<&Budget>,
+    items: &[ItemRef],
+) -> Result<Vec<Finding>, LlmError> {
+    let prompt = prompts::load(ws, "lint");
+    let listing: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| format!("[{}] ({}) {}", i + 1, item.source, item.text))
+        .collect();
+    let message = format!(
+        "Find conflicting pairs among these knowledge items:\n\n{}\n\nOUTPUT:",
+        listing.join("\n")
+    );
+
+    if llm::structured_output_available(ws) {
+        let system_prompt = format!("{}\n{}", prompt.trim_end(), LINT_JSON_FORMAT);
+        match llm::call_claude_json::<LintOutput>(
+            ws,
+            "lint",
+            None,
+            budget,
+            &system_prompt,
+            &message,
+            &lint_schema(),
+        ) {
+            Ok(output) => return Ok(output.conflicts),
+            Err(e) if e.kind == ErrorKind::Unsupported => {
+                state::log(ws, "lint", "Structured output unsupported, using markers");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let system_prompt = format!("{}\n{}", prompt.trim_end(), LINT_MARKER_FORMAT);
+    let response = llm::call_claude(ws, "lint", None, budget, &system_prompt, &message)?;
+    Ok(parse_marker_findings(ws, &response))
+}
+
+/// Turn findings into conflicts, dropping bad numbers, repeats and pairs the
+/// user already chose to keep side by side
+fn to_conflicts(
+    findings: Vec<Finding>,
+    items: &[ItemRef],
+    resolutions: &[Resolution],
+) -> Vec<Conflict> {
+    let kept_together = |a: &str, b: &str| {
+        let (a, b) = (compile::normalize_item(a), compile::normalize_item(b));
+        resolutions.iter().any(|r| {
+            r.dropped.is_none() && {
+                let kept: BTreeSet<String> =
+                    r.kept.iter().map(|k| compile::normalize_item(k)).collect();
+                kept.contains(&a) && kept.contains(&b)
+            }
+        })
+    };
+
+    let mut seen = BTreeSet::new();
+    let mut conflicts = Vec::new();
+    for finding in findings {
+        let (a, b) = (finding.a.min(finding.b), finding.a.max(finding.b));
+        if a == 0 || a == b || b > items.len() || !seen.insert((a, b)) {
+            continue;
+        }
+        let (a, b) = (&items[a - 1], &items[b - 1]);
+        if kept_together(&a.text, &b.text) {
+            continue;
+        }
+        conflicts.push(Conflict {
+            kind: finding.kind,
+            reason: finding.reason.trim().to_string(),
+            a: a.clone(),
+            b: b.clone(),
+            resolved: None,
+        });
+    }
+    conflicts
+}
+
+/// Check all knowledge for conflicts and save them to .wm/lint.json
+/// LLM calls count towards `budget`, if given; status lines go to `progress`
+/// (printed by the CLI, see distill::Progress).
+pub fn check(
+    ws: &Workspace,
+    budget: Option<&Budget>,
+    progress: &dyn Fn(Progress<'_>),
+) -> Result<Vec<Conflict>, String> {
+    let items = collect_items(ws);
+    let findings = if items.len() < 2 {
+        Vec::new()
+    } else {
+        progress(Progress::Status(&format!(
+            "Checking {} item(s) for conflicts...",
+            items.len()
+        )));
+        call_lint_llm(ws, budget, &items)?
+    };
+
+    let resolutions = load(ws).resolutions;
+    let conflicts = to_conflicts(findings, &items, &resolutions);
+    state::log(
+        ws,
+        "lint",
+        &format!(
+            "Checked {} item(s), {} conflict(s)",
+            items.len(),
+            conflicts.len()
+        ),
+    );
+
+    let saved = conflicts.clone();
+    update(ws, |lint_state| {
+        lint_state.checked_at = Some(Utc::now());
+        lint_state.conflicts = saved;
+    })?;
+    Ok(conflicts)
+}
+
+// ============================================================================
+// Resolutions and distill
+// ============================================================================
+
+/// Normalized items the user dropped and hasn't kept again since
+pub fn dropped(resolutions: &[Resolution]) -> BTreeSet<String> {
+    let mut dropped = BTreeSet::new();
+    for resolution in resolutions {
+        if let Some(item) = &resolution.dropped {
+            dropped.insert(compile::normalize_item(item));
+        }
+        for item in &resolution.kept {
+            dropped.remove(&compile::normalize_item(item));
+        }
+    }
+    dropped
+}
+
+/// Resolutions to follow when Pass 2 regenerates the category files ("" if none)
+pub fn resolutions_prompt(resolutions: &[Resolution]) -> String {
+    if resolutions.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from(
+        "RESOLVED CONFLICTS (decided by the user; follow them even if the insights disagree):\n",
+    );
+    for resolution in resolutions {
+        match &resolution.dropped {
+            Some(dropped) => output.push_str(&format!(
+                "- Leave out \"{}\"; keep \"{}\"\n",
+                dropped,
+                resolution.kept.join("\"; \"")
+            )),
+            None => output.push_str(&format!(
+                "- Keep both, each with its scope tag: \"{}\"\n",
+                resolution.kept.join("\"; \"")
+            )),
+        }
+    }
+    output
+}
+
+/// An item with its scope tag replaced by `scopes`
+fn with_scope(item: &str, scopes: &[String]) -> String {
+    let (text, _) = scope::parse_scopes(item);
+    format!("{} [scope: {}]", text, scopes.join(", "))
+}
+
+/// Rewrite one item in its file (None removes it), under the file's lock
+fn rewrite(ws: &Workspace, item: &ItemRef, replacement: Option<&str>) -> Result<(), String> {
+    let path = state::wm_path(ws, &item.file);
+    let _lock = lock::acquire(&path).map_err(|e| format!("Failed to lock {}: {}", item.file, e))?;
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let updated = rewrite_item(&content, &item.text, replacement).ok_or_else(|| {
+        format!(
+            "\"{}\" is no longer in .wm/{}. Run 'wm lint' again.",
+            item.text, item.file
+        )
+    })?;
+    history::write_tracked(ws, &item.file, &updated, "lint")
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// How to resolve a conflict (`wm lint --resolve N ...`)
+#[derive(Debug, Clone, Default)]
+pub struct ResolveOptions {
+    /// newer, a, b or both
+    pub keep: String,
+
+    /// Scope for item a when keeping both
+    pub scope_a: Vec<String>,
+
+    /// Scope for item b when keeping both
+    pub scope_b: Vec<String>,
+}
+
+/// Run wm lint - check for conflicts, or resolve one from the last check
+pub fn run(ws: &Workspace, resolve: Option<usize>, options: ResolveOptions) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+    match resolve {
+        Some(number) => run_resolve(ws, number, &options),
+        None => {
+            let conflicts = check(ws, None, &distill::print_progress)?;
+            print_report(&conflicts);
+            Ok(())
+        }
+    }
+}
+
+/// Print conflicts with their provenance and how to resolve them
+pub fn print_report(conflicts: &[Conflict]) {
+    print!("{}", format_report(conflicts));
+}
+
+/// The conflict report as text, one block per conflict
+pub fn format_report(conflicts: &[Conflict]) -> String {
+    if conflicts.is_empty() {
+        return "No conflicting items found.\n".to_string();
+    }
+
+    let mut out = String::new();
+    for (i, conflict) in conflicts.iter().enumerate() {
+        out.push_str(&format!(
+            "{}. {}: {}\n",
+            i + 1,
+            conflict.kind.label(),
+            conflict.reason
+        ));
+        let newer = conflict.newer();
+        for (side, letter, item) in [(Side::A, "a", &conflict.a), (Side::B, "b", &conflict.b)] {
+            let mark = if newer == Some(side) { "  (newer)" } else { "" };
+            out.push_str(&format!(
+                "   {}) [{}] {}{}\n",
+                letter, item.source, item.text, mark
+            ));
+            out.push_str(&format!("      {}\n", item.provenance()));
+        }
+        if let Some(resolved) = &conflict.resolved {
+            out.push_str(&format!("   Resolved: {}\n", resolved));
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "{} conflict(s). Resolve with: wm lint --resolve <n> --keep newer|a|b|both [--scope-a PATH] [--scope-b PATH]\n",
+        conflicts.len()
+    ));
+    out
+}
+
+fn run_resolve(ws: &Workspace, number: usize, options: &ResolveOptions) -> Result<(), String> {
+    let lint_state = load(ws);
+    let conflict = number
+        .checked_sub(1)
+        .and_then(|i| lint_state.conflicts.get(i))
+        .ok_or_else(|| format!("No conflict {}. Run 'wm lint' to list them.", number))?;
+    if let Some(resolved) = &conflict.resolved {
+        return Err(format!(
+            "Conflict {} is already resolved ({}).",
+            number, resolved
+        ));
+    }
+
+    let scoped = !options.scope_a.is_empty() || !options.scope_b.is_empty();
+    let (resolution, summary) = match options.keep.as_str() {
+        "newer" | "a" | "b" => {
+            if scoped {
+                return Err("--scope-a and --scope-b only apply to --keep both.".to_string());
+            }
+            let keep = match options.keep.as_str() {
+                "a" => Side::A,
+                "b" => Side::B,
+                _ => conflict.newer().ok_or_else(|| {
+                    "Can't tell which item is newer (no history or session dates). Use --keep a or --keep b."
+                        .to_string()
+                })?,
+            };
+            let (kept, dropped, letter) = match keep {
+                Side::A => (&conflict.a, &conflict.b, "a"),
+                Side::B => (&conflict.b, &conflict.a, "b"),
+            };
+            rewrite(ws, dropped, None)?;
+            println!("Dropped from .wm/{}: {}", dropped.file, dropped.text);
+            (
+                Resolution {
+                    resolved_at: Utc::now(),
+                    kept: vec![kept.text.clone()],
+                    dropped: Some(dropped.text.clone()),
+                },
+                format!("kept {}", letter),
+            )
+        }
+        "both" => {
+            if !scoped {
+                return Err(
+                    "--keep both needs --scope-a and/or --scope-b, so each item only applies to its own paths."
+                        .to_string(),
+                );
+            }
+            let mut kept = Vec::new();
+            for (item, scopes) in [
+                (&conflict.a, &options.scope_a),
+                (&conflict.b, &options.scope_b),
+            ] {
+                if scopes.is_empty() {
+                    kept.push(item.text.clone());
+                    continue;
+                }
+                let text = with_scope(&item.text, scopes);
+                rewrite(ws, item, Some(&text))?;
+                println!("Scoped in .wm/{}: {}", item.file, text);
+                kept.push(text);
+            }
+            (
+                Resolution {
+                    resolved_at: Utc::now(),
+                    kept,
+                    dropped: None,
+                },
+                "kept both, scoped".to_string(),
+            )
+        }
+        other => {
+            return Err(format!(
+                "Unknown --keep '{}'. Use one of: newer, a, b, both.",
+                other
+            ));
+        }
+    };
+
+    state::log(
+        ws,
+        "lint",
+        &format!("Resolved conflict {}: {}", number, summary),
+    );
+    update(ws, |lint_state| {
+        if let Some(conflict) = lint_state.conflicts.get_mut(number - 1) {
+            conflict.resolved = Some(summary);
+        }
+        lint_state.resolutions.push(resolution);
+    })?;
+    println!(
+        "Resolved conflict {}. Distill will keep to this decision.",
+        number
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn item(text: &str, added_days_ago: Option<i64>) -> ItemRef {
+        ItemRef {
+            file: "distill/metis.md".to_string(),
+            source: "metis".to_string(),
+            text: text.to_string(),
+            added: added_days_ago.map(|days| Utc::now() - Duration::days(days)),
+            added_by: Some("distill".to_string()),
+            sessions: 0,
+            last_reinforced: None,
+        }
+    }
+
+    #[test]
+    fn test_rewrite_item_handles_continuations() {
+        let content = "# Metis\n\n- Prefer anyhow\n  for errors.\n- Use pnpm\n";
+        assert_eq!(
+            rewrite_item(content, "Prefer anyhow for errors", None).unwrap(),
+            "# Metis\n\n- Use pnpm\n"
+        );
+        assert_eq!(
+            rewrite_item(content, "use pnpm.", Some("Use pnpm [scope: web]")).unwrap(),
+            "# Metis\n\n- Prefer anyhow\n  for errors.\n- Use pnpm [scope: web]\n"
+        );
+        assert!(rewrite_item(content, "Use npm", None).is_none());
+    }
+
+    #[test]
+    fn test_parse_marker_findings() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let response = "HAS_CONFLICTS: YES\n- [1] | 3 | supersedes | thiserror replaced anyhow\n- 2 | x | contradicts | bad\nnot a finding";
+        assert_eq!(
+            parse_marker_findings(ws, response),
+            vec![Finding {
+                a: 1,
+                b: 3,
+                kind: ConflictKind::Supersedes,
+                reason: "thiserror replaced anyhow".to_string(),
+            }]
+        );
+        assert!(parse_marker_findings(ws, "HAS_CONFLICTS: NO").is_empty());
+    }
+
+    #[test]
+    fn test_to_conflicts_validates_findings() {
+        let items = vec![
+            item("Prefer anyhow for errors", Some(200)),
+            item("Use thiserror enums, never anyhow", Some(10)),
+            item("Deploy with make [scope: infra]", None),
+            item("Deploy with the workflow [scope: web]", None),
+        ];
+        let finding = |a, b| Finding {
+            a,
+            b,
+            kind: ConflictKind::Contradicts,
+            reason: "r".to_string(),
+        };
+        let resolutions = vec![Resolution {
+            resolved_at: Utc::now(),
+            kept: vec![items[2].text.clone(), items[3].text.clone()],
+            dropped: None,
+        }];
+
+        let conflicts = to_conflicts(
+            vec![
+                finding(2, 1),
+                finding(1, 2),
+                finding(1, 1),
+                finding(0, 5),
+                finding(3, 4),
+            ],
+            &items,
+            &resolutions,
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].a.text, "Prefer anyhow for errors");
+        assert_eq!(conflicts[0].newer(), Some(Side::B));
+    }
+
+    #[test]
+    fn test_dropped_and_scoping() {
+        let resolution = |kept: &str, dropped: Option<&str>| Resolution {
+            resolved_at: Utc::now(),
+            kept: vec![kept.to_string()],
+            dropped: dropped.map(str::to_string),
+        };
+        let resolutions = vec![
+            resolution("Use thiserror", Some("Prefer anyhow.")),
+            resolution("Use yarn", Some("Use pnpm")),
+            resolution("Use pnpm", Some("Use yarn")),
+        ];
+        let dropped = dropped(&resolutions);
+        assert_eq!(
+            dropped.into_iter().collect::<Vec<_>>(),
+            vec!["prefer anyhow", "use yarn"]
+        );
+        assert!(resolutions_prompt(&resolutions).contains("Leave out \"Prefer anyhow.\""));
+        assert_eq!(resolutions_prompt(&[]), "");
+
+        assert_eq!(
+            with_scope("Run make [scope: old/]", &["infra".to_string()]),
+            "Run make [scope: infra]"
+        );
+    }
+}
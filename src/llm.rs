@@ -4,8 +4,15 @@
 //! Both modules use the same pattern: call Claude CLI with a system prompt,
 //! parse the response using text-based markers (HAS_KNOWLEDGE, HAS_RELEVANT, etc).
 
+use crate::audit;
 use crate::state;
-use std::process::{Command, Stdio};
+use crate::usage::{self, Budget};
+use crate::workspace::Workspace;
+use serde::de::DeserializeOwned;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Result of calling the LLM with a marker-based response format
 #[derive(Debug)]
@@ -17,53 +24,354 @@ pub struct MarkerResponse {
     pub content: String,
 }
 
-/// Drop guard that restores an environment variable when dropped
-/// AIDEV-NOTE: Ensures env vars are restored even if the LLM call panics or
-/// if future code changes add early returns via `?`. More robust than manual cleanup.
-/// Preserves original value if the env var was already set.
-struct EnvGuard {
-    var_name: &'static str,
-    original_value: Option<String>,
-}
-
-impl EnvGuard {
-    fn new(var_name: &'static str, value: &str) -> Self {
-        // Capture original value before overwriting
-        let original_value = std::env::var(var_name).ok();
-        // SAFETY: Single-threaded, setting recursion prevention flag
-        unsafe { std::env::set_var(var_name, value) };
-        Self {
-            var_name,
-            original_value,
+/// Backend name recorded in the audit trail
+const BACKEND: &str = "claude-cli";
+
+/// What the Claude CLI returned, beyond the result text
+#[derive(Debug, Default, PartialEq)]
+struct CliResponse {
+    result: String,
+
+    /// Schema-conforming output when called with --json-schema
+    structured: Option<serde_json::Value>,
+    model: Option<String>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cost_usd: Option<f64>,
+}
+
+impl CliResponse {
+    /// What the model answered: the structured output if any, else the text
+    fn response_text(&self) -> String {
+        match &self.structured {
+            Some(value) => value.to_string(),
+            None => self.result.clone(),
+        }
+    }
+
+    /// Tokens and cost of this call (0 for whatever the CLI didn't report)
+    fn totals(&self) -> usage::Totals {
+        usage::Totals {
+            calls: 1,
+            input_tokens: self.input_tokens.unwrap_or(0),
+            output_tokens: self.output_tokens.unwrap_or(0),
+            cost_usd: self.cost_usd.unwrap_or(0.0),
+        }
+    }
+}
+
+/// Set once the CLI has rejected --json-schema in this process
+static STRUCTURED_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Longest wait between retries, however many attempts have failed
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often a running call is checked for completion or timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Why an LLM call failed
+/// AIDEV-NOTE: Categories come from the CLI's exit/stderr/result text (see
+/// classify). Only transient ones are retried; a prompt that is too long or a
+/// missing login fails the same way every time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    /// The call ran past llm.timeout_secs and was killed
+    Timeout,
+    RateLimited,
+    /// The API is overloaded or returned a server error
+    Overloaded,
+    Network,
+    PromptTooLong,
+    /// Not logged in or invalid credentials
+    Auth,
+    /// The claude CLI could not be started
+    NotInstalled,
+    /// The CLI's output couldn't be understood
+    InvalidResponse,
+    /// The CLI doesn't support a requested feature (e.g. --json-schema)
+    Unsupported,
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether trying again later may succeed
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Timeout
+                | ErrorKind::RateLimited
+                | ErrorKind::Overloaded
+                | ErrorKind::Network
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::RateLimited => "rate limited",
+            ErrorKind::Overloaded => "overloaded",
+            ErrorKind::Network => "network",
+            ErrorKind::PromptTooLong => "prompt too long",
+            ErrorKind::Auth => "auth",
+            ErrorKind::NotInstalled => "not installed",
+            ErrorKind::InvalidResponse => "invalid response",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::Other => "other",
         }
     }
 }
 
-impl Drop for EnvGuard {
-    fn drop(&mut self) {
-        // SAFETY: Single-threaded, restoring previous state
-        match &self.original_value {
-            Some(val) => unsafe { std::env::set_var(self.var_name, val) },
-            None => unsafe { std::env::remove_var(self.var_name) },
+/// A failed LLM call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlmError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl LlmError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        LlmError {
+            kind,
+            message: message.into(),
         }
     }
 }
 
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.kind.label(), self.message)
+    }
+}
+
+impl From<LlmError> for String {
+    fn from(e: LlmError) -> String {
+        e.to_string()
+    }
+}
+
 /// Call Claude CLI with a system prompt and message
 ///
 /// Returns the raw result string from the Claude CLI JSON response.
-/// Sets WM_DISABLED and SUPEREGO_DISABLED to prevent recursion.
-pub fn call_claude(system_prompt: &str, message: &str) -> Result<String, String> {
-    // Prevent recursion using drop guards - env vars are restored even on panic/early return
-    let _wm_guard = EnvGuard::new("WM_DISABLED", "1");
-    let _sg_guard = EnvGuard::new("SUPEREGO_DISABLED", "1");
+/// The CLI runs with WM_DISABLED and SUPEREGO_DISABLED set to prevent recursion.
+/// `command` and `session` (when the call is for one session) tag the call in
+/// the usage log and audit trail; a successful call also counts towards
+/// `budget`, if given. Each attempt is killed after
+/// llm.timeout_secs; retryable failures are retried llm.retries times with
+/// exponential backoff.
+pub fn call_claude(
+    ws: &Workspace,
+    command: &str,
+    session: Option<&str>,
+    budget: Option<&Budget>,
+    system_prompt: &str,
+    message: &str,
+) -> Result<String, LlmError> {
+    call_with_retries(ws, command, session, budget, system_prompt, message, None).map(|r| r.result)
+}
+
+/// Call Claude CLI for JSON output matching `schema`
+///
+/// Uses the CLI's structured output (--json-schema) and deserializes the
+/// result into `T`. Fails with ErrorKind::Unsupported when the CLI doesn't
+/// know the flag; callers then fall back to a marker prompt (see
+/// structured_output_available).
+pub fn call_claude_json<T: DeserializeOwned>(
+    ws: &Workspace,
+    command: &str,
+    session: Option<&str>,
+    budget: Option<&Budget>,
+    system_prompt: &str,
+    message: &str,
+    schema: &serde_json::Value,
+) -> Result<T, LlmError> {
+    let schema = schema.to_string();
+    let response = call_with_retries(
+        ws,
+        command,
+        session,
+        budget,
+        system_prompt,
+        message,
+        Some(&schema),
+    )
+    .inspect_err(|e| {
+        if e.kind == ErrorKind::Unsupported {
+            STRUCTURED_UNSUPPORTED.store(true, Ordering::Relaxed);
+        }
+    })?;
+
+    let value = match response.structured {
+        Some(value) => value,
+        None => parse_json_text(&response.result).ok_or_else(|| {
+            LlmError::new(
+                ErrorKind::InvalidResponse,
+                format!("Expected JSON output, got: {}", preview(&response.result)),
+            )
+        })?,
+    };
+    serde_json::from_value(value).map_err(|e| {
+        LlmError::new(
+            ErrorKind::InvalidResponse,
+            format!("JSON output doesn't match the schema: {}", e),
+        )
+    })
+}
+
+/// Whether to ask for structured (JSON) output rather than markers
+/// False when llm.structured_output is off or the CLI rejected --json-schema.
+pub fn structured_output_available(ws: &Workspace) -> bool {
+    state::read_config(ws).llm.structured_output && !STRUCTURED_UNSUPPORTED.load(Ordering::Relaxed)
+}
+
+/// Find a JSON object in free text (bare, or inside a ```json fence)
+fn parse_json_text(text: &str) -> Option<serde_json::Value> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+fn preview(text: &str) -> String {
+    let preview: String = text.chars().take(200).collect();
+    if preview.len() < text.len() {
+        format!("{}...", preview)
+    } else {
+        preview
+    }
+}
+
+/// Shared body of call_claude and call_claude_json: attempts with retries
+fn call_with_retries(
+    ws: &Workspace,
+    command: &str,
+    session: Option<&str>,
+    budget: Option<&Budget>,
+    system_prompt: &str,
+    message: &str,
+    schema: Option<&str>,
+) -> Result<CliResponse, LlmError> {
+    let config = state::read_config(ws).llm;
+    let timeout =
+        (config.timeout_secs > 0).then(|| Duration::from_secs(u64::from(config.timeout_secs)));
+
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let response = call_claude_inner(ws, system_prompt, message, schema, timeout);
+        let duration_ms = started.elapsed().as_millis() as u64;
+        record_attempt(
+            ws,
+            command,
+            session,
+            system_prompt,
+            message,
+            &response,
+            duration_ms,
+        );
+
+        match response {
+            Ok(response) => {
+                if let Some(budget) = budget {
+                    budget.add(response.totals());
+                }
+                return Ok(response);
+            }
+            Err(e) if e.kind.is_retryable() && attempt < config.retries => {
+                let delay = backoff(config.backoff_secs, attempt, jitter());
+                state::log(
+                    ws,
+                    "llm",
+                    &format!(
+                        "Attempt {} failed ({}), retrying in {:.1}s",
+                        attempt + 1,
+                        e,
+                        delay.as_secs_f64()
+                    ),
+                );
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => {
+                state::log(ws, "llm", &format!("Call failed: {}", e));
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Add one attempt to the usage log (if it succeeded) and the audit trail
+fn record_attempt(
+    ws: &Workspace,
+    command: &str,
+    session: Option<&str>,
+    system_prompt: &str,
+    message: &str,
+    response: &Result<CliResponse, LlmError>,
+    duration_ms: u64,
+) {
+    let empty = CliResponse::default();
+    let (details, text) = match response {
+        Ok(response) => (response, Ok(response.response_text())),
+        Err(e) => (&empty, Err(e.to_string())),
+    };
+    let outcome = match &text {
+        Ok(text) => Ok(text.as_str()),
+        Err(e) => Err(e.as_str()),
+    };
+    if let Ok(response) = response {
+        usage::record(
+            ws,
+            command,
+            session,
+            response.model.as_deref(),
+            response.totals(),
+        );
+    }
+    audit::record(
+        ws,
+        &audit::Call {
+            command,
+            session,
+            backend: BACKEND,
+            model: details.model.clone(),
+            system_prompt,
+            message,
+            outcome,
+            input_tokens: details.input_tokens,
+            output_tokens: details.output_tokens,
+            duration_ms,
+        },
+    );
+}
+
+/// Delay before retry number `attempt + 1`: base * 2^attempt, capped, scaled
+/// by `jitter` (0.0..1.0) into the upper half so retries don't synchronize
+fn backoff(base_secs: u32, attempt: u32, jitter: f64) -> Duration {
+    let exponential = Duration::from_secs(u64::from(base_secs))
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    exponential.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
 
-    call_claude_inner(system_prompt, message)
+/// Cheap pseudo-random fraction in 0.0..1.0 (no rand dependency needed)
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let mixed = (u64::from(nanos) ^ u64::from(std::process::id())).wrapping_mul(0x9E3779B97F4A7C15);
+    (mixed >> 11) as f64 / (1u64 << 53) as f64
 }
 
-/// Inner implementation of call_claude (without env var management)
-fn call_claude_inner(system_prompt: &str, message: &str) -> Result<String, String> {
+/// Inner implementation of call_claude (one attempt, without env var management)
+fn call_claude_inner(
+    ws: &Workspace,
+    system_prompt: &str,
+    message: &str,
+    schema: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<CliResponse, LlmError> {
     state::log(
+        ws,
         "llm",
         &format!("Calling Claude CLI (message: {} bytes)", message.len()),
     );
@@ -74,42 +382,249 @@ fn call_claude_inner(system_prompt: &str, message: &str) -> Result<String, Strin
         .arg("json")
         .arg("--no-session-persistence")
         .arg("--system-prompt")
-        .arg(system_prompt)
-        .arg(message)
+        .arg(system_prompt);
+    if let Some(schema) = schema {
+        cmd.arg("--json-schema").arg(schema);
+    }
+    // Set on the child only: its hooks must not re-enter wm or sg, and the
+    // process environment stays untouched for other threads
+    cmd.arg(message)
+        .env("WM_DISABLED", "1")
+        .env("SUPEREGO_DISABLED", "1")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .stdin(Stdio::null());
 
-    let child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn claude CLI: {}", e))?;
+    let child = cmd.spawn().map_err(|e| {
+        let kind = if e.kind() == std::io::ErrorKind::NotFound {
+            ErrorKind::NotInstalled
+        } else {
+            ErrorKind::Other
+        };
+        LlmError::new(kind, format!("Failed to spawn claude CLI: {}", e))
+    })?;
 
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for claude CLI: {}", e))?;
+    let output = wait_with_timeout(child, timeout)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
-        return Err(format!(
-            "Claude CLI failed (exit {:?}):\nstderr: {}\nstdout: {}",
-            output.status.code(),
-            stderr,
-            stdout
+        return Err(LlmError::new(
+            classify(&format!("{}\n{}", stderr, error_text(&stdout))),
+            format!(
+                "Claude CLI failed (exit {:?}):\nstderr: {}\nstdout: {}",
+                output.status.code(),
+                stderr,
+                stdout
+            ),
         ));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_cli_response(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Wait for the CLI, killing it once `timeout` has passed
+/// AIDEV-NOTE: stdout/stderr are drained on threads while we poll; otherwise a
+/// large response fills the pipe and the CLI blocks before it can exit.
+fn wait_with_timeout(mut child: Child, timeout: Option<Duration>) -> Result<Output, LlmError> {
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if timeout.is_some_and(|t| started.elapsed() >= t) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(LlmError::new(
+                    ErrorKind::Timeout,
+                    format!(
+                        "Claude CLI timed out after {}s (llm.timeout_secs)",
+                        started.elapsed().as_secs()
+                    ),
+                ));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                return Err(LlmError::new(
+                    ErrorKind::Other,
+                    format!("Failed to wait for claude CLI: {}", e),
+                ));
+            }
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// The error message in a failed call's stdout
+/// AIDEV-NOTE: The CLI's JSON wrapper also carries numbers like `duration_ms`
+/// and token counts, so only its `result` and `error` fields are classified.
+/// Non-JSON stdout is taken as the message itself.
+fn error_text(stdout: &str) -> String {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(stdout) else {
+        return stdout.to_string();
+    };
+    let mut parts = Vec::new();
+    for field in ["result", "error"] {
+        match json.get(field) {
+            Some(serde_json::Value::String(text)) => parts.push(text.clone()),
+            Some(error @ serde_json::Value::Object(_)) => {
+                for key in ["type", "message"] {
+                    if let Some(text) = error.get(key).and_then(|v| v.as_str()) {
+                        parts.push(text.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.join("\n")
+}
 
-    // Parse Claude CLI JSON wrapper to extract result field
-    let cli_response: serde_json::Value = serde_json::from_str(&stdout)
-        .map_err(|e| format!("Failed to parse Claude CLI response: {}", e))?;
+/// Categorize a failure from the CLI's error text
+pub fn classify(text: &str) -> ErrorKind {
+    let text = text.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|n| text.contains(n));
+    // Status codes only count as whole words, not inside other numbers
+    let has_code = |codes: &[&str]| {
+        text.split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| codes.contains(&word))
+    };
 
-    cli_response
-        .get("result")
-        .and_then(|v| v.as_str())
-        .map(String::from)
-        .ok_or_else(|| "Claude CLI response missing 'result' field".to_string())
+    if has(&["unknown option", "unknown argument", "unexpected argument"]) {
+        ErrorKind::Unsupported
+    } else if has(&[
+        "prompt is too long",
+        "prompt too long",
+        "context length",
+        "context window",
+        "too many tokens",
+    ]) {
+        ErrorKind::PromptTooLong
+    } else if has(&[
+        "rate limit",
+        "rate_limit",
+        "too many requests",
+        "usage limit",
+    ]) || has_code(&["429"])
+    {
+        ErrorKind::RateLimited
+    } else if has(&[
+        "overloaded",
+        "internal server error",
+        "service unavailable",
+        "bad gateway",
+        "api_error",
+    ]) || has_code(&["500", "502", "503", "529"])
+    {
+        ErrorKind::Overloaded
+    } else if has(&[
+        "invalid api key",
+        "authentication",
+        "unauthorized",
+        "/login",
+        "not logged in",
+    ]) || has_code(&["401"])
+    {
+        ErrorKind::Auth
+    } else if has(&[
+        "econnreset",
+        "econnrefused",
+        "etimedout",
+        "enotfound",
+        "socket hang up",
+        "fetch failed",
+        "network",
+        "connection",
+        "timed out",
+    ]) {
+        ErrorKind::Network
+    } else {
+        ErrorKind::Other
+    }
+}
+
+/// Parse the Claude CLI JSON wrapper: the result text plus model, tokens and cost
+/// AIDEV-NOTE: Input tokens include prompt-cache reads and writes, since those
+/// were sent too. The model is the first key of `modelUsage` when present.
+fn parse_cli_response(stdout: &str) -> Result<CliResponse, LlmError> {
+    let cli_response: serde_json::Value = serde_json::from_str(stdout).map_err(|e| {
+        LlmError::new(
+            ErrorKind::InvalidResponse,
+            format!("Failed to parse Claude CLI response: {}", e),
+        )
+    })?;
+
+    let structured = cli_response
+        .get("structured_output")
+        .filter(|v| !v.is_null())
+        .cloned();
+    let result = match cli_response.get("result").and_then(|v| v.as_str()) {
+        Some(result) => result.to_string(),
+        None if structured.is_some() => String::new(),
+        None => {
+            return Err(LlmError::new(
+                ErrorKind::InvalidResponse,
+                "Claude CLI response missing 'result' field",
+            ));
+        }
+    };
+
+    // The CLI exits 0 but flags API errors (rate limits, overloads) in the JSON
+    if cli_response.get("is_error").and_then(|v| v.as_bool()) == Some(true) {
+        return Err(LlmError::new(
+            classify(&result),
+            format!("Claude CLI reported an error: {}", result),
+        ));
+    }
+
+    let usage = cli_response.get("usage");
+    let tokens = |field: &str| usage.and_then(|u| u.get(field)).and_then(|v| v.as_u64());
+    let input_tokens = tokens("input_tokens").map(|input| {
+        input
+            + tokens("cache_creation_input_tokens").unwrap_or(0)
+            + tokens("cache_read_input_tokens").unwrap_or(0)
+    });
+
+    Ok(CliResponse {
+        result,
+        structured,
+        model: cli_response
+            .get("modelUsage")
+            .and_then(|m| m.as_object())
+            .and_then(|m| m.keys().next().cloned()),
+        input_tokens,
+        output_tokens: tokens("output_tokens"),
+        cost_usd: cli_response
+            .get("total_cost_usd")
+            .or_else(|| cli_response.get("cost_usd"))
+            .and_then(|v| v.as_f64()),
+    })
 }
 
 /// Parse a marker-based response (e.g., "HAS_KNOWLEDGE: YES\n<content>")
@@ -117,7 +632,7 @@ fn call_claude_inner(system_prompt: &str, message: &str) -> Result<String, Strin
 /// The marker format is: `MARKER_NAME: YES|NO|TRUE|FALSE`
 /// If positive, content is everything after the marker line.
 /// If no marker found, returns negative with empty content.
-pub fn parse_marker_response(text: &str, marker_name: &str) -> MarkerResponse {
+pub fn parse_marker_response(ws: &Workspace, text: &str, marker_name: &str) -> MarkerResponse {
     let lines: Vec<&str> = text.lines().collect();
     let marker_prefix = format!("{}:", marker_name);
 
@@ -143,6 +658,7 @@ pub fn parse_marker_response(text: &str, marker_name: &str) -> MarkerResponse {
 
     // Fallback: no marker found
     state::log(
+        ws,
         "llm",
         &format!(
             "No {} marker found in response, treating as negative",
@@ -167,32 +683,128 @@ mod tests {
 
     #[test]
     fn test_parse_marker_yes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
         let text = "HAS_KNOWLEDGE: YES\n- First insight\n- Second insight";
-        let result = parse_marker_response(text, "HAS_KNOWLEDGE");
+        let result = parse_marker_response(ws, text, "HAS_KNOWLEDGE");
         assert!(result.is_positive);
         assert_eq!(result.content, "- First insight\n- Second insight");
     }
 
     #[test]
     fn test_parse_marker_no() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
         let text = "HAS_KNOWLEDGE: NO";
-        let result = parse_marker_response(text, "HAS_KNOWLEDGE");
+        let result = parse_marker_response(ws, text, "HAS_KNOWLEDGE");
         assert!(!result.is_positive);
         assert!(result.content.is_empty());
     }
 
     #[test]
     fn test_parse_marker_with_markdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
         let text = "## HAS_RELEVANT: TRUE\nSome content here";
-        let result = parse_marker_response(text, "HAS_RELEVANT");
+        let result = parse_marker_response(ws, text, "HAS_RELEVANT");
         assert!(result.is_positive);
         assert_eq!(result.content, "Some content here");
     }
 
+    #[test]
+    fn test_parse_cli_response_usage() {
+        let stdout = r#"{"type":"result","result":"HAS_KNOWLEDGE: NO","total_cost_usd":0.0123,
+            "usage":{"input_tokens":120,"cache_read_input_tokens":30,"output_tokens":8},
+            "modelUsage":{"claude-sonnet-4-5":{"inputTokens":150}}}"#;
+        let response = parse_cli_response(stdout).unwrap();
+        assert_eq!(response.result, "HAS_KNOWLEDGE: NO");
+        assert_eq!(response.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(response.input_tokens, Some(150));
+        assert_eq!(response.output_tokens, Some(8));
+        assert_eq!(response.cost_usd, Some(0.0123));
+
+        let bare = parse_cli_response(r#"{"result":"ok"}"#).unwrap();
+        assert_eq!(bare.input_tokens, None);
+        assert!(parse_cli_response(r#"{"usage":{}}"#).is_err());
+    }
+
+    #[test]
+    fn test_cli_error_flag_is_classified() {
+        let stdout = r#"{"type":"result","is_error":true,"result":"API Error: 529 Overloaded"}"#;
+        let err = parse_cli_response(stdout).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Overloaded);
+        assert!(err.kind.is_retryable());
+    }
+
+    #[test]
+    fn test_structured_output_and_json_text() {
+        let stdout = r#"{"result":"","structured_output":{"has_knowledge":false,"insights":[]}}"#;
+        let response = parse_cli_response(stdout).unwrap();
+        assert_eq!(
+            response.structured.unwrap()["has_knowledge"],
+            serde_json::Value::Bool(false)
+        );
+
+        let fenced = "Here you go:\n```json\n{\"items\": []}\n```";
+        assert_eq!(
+            parse_json_text(fenced),
+            Some(serde_json::json!({"items": []}))
+        );
+        assert_eq!(parse_json_text("HAS_KNOWLEDGE: NO"), None);
+        assert_eq!(
+            classify("error: unknown option '--json-schema'"),
+            ErrorKind::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify("API Error: 429 rate_limit_error"),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            classify("Prompt is too long: 250000 tokens > 200000 maximum"),
+            ErrorKind::PromptTooLong
+        );
+        assert_eq!(
+            classify("Invalid API key · Please run /login"),
+            ErrorKind::Auth
+        );
+        assert_eq!(classify("fetch failed: ECONNRESET"), ErrorKind::Network);
+        assert_eq!(classify("something odd"), ErrorKind::Other);
+        assert_eq!(classify("HTTP 503"), ErrorKind::Overloaded);
+        assert!(!ErrorKind::PromptTooLong.is_retryable());
+    }
+
+    #[test]
+    fn test_classify_failed_call_uses_error_text_only() {
+        let stdout = r#"{"type":"result","is_error":true,"duration_ms":4290,"num_turns":1,"result":"Something odd happened","usage":{"input_tokens":5010}}"#;
+        assert_eq!(error_text(stdout), "Something odd happened");
+        assert_eq!(classify(&error_text(stdout)), ErrorKind::Other);
+        assert_eq!(classify("duration_ms 4290"), ErrorKind::Other);
+
+        let stdout =
+            r#"{"error":{"type":"overloaded_error","message":"Overloaded"},"duration_ms":429}"#;
+        assert_eq!(classify(&error_text(stdout)), ErrorKind::Overloaded);
+        assert_eq!(error_text("plain failure 401"), "plain failure 401");
+    }
+
+    #[test]
+    fn test_backoff_doubles_with_jitter_and_cap() {
+        assert_eq!(backoff(2, 0, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff(2, 0, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff(2, 2, 1.0), Duration::from_secs(8));
+        assert_eq!(backoff(2, 10, 1.0), MAX_BACKOFF);
+        assert!((0.0..1.0).contains(&jitter()));
+    }
+
     #[test]
     fn test_parse_marker_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
         let text = "No markers here";
-        let result = parse_marker_response(text, "HAS_KNOWLEDGE");
+        let result = parse_marker_response(ws, text, "HAS_KNOWLEDGE");
         assert!(!result.is_positive);
         assert!(result.content.is_empty());
     }
@@ -0,0 +1,120 @@
+//! Advisory file locks for read-modify-write on .wm/ files
+//!
+//! Several Claude sessions in one project run hooks concurrently, so every
+//! read-modify-write of a shared file (config, state.md, distill outputs and
+//! caches, history index) holds a lock on a sibling `<file>.lock` while it works.
+//!
+//! AIDEV-NOTE: Locks are OS advisory locks (flock/LockFileEx via std), so they
+//! are released automatically if a process dies - there are no stale lock files
+//! to clean up. A lock is per open file, so never acquire the same lock twice in
+//! one process (it would wait on itself until the timeout).
+
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another wm process to release a lock
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay between attempts while waiting
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Held lock; released on drop
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+/// Payload of the ErrorKind::TimedOut error returned when a lock isn't released
+/// in time (becomes error::Error::LockTimeout at the library boundary)
+#[derive(Debug)]
+pub struct LockTimeout {
+    /// File the lock guards
+    pub path: PathBuf,
+    pub waited: Duration,
+}
+
+impl fmt::Display for LockTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "timed out after {}s waiting for lock on {} (another wm process is writing it)",
+            self.waited.as_secs(),
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for LockTimeout {}
+
+/// Lock `path` for exclusive read-modify-write, waiting up to LOCK_TIMEOUT
+pub fn acquire(path: &Path) -> io::Result<FileLock> {
+    acquire_with_timeout(path, LOCK_TIMEOUT)
+}
+
+/// Lock `path`, waiting up to `timeout`
+/// Fails with ErrorKind::TimedOut if another process keeps holding the lock.
+pub fn acquire_with_timeout(path: &Path, timeout: Duration) -> io::Result<FileLock> {
+    let lock_path = lock_path(path);
+    if let Some(parent) = lock_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(FileLock { _file: file }),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(RETRY_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    LockTimeout {
+                        path: path.to_path_buf(),
+                        waited: timeout,
+                    },
+                ));
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+}
+
+/// Lock file guarding `path`
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_times_out_while_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let held = acquire(&path).unwrap();
+        let err = acquire_with_timeout(&path, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(dir.path().join("config.toml.lock").exists());
+        assert!(matches!(
+            crate::Error::io("Failed to lock config", err),
+            crate::Error::LockTimeout { .. }
+        ));
+
+        drop(held);
+        assert!(acquire_with_timeout(&path, Duration::from_millis(100)).is_ok());
+    }
+}
@@ -0,0 +1,522 @@
+//! MCP server mode - exposes working memory as Model Context Protocol tools
+//!
+//! Runs a JSON-RPC 2.0 server over stdio (one message per line) so any
+//! MCP-capable agent can pull memory on demand instead of relying on hooks.
+//!
+//! AIDEV-NOTE: stdout is the protocol channel. Nothing in this module (or the
+//! functions it calls) may print to stdout - use the non-printing helpers like
+//! compile::build_working_set and dive::switch_to, and log via state::log.
+
+use crate::category::{self, Category};
+use crate::compile;
+use crate::distill;
+use crate::dive;
+use crate::shared;
+use crate::state;
+use crate::workspace::Workspace;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+
+/// MCP protocol revision we implement
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Maximum number of search results returned by search_memory
+const MAX_SEARCH_RESULTS: usize = 20;
+
+/// URI of a category's distilled file, exposed as a resource
+fn resource_uri(category: &Category) -> String {
+    format!("wm://distill/{}", category.filename)
+}
+
+/// Run wm mcp (blocks until stdin closes)
+pub fn run(ws: &Workspace) -> Result<(), String> {
+    state::log(ws, "mcp", "Server started");
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| format!("Failed to read stdin: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(ws, &message),
+            Err(e) => Some(error_response(
+                Value::Null,
+                -32700,
+                &format!("Parse error: {}", e),
+            )),
+        };
+
+        if let Some(response) = response {
+            writeln!(stdout, "{}", response).map_err(|e| format!("Failed to write: {}", e))?;
+            stdout
+                .flush()
+                .map_err(|e| format!("Failed to flush: {}", e))?;
+        }
+    }
+
+    state::log(ws, "mcp", "Server stopped (stdin closed)");
+    Ok(())
+}
+
+/// Handle one JSON-RPC message, returning a response for requests (None for notifications)
+fn handle_message(ws: &Workspace, message: &Value) -> Option<Value> {
+    let method = message.get("method").and_then(|m| m.as_str())?;
+    // Notifications carry no id and get no response
+    let id = message.get("id")?.clone();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    state::log(ws, "mcp", &format!("Request: {}", method));
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {}, "resources": {} },
+            "serverInfo": { "name": "wm", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions(ws) })),
+        "tools/call" => Ok(call_tool(ws, &params)),
+        "resources/list" => Ok(json!({ "resources": resource_definitions(ws) })),
+        "resources/read" => read_resource(ws, &params),
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+// ============================================================================
+// Tools
+// ============================================================================
+
+fn tool_definitions(ws: &Workspace) -> Value {
+    let categories: Vec<String> = category::all(ws).into_iter().map(|c| c.name).collect();
+    json!([
+        {
+            "name": "search_memory",
+            "description": "Search distilled knowledge (guardrails, metis, ...), state and the current dive prep for items matching a query",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string", "description": "Words to search for" } },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_working_set",
+            "description": "Get the full working set (dive context, guardrails and metis) for this project",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "add_insight",
+            "description": "Record a new insight in a knowledge category: guardrails (hard constraints), metis (soft guidance), or another configured category",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "Self-contained insight about how to work in this project" },
+                    "category": { "type": "string", "enum": categories, "default": "metis" },
+                    "scope": { "type": "string", "enum": ["project", "shared", "user"], "default": "project", "description": "'project' keeps it in .wm/manual/ (kept across distill runs); 'shared' stores it in .wm/shared/ (committed for the team); 'user' stores it in the user-global memory shared by all projects" },
+                },
+                "required": ["text"],
+            },
+        },
+        {
+            "name": "list_dives",
+            "description": "List named dive preps and which one is current",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "switch_dive",
+            "description": "Switch the current dive prep",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string", "description": "Name of the dive prep" } },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+/// Dispatch a tools/call request; tool failures are reported in-band via isError
+fn call_tool(ws: &Workspace, params: &Value) -> Value {
+    let name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
+    let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+    let arg = |key: &str| args.get(key).and_then(|v| v.as_str());
+
+    let result = if !state::is_initialized(ws) {
+        Err("Not initialized. Run 'wm init' first.".to_string())
+    } else {
+        match name {
+            "search_memory" => match arg("query") {
+                Some(query) => Ok(search_memory(ws, query)),
+                None => Err("Missing required argument: query".to_string()),
+            },
+            "get_working_set" => Ok(get_working_set(ws)),
+            "add_insight" => match arg("text") {
+                Some(text) => add_insight(
+                    ws,
+                    text,
+                    arg("category").unwrap_or("metis"),
+                    arg("scope").unwrap_or("project"),
+                ),
+                None => Err("Missing required argument: text".to_string()),
+            },
+            "list_dives" => list_dives(ws),
+            "switch_dive" => match arg("name") {
+                Some(prep) => dive::switch_to(ws, prep)
+                    .map(|_| format!("Switched to dive prep '{}'", prep))
+                    .map_err(String::from),
+                None => Err("Missing required argument: name".to_string()),
+            },
+            _ => Err(format!("Unknown tool: {}", name)),
+        }
+    };
+
+    let (text, is_error) = match result {
+        Ok(text) => (text, false),
+        Err(e) => (e, true),
+    };
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+/// Case-insensitive search over memory items; items matching more terms rank first
+fn search_memory(ws: &Workspace, query: &str) -> String {
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+    if terms.is_empty() {
+        return "Empty query.".to_string();
+    }
+
+    let mut sources: Vec<(String, String)> = category::all(ws)
+        .into_iter()
+        .map(|c| {
+            let content = compile::read_distilled_file(ws, &c.filename);
+            (c.name, content)
+        })
+        .collect();
+    sources.push((
+        "state".to_string(),
+        std::fs::read_to_string(state::wm_path(ws, "state.md")).unwrap_or_default(),
+    ));
+    if let Some(current) = state::current_dive(ws) {
+        let content =
+            std::fs::read_to_string(state::dive_prep_path(ws, &current)).unwrap_or_default();
+        sources.push(("dive".to_string(), content));
+    }
+
+    let mut hits: Vec<(usize, &str, String)> = sources
+        .iter()
+        .flat_map(|(source, content)| {
+            compile::split_items(content)
+                .into_iter()
+                .map(move |item| (source.as_str(), item))
+        })
+        .filter_map(|(source, item)| {
+            let lower = item.to_lowercase();
+            let score = terms.iter().filter(|t| lower.contains(t.as_str())).count();
+            (score > 0).then_some((score, source, item))
+        })
+        .collect();
+
+    if hits.is_empty() {
+        return format!("No memory items match '{}'.", query);
+    }
+
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.0));
+    hits.truncate(MAX_SEARCH_RESULTS);
+    hits.iter()
+        .map(|(_, source, item)| format!("- [{}] {}", source, item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn get_working_set(ws: &Workspace) -> String {
+    let content = compile::build_working_set(ws);
+    if content.trim().is_empty() {
+        "No distilled knowledge found. Run 'wm distill' first.".to_string()
+    } else {
+        content
+    }
+}
+
+fn add_insight(ws: &Workspace, text: &str, category: &str, scope: &str) -> Result<String, String> {
+    if text.trim().is_empty() {
+        return Err("Insight text cannot be empty".to_string());
+    }
+
+    let categories = category::all(ws);
+    let found = category::find(&categories, category).ok_or_else(|| {
+        format!(
+            "Unknown category: {}. Use one of: {}.",
+            category,
+            category::names(&categories)
+        )
+    })?;
+    let (filename, title) = (found.filename.as_str(), found.title.as_str());
+
+    match scope {
+        "project" => {
+            distill::append_manual_item(ws, filename, title, text)?;
+            state::log(ws, "mcp", &format!("Added {} item", category));
+            Ok(format!("Added to manual/{}", filename))
+        }
+        "user" => {
+            let added = distill::merge_user_items(filename, title, &[text.trim().to_string()])?;
+            state::log(ws, "mcp", &format!("Added user-level {} item", category));
+            if added > 0 {
+                Ok(format!("Added to user-global {}", filename))
+            } else {
+                Ok(format!("Already present in user-global {}", filename))
+            }
+        }
+        "shared" => {
+            let added = shared::add_items(ws, filename, &[text.trim().to_string()])?;
+            state::log(ws, "mcp", &format!("Added shared {} item", category));
+            if added > 0 {
+                Ok(format!("Added to shared/{}", filename))
+            } else {
+                Ok(format!("Already present in shared/{}", filename))
+            }
+        }
+        other => Err(format!(
+            "Unknown scope: {}. Use 'project', 'shared' or 'user'.",
+            other
+        )),
+    }
+}
+
+fn list_dives(ws: &Workspace) -> Result<String, String> {
+    let preps = dive::list_preps(ws)?;
+    if preps.is_empty() {
+        return Ok("No dive preps found.".to_string());
+    }
+
+    Ok(preps
+        .iter()
+        .map(|prep| {
+            let marker = if prep.is_current { "* " } else { "  " };
+            format!("{}{}", marker, prep.name)
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+// ============================================================================
+// Resources
+// ============================================================================
+
+/// One resource per category's distilled file
+fn resource_definitions(ws: &Workspace) -> Value {
+    category::all(ws)
+        .iter()
+        .map(|category| {
+            json!({
+                "uri": resource_uri(category),
+                "name": category.filename,
+                "description": category.description,
+                "mimeType": "text/markdown",
+            })
+        })
+        .collect()
+}
+
+fn read_resource(ws: &Workspace, params: &Value) -> Result<Value, (i64, String)> {
+    let uri = params.get("uri").and_then(|u| u.as_str()).unwrap_or("");
+    let categories = category::all(ws);
+    let filename = &categories
+        .iter()
+        .find(|category| resource_uri(category) == uri)
+        .ok_or_else(|| (-32602, format!("Unknown resource: {}", uri)))?
+        .filename;
+
+    Ok(json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": "text/markdown",
+            "text": compile::read_distilled_file(ws, filename),
+        }],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_gets_no_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let message = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_message(ws, &message).is_none());
+    }
+
+    #[test]
+    fn test_initialize_and_unknown_method() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let init = handle_message(
+            ws,
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }),
+        )
+        .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(init["id"], 1);
+
+        let unknown =
+            handle_message(ws, &json!({ "jsonrpc": "2.0", "id": 2, "method": "bogus" })).unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_tools_list_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let tools = tool_definitions(ws);
+        let names: Vec<&str> = tools
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "search_memory",
+                "get_working_set",
+                "add_insight",
+                "list_dives",
+                "switch_dive"
+            ]
+        );
+    }
+
+    /// Call a tool against `ws`, returning its text and whether it failed
+    fn call(ws: &Workspace, name: &str, arguments: Value) -> (String, bool) {
+        let result = call_tool(ws, &json!({ "name": name, "arguments": arguments }));
+        (
+            result["content"][0]["text"].as_str().unwrap().to_string(),
+            result["isError"].as_bool().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_add_insight_survives_distill() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::init(dir.path()).unwrap();
+
+        let (text, failed) = call(
+            ws,
+            "add_insight",
+            json!({ "text": "Run migrations with sqlx", "category": "guardrails" }),
+        );
+        assert!(!failed, "{}", text);
+        assert!(ws.wm_dir().join("manual/guardrails.md").exists());
+
+        // Pass 2 rewrites the distilled file; the manual item is kept apart
+        std::fs::create_dir_all(ws.wm_dir().join("distill")).unwrap();
+        std::fs::write(
+            ws.wm_dir().join("distill/guardrails.md"),
+            "# Guardrails\n\n- Never force push\n",
+        )
+        .unwrap();
+        let (working_set, _) = call(ws, "get_working_set", json!({}));
+        assert!(working_set.contains("- Never force push"));
+        assert!(working_set.contains("- Run migrations with sqlx"));
+
+        let (text, failed) = call(
+            ws,
+            "add_insight",
+            json!({ "text": "Same for the team", "scope": "shared" }),
+        );
+        assert!(!failed, "{}", text);
+        assert!(
+            std::fs::read_to_string(ws.wm_dir().join("shared/metis.md"))
+                .unwrap()
+                .contains("Same for the team")
+        );
+
+        let (text, failed) = call(
+            ws,
+            "add_insight",
+            json!({ "text": "x", "category": "bogus" }),
+        );
+        assert!(failed);
+        assert!(text.starts_with("Unknown category: bogus"));
+        let (_, failed) = call(ws, "add_insight", json!({ "text": "x", "scope": "team" }));
+        assert!(failed);
+    }
+
+    #[test]
+    fn test_search_memory_ranks_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::init(dir.path()).unwrap();
+        std::fs::create_dir_all(ws.wm_dir().join("distill")).unwrap();
+        std::fs::write(
+            ws.wm_dir().join("distill/metis.md"),
+            "# Metis\n\n- Prefer small commits\n- Run the auth tests before pushing auth changes\n",
+        )
+        .unwrap();
+        call(
+            ws,
+            "add_insight",
+            json!({ "text": "Auth tokens expire hourly" }),
+        );
+
+        let (text, failed) = call(ws, "search_memory", json!({ "query": "auth tests" }));
+        assert!(!failed);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- [metis] Run the auth tests before pushing auth changes",
+                "- [metis] Auth tokens expire hourly",
+            ]
+        );
+
+        let (text, _) = call(ws, "search_memory", json!({ "query": "kubernetes" }));
+        assert_eq!(text, "No memory items match 'kubernetes'.");
+        let (_, failed) = call(ws, "search_memory", json!({}));
+        assert!(failed);
+    }
+
+    #[test]
+    fn test_switch_dive() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::init(dir.path()).unwrap();
+        ws.create_dive("auth-rework", None).unwrap();
+
+        let (text, failed) = call(ws, "switch_dive", json!({ "name": "auth-rework" }));
+        assert!(!failed, "{}", text);
+        assert_eq!(ws.current_dive().as_deref(), Some("auth-rework"));
+        let (text, _) = call(ws, "list_dives", json!({}));
+        assert_eq!(text, "* auth-rework");
+
+        let (_, failed) = call(ws, "switch_dive", json!({ "name": "missing" }));
+        assert!(failed);
+        assert_eq!(ws.current_dive().as_deref(), Some("auth-rework"));
+    }
+
+    #[test]
+    fn test_tools_need_initialized_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let (text, failed) = call(ws, "search_memory", json!({ "query": "x" }));
+        assert!(failed);
+        assert!(text.starts_with("Not initialized"));
+    }
+}
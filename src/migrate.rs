@@ -0,0 +1,452 @@
+//! Schema versioning and migrations for the .wm/ layout
+//!
+//! config.toml records the layout `version` a store was last migrated to.
+//! Stores from older wm releases are upgraded automatically on first use, and
+//! `wm migrate --dry-run` previews what would change.
+//!
+//! To change the layout: add a migration to MIGRATIONS with the next version
+//! number and bump CURRENT_VERSION. Migrations must be idempotent - a store
+//! may have been partly fixed by hand.
+//!
+//! AIDEV-NOTE: Migrations run under .wm/migrate.lock and re-check the version
+//! after acquiring it, so concurrent hooks can't migrate the same store twice.
+
+use crate::history;
+use crate::lock;
+use crate::shared;
+use crate::state;
+use crate::workspace::Workspace;
+use std::fs;
+
+/// Layout version written by this build
+pub const CURRENT_VERSION: u32 = 3;
+
+/// A single layout upgrade
+struct Migration {
+    /// Version the store is at after this migration
+    version: u32,
+
+    description: &'static str,
+
+    /// Apply the migration (or only describe it when dry_run), returning the changes
+    apply: fn(ws: &Workspace, dry_run: bool) -> Result<Vec<String>, String>,
+}
+
+/// All migrations, in version order
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Consolidate legacy files",
+        apply: migrate_v1,
+    },
+    Migration {
+        version: 2,
+        description: "Commit .wm/ignore",
+        apply: migrate_v2,
+    },
+    Migration {
+        version: 3,
+        description: "Commit .wm/prompts/",
+        apply: migrate_v3,
+    },
+];
+
+/// Changes made (or planned) by one migration
+#[derive(Debug)]
+pub struct MigrationReport {
+    pub version: u32,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+/// Layout version of the current store
+pub fn store_version(ws: &Workspace) -> u32 {
+    state::read_config(ws).version
+}
+
+/// Run all pending migrations (or only plan them when dry_run)
+pub fn migrate(ws: &Workspace, dry_run: bool) -> Result<Vec<MigrationReport>, String> {
+    let _lock = lock::acquire(&state::wm_path(ws, "migrate"))
+        .map_err(|e| format!("Failed to lock .wm/ for migration: {}", e))?;
+
+    // An unparseable config would read as version 0 and be overwritten with defaults
+    let from = state::parse_config(ws)?.version;
+    let mut reports = Vec::new();
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        let mut changes = (migration.apply)(ws, dry_run)?;
+        changes.push(format!("Record layout version {}", migration.version));
+
+        if !dry_run {
+            state::update_config(ws, |config| config.version = migration.version)
+                .map_err(|e| format!("Failed to update config: {}", e))?;
+            state::log(
+                ws,
+                "migrate",
+                &format!("Migrated to v{}: {}", migration.version, changes.join("; ")),
+            );
+        }
+
+        reports.push(MigrationReport {
+            version: migration.version,
+            description: migration.description,
+            changes,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// Upgrade an older store on first use; failures are logged, never fatal
+/// Called before every command except init/migrate, and by Workspace::open.
+pub fn auto_migrate(ws: &Workspace) {
+    if !state::is_initialized(ws) {
+        return;
+    }
+    match state::parse_config(ws) {
+        Ok(config) if config.version < CURRENT_VERSION => {}
+        _ => return,
+    }
+    if let Err(e) = migrate(ws, false) {
+        state::log(ws, "migrate", &format!("Automatic migration failed: {}", e));
+    }
+}
+
+/// Run wm migrate
+pub fn run(ws: &Workspace, dry_run: bool) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+
+    let version = state::parse_config(ws)?.version;
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            ".wm/ is at layout version {}, newer than this wm supports ({}). Upgrade wm.",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    let reports = migrate(ws, dry_run)?;
+    if reports.is_empty() {
+        println!("Already at layout version {}.", version);
+        return Ok(());
+    }
+
+    for report in &reports {
+        println!("v{}: {}", report.version, report.description);
+        for change in &report.changes {
+            println!("  - {}", change);
+        }
+    }
+    if dry_run {
+        println!("\nDry run - nothing changed. Run 'wm migrate' to apply.");
+    } else {
+        println!("\nMigrated .wm/ to layout version {}.", CURRENT_VERSION);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Migrations
+// ============================================================================
+
+/// v1: remove dead files, move shadowed dive files into named preps,
+/// bring the compress backup under history, add git ignore/attributes
+///
+/// AIDEV-NOTE: dive_context.md is still what /dive-prep writes, so it is only
+/// moved when a named prep is current (compile ignores it then). OH_context.md
+/// has no writers left and is always moved; it stays the active dive if
+/// compile was reading it.
+fn migrate_v1(ws: &Workspace, dry_run: bool) -> Result<Vec<String>, String> {
+    let mut changes = Vec::new();
+    let wm = |file: &str| state::wm_path(ws, file);
+
+    for dead in ["checkpoint.json", "state.md.tmp"] {
+        if wm(dead).exists() {
+            changes.push(format!("Remove unused {}", dead));
+            if !dry_run {
+                fs::remove_file(wm(dead))
+                    .map_err(|e| format!("Failed to remove {}: {}", dead, e))?;
+            }
+        }
+    }
+
+    let backup = wm("state.md.backup");
+    if backup.exists() {
+        changes.push("Import state.md.backup into history and remove it".to_string());
+        if !dry_run {
+            let content = fs::read_to_string(&backup)
+                .map_err(|e| format!("Failed to read state.md.backup: {}", e))?;
+            history::import(ws, "state.md", &content, "import state.md.backup")?;
+            fs::remove_file(&backup)
+                .map_err(|e| format!("Failed to remove state.md.backup: {}", e))?;
+        }
+    }
+
+    let mut current = state::current_dive(ws);
+    for (legacy, prep) in [
+        ("dive_context.md", "dive-context"),
+        ("OH_context.md", "oh-context"),
+    ] {
+        let path = wm(legacy);
+        if !path.exists() || (legacy == "dive_context.md" && current.is_none()) {
+            continue;
+        }
+
+        let name = unused_prep_name(ws, prep);
+        changes.push(format!("Move {} to dives/{}.md", legacy, name));
+        let activate = current.is_none() && !wm("dive_context.md").exists();
+        if activate {
+            changes.push(format!("Set current dive prep to '{}'", name));
+            current = Some(name.clone());
+        }
+
+        if !dry_run {
+            state::ensure_dive_dir(ws)
+                .map_err(|e| format!("Failed to create dives directory: {}", e))?;
+            fs::rename(&path, state::dive_prep_path(ws, &name))
+                .map_err(|e| format!("Failed to move {}: {}", legacy, e))?;
+            if activate {
+                state::set_current_dive(ws, Some(&name))
+                    .map_err(|e| format!("Failed to update config: {}", e))?;
+            }
+        }
+    }
+
+    for (file, content) in [
+        (".gitignore", shared::GITIGNORE),
+        (".gitattributes", shared::GITATTRIBUTES),
+    ] {
+        if !wm(file).exists() {
+            changes.push(format!("Write {}", file));
+            if !dry_run {
+                fs::write(wm(file), content)
+                    .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// v2: let .wm/ignore be committed, like guardrails.toml
+fn migrate_v2(ws: &Workspace, dry_run: bool) -> Result<Vec<String>, String> {
+    extend_gitignore(ws, &["!ignore"], dry_run)
+}
+
+/// v3: let prompt overrides in .wm/prompts/ be committed
+fn migrate_v3(ws: &Workspace, dry_run: bool) -> Result<Vec<String>, String> {
+    extend_gitignore(ws, &["!prompts/", "!prompts/*.md"], dry_run)
+}
+
+/// Append `lines` missing from .wm/.gitignore (writing the current one if absent)
+/// AIDEV-NOTE: Appending is enough: the file starts with `*` and later
+/// negations win. Lines the user added or reordered are left alone.
+fn extend_gitignore(ws: &Workspace, lines: &[&str], dry_run: bool) -> Result<Vec<String>, String> {
+    let path = state::wm_path(ws, ".gitignore");
+    let Ok(mut content) = fs::read_to_string(&path) else {
+        if !dry_run {
+            fs::write(&path, shared::GITIGNORE)
+                .map_err(|e| format!("Failed to write .gitignore: {}", e))?;
+        }
+        return Ok(vec!["Write .gitignore".to_string()]);
+    };
+
+    let missing: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| !content.lines().any(|existing| existing.trim() == *line))
+        .collect();
+    if missing.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if !dry_run {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for line in &missing {
+            content.push_str(line);
+            content.push('\n');
+        }
+        fs::write(&path, content).map_err(|e| format!("Failed to write .gitignore: {}", e))?;
+    }
+    Ok(vec![format!("Add {} to .gitignore", missing.join(", "))])
+}
+
+/// First prep name not already taken (name, name-2, name-3, ...)
+fn unused_prep_name(ws: &Workspace, base: &str) -> String {
+    let mut name = base.to_string();
+    let mut n = 2;
+    while state::dive_prep_path(ws, &name).exists() {
+        name = format!("{}-{}", base, n);
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    #[test]
+    fn test_migrations_are_ordered_and_reach_current() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(versions.last(), Some(&CURRENT_VERSION));
+    }
+
+    /// A pre-versioning store (no `version` in config.toml)
+    fn legacy_store() -> (tempfile::TempDir, Workspace) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".wm")).unwrap();
+        fs::write(dir.path().join(".wm/config.toml"), "").unwrap();
+        let ws = Workspace::new(dir.path().to_path_buf());
+        (dir, ws)
+    }
+
+    /// Every file under .wm/ (lock files aside) with its content
+    fn snapshot(ws: &Workspace) -> BTreeMap<String, String> {
+        fn walk(dir: &Path, root: &Path, out: &mut BTreeMap<String, String>) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    walk(&path, root, out);
+                } else if path.extension().is_none_or(|ext| ext != "lock") {
+                    let rel = path
+                        .strip_prefix(root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string();
+                    out.insert(rel, fs::read_to_string(&path).unwrap_or_default());
+                }
+            }
+        }
+        let mut out = BTreeMap::new();
+        walk(&ws.wm_dir(), &ws.wm_dir(), &mut out);
+        out
+    }
+
+    #[test]
+    fn test_migrate_v1_moves_legacy_files() {
+        let (_dir, ws) = &legacy_store();
+        for (file, content) in [
+            ("checkpoint.json", "{}"),
+            ("state.md.tmp", "partial"),
+            ("dive_context.md", "# Dive: legacy"),
+            ("OH_context.md", "# OH context"),
+            ("dives/current.md", "# Dive: current"),
+            ("dives/oh-context.md", "# Taken"),
+        ] {
+            let path = state::wm_path(ws, file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        state::set_current_dive(ws, Some("current")).unwrap();
+
+        let reports = migrate(ws, false).unwrap();
+        assert_eq!(reports.len(), MIGRATIONS.len());
+
+        for gone in [
+            "checkpoint.json",
+            "state.md.tmp",
+            "dive_context.md",
+            "OH_context.md",
+        ] {
+            assert!(!state::wm_path(ws, gone).exists(), "{} still there", gone);
+        }
+        let prep = |name: &str| fs::read_to_string(state::dive_prep_path(ws, name)).unwrap();
+        assert_eq!(prep("dive-context"), "# Dive: legacy");
+        assert_eq!(prep("oh-context"), "# Taken");
+        assert_eq!(prep("oh-context-2"), "# OH context");
+        // A named prep was already current, so it stays current
+        assert_eq!(state::current_dive(ws).as_deref(), Some("current"));
+        assert!(state::wm_path(ws, ".gitignore").exists());
+        assert_eq!(store_version(ws), CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_v1_activates_oh_context() {
+        let (_dir, ws) = &legacy_store();
+        fs::write(state::wm_path(ws, "OH_context.md"), "# OH context").unwrap();
+
+        migrate(ws, false).unwrap();
+        assert_eq!(state::current_dive(ws).as_deref(), Some("oh-context"));
+    }
+
+    #[test]
+    fn test_migrate_dry_run_changes_nothing() {
+        let (_dir, ws) = &legacy_store();
+        fs::write(state::wm_path(ws, "checkpoint.json"), "{}").unwrap();
+        fs::write(state::wm_path(ws, "OH_context.md"), "# OH context").unwrap();
+        let before = snapshot(ws);
+
+        let reports = migrate(ws, true).unwrap();
+        assert!(
+            reports[0]
+                .changes
+                .contains(&"Remove unused checkpoint.json".to_string())
+        );
+        assert!(
+            reports[0]
+                .changes
+                .contains(&"Move OH_context.md to dives/oh-context.md".to_string())
+        );
+        assert_eq!(snapshot(ws), before);
+        assert_eq!(store_version(ws), 0);
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let (_dir, ws) = &legacy_store();
+        fs::write(state::wm_path(ws, "checkpoint.json"), "{}").unwrap();
+        fs::write(state::wm_path(ws, "OH_context.md"), "# OH context").unwrap();
+
+        migrate(ws, false).unwrap();
+        let after = snapshot(ws);
+        assert!(migrate(ws, false).unwrap().is_empty());
+        // Re-applying a migration to an already migrated store changes nothing
+        for migration in MIGRATIONS {
+            assert_eq!((migration.apply)(ws, false).unwrap(), Vec::<String>::new());
+        }
+        assert_eq!(snapshot(ws), after);
+    }
+
+    #[test]
+    fn test_migrate_v2_extends_gitignore() {
+        let (_dir, ws) = &legacy_store();
+        let v1_gitignore = "*\n!.gitignore\n!guardrails.toml\n!shared/\n!shared/*.md\n";
+        fs::write(state::wm_path(ws, ".gitignore"), v1_gitignore).unwrap();
+        state::update_config(ws, |config| config.version = 1).unwrap();
+
+        let reports = migrate(ws, false).unwrap();
+        assert_eq!(reports[0].changes[0], "Add !ignore to .gitignore");
+        assert!(
+            fs::read_to_string(state::wm_path(ws, ".gitignore"))
+                .unwrap()
+                .starts_with(&format!("{}!ignore\n", v1_gitignore))
+        );
+    }
+
+    #[test]
+    fn test_migrate_v3_extends_gitignore() {
+        let (_dir, ws) = &legacy_store();
+        let v2_gitignore = "*\n!.gitignore\n!guardrails.toml\n!ignore\n!shared/\n!shared/*.md\n";
+        fs::write(state::wm_path(ws, ".gitignore"), v2_gitignore).unwrap();
+        state::update_config(ws, |config| config.version = 2).unwrap();
+
+        let reports = migrate(ws, false).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(
+            reports[0].changes[0],
+            "Add !prompts/, !prompts/*.md to .gitignore"
+        );
+        assert_eq!(
+            fs::read_to_string(state::wm_path(ws, ".gitignore")).unwrap(),
+            format!("{}!prompts/\n!prompts/*.md\n", v2_gitignore)
+        );
+        assert_eq!(state::read_config(ws).version, 3);
+    }
+}
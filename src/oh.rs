@@ -7,6 +7,7 @@
 //! Requires OH_API_KEY env var. OH_API_URL defaults to https://app.openhorizons.me
 
 use crate::state;
+use crate::workspace::Workspace;
 use serde::{Deserialize, Serialize};
 
 /// Default OH API URL
@@ -43,6 +44,7 @@ pub struct PushResult {
 ///
 /// Returns the number of items successfully pushed and any errors.
 pub fn push_candidates(
+    ws: &Workspace,
     context_id: &str,
     guardrails: &[String],
     metis: &[String],
@@ -53,6 +55,7 @@ pub fn push_candidates(
     let api_url = std::env::var("OH_API_URL").unwrap_or_else(|_| DEFAULT_OH_API_URL.to_string());
 
     state::log(
+        ws,
         "oh",
         &format!(
             "Pushing {} guardrails and {} metis to OH context {}",
@@ -73,13 +76,14 @@ pub fn push_candidates(
         match push_single_candidate(&api_url, &api_key, context_id, "guardrail", item) {
             Ok(candidate_id) => {
                 state::log(
+                    ws,
                     "oh",
                     &format!("Created guardrail candidate: {}", candidate_id),
                 );
                 result.guardrails_pushed += 1;
             }
             Err(e) => {
-                state::log("oh", &format!("Failed to push guardrail: {}", e));
+                state::log(ws, "oh", &format!("Failed to push guardrail: {}", e));
                 result.errors.push((truncate_for_error(item), e));
             }
         }
@@ -89,11 +93,15 @@ pub fn push_candidates(
     for item in metis {
         match push_single_candidate(&api_url, &api_key, context_id, "metis", item) {
             Ok(candidate_id) => {
-                state::log("oh", &format!("Created metis candidate: {}", candidate_id));
+                state::log(
+                    ws,
+                    "oh",
+                    &format!("Created metis candidate: {}", candidate_id),
+                );
                 result.metis_pushed += 1;
             }
             Err(e) => {
-                state::log("oh", &format!("Failed to push metis: {}", e));
+                state::log(ws, "oh", &format!("Failed to push metis: {}", e));
                 result.errors.push((truncate_for_error(item), e));
             }
         }
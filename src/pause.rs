@@ -0,0 +1,293 @@
+//! wm pause / resume / status
+//!
+//! A pause is project-wide (stored in .wm/config.toml) or scoped to one session
+//! (stored in .wm/sessions/<id>/paused.json), and either lasts until resumed or
+//! ends by itself (`--for 2h`, `--until 17:30`). state::is_extract_enabled and
+//! state::is_compile_enabled check both scopes against the clock.
+
+use crate::state;
+use crate::types::{Operation, Pause};
+use crate::workspace::Workspace;
+use chrono::{
+    DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound, TimeZone, Utc,
+};
+use std::fs;
+
+/// How long and where a pause applies
+#[derive(Debug, Default)]
+pub struct PauseOptions {
+    /// Duration like 30m, 2h, 1h30m, 1d
+    pub duration: Option<String>,
+
+    /// End time: HH:MM (next occurrence), YYYY-MM-DD HH:MM (local), or RFC 3339
+    pub until: Option<String>,
+
+    /// Only pause this session
+    pub session: Option<String>,
+}
+
+/// Run wm pause [operation]
+pub fn run_pause(
+    ws: &Workspace,
+    operation: Option<&str>,
+    options: PauseOptions,
+) -> Result<(), String> {
+    ensure_initialized(ws)?;
+    let ops = parse_operations(operation)?;
+
+    let now = Utc::now();
+    let until = match (&options.duration, &options.until) {
+        (Some(duration), _) => Some(now + parse_duration(duration)?),
+        (None, Some(until)) => Some(parse_until(until, Local::now())?),
+        (None, None) => None,
+    };
+    let pause = Pause {
+        until: until.map(|t| t.trunc_subsecs(0)),
+    };
+
+    set_paused(ws, &ops, Some(pause), options.session.as_deref())?;
+
+    let names: Vec<&str> = ops.iter().map(|op| op.name()).collect();
+    println!(
+        "Paused: {}{} ({})",
+        names.join(", "),
+        scope_label(options.session.as_deref()),
+        describe(&pause, now)
+    );
+    Ok(())
+}
+
+/// Run wm resume [operation]
+pub fn run_resume(
+    ws: &Workspace,
+    operation: Option<&str>,
+    session: Option<&str>,
+) -> Result<(), String> {
+    ensure_initialized(ws)?;
+    let ops = parse_operations(operation)?;
+    set_paused(ws, &ops, None, session)?;
+
+    let names: Vec<&str> = ops.iter().map(|op| op.name()).collect();
+    println!("Resumed: {}{}", names.join(", "), scope_label(session));
+    Ok(())
+}
+
+/// Run wm status - project and session pauses, with remaining time
+pub fn run_status(ws: &Workspace) -> Result<(), String> {
+    ensure_initialized(ws)?;
+    let now = Utc::now();
+    let config = state::read_config(ws);
+
+    for op in Operation::ALL {
+        match config.operations.pause(op, now) {
+            Some(pause) => println!("{}: paused ({})", op.name(), describe(&pause, now)),
+            None => println!("{}: running", op.name()),
+        }
+    }
+
+    for session in sessions_with_pauses(ws) {
+        let pauses = state::read_session_pauses(ws, &session);
+        for op in Operation::ALL {
+            if let Some(pause) = pauses.pause(op, now) {
+                println!(
+                    "{}: paused in session {} ({})",
+                    op.name(),
+                    session,
+                    describe(&pause, now)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn ensure_initialized(ws: &Workspace) -> Result<(), String> {
+    if state::is_initialized(ws) {
+        Ok(())
+    } else {
+        Err("Not initialized. Run 'wm init' first.".to_string())
+    }
+}
+
+/// Operations named on the command line (None = both)
+fn parse_operations(operation: Option<&str>) -> Result<Vec<Operation>, String> {
+    match operation {
+        None => Ok(Operation::ALL.to_vec()),
+        Some(name) => Operation::parse(name)
+            .map(|op| vec![op])
+            .ok_or_else(|| format!("Unknown operation: {}. Use 'extract' or 'compile'.", name)),
+    }
+}
+
+/// Pause (Some) or resume (None) operations project-wide or for one session
+fn set_paused(
+    ws: &Workspace,
+    ops: &[Operation],
+    pause: Option<Pause>,
+    session: Option<&str>,
+) -> Result<(), String> {
+    match session {
+        Some(session) => state::update_session_pauses(ws, session, |pauses| {
+            for op in ops {
+                pauses.set_paused(*op, pause);
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write session pauses: {}", e)),
+        None => state::update_config(ws, |config| {
+            for op in ops {
+                config.operations.set_paused(*op, pause);
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write config: {}", e)),
+    }
+}
+
+fn scope_label(session: Option<&str>) -> String {
+    session
+        .map(|id| format!(" in session {}", id))
+        .unwrap_or_default()
+}
+
+/// Session IDs under .wm/sessions/ that have a pause file
+fn sessions_with_pauses(ws: &Workspace) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(state::wm_path(ws, "sessions")) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("paused.json").exists())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    sessions.sort();
+    sessions
+}
+
+/// "until resumed" or "1h 20m left, until 2026-10-18 17:30"
+fn describe(pause: &Pause, now: DateTime<Utc>) -> String {
+    match pause.until {
+        None => "until resumed".to_string(),
+        Some(until) => format!(
+            "{} left, until {}",
+            format_remaining(until - now),
+            until.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        ),
+    }
+}
+
+/// Compact remaining time: 2d 3h, 1h 20m, 5m, <1m
+fn format_remaining(remaining: Duration) -> String {
+    let minutes = remaining.num_minutes();
+    let (days, hours, mins) = (minutes / 1440, minutes % 1440 / 60, minutes % 60);
+    match (days, hours, mins) {
+        (0, 0, 0) => "<1m".to_string(),
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+/// Parse a duration like 30m, 2h, 1h30m, 1d (units: s, m, h, d)
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration '{}'. Use e.g. 30m, 2h, 1h30m, 1d.", input);
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        digits.clear();
+        total += match c {
+            's' => Duration::seconds(n),
+            'm' => Duration::minutes(n),
+            'h' => Duration::hours(n),
+            'd' => Duration::days(n),
+            _ => return Err(invalid()),
+        };
+    }
+
+    if !digits.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Parse an end time relative to `now` (local time unless an offset is given)
+pub fn parse_until(input: &str, now: DateTime<Local>) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+    let local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(|| format!("'{}' doesn't exist in the local timezone", input))
+    };
+
+    let until = if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        time.with_timezone(&Utc)
+    } else if let Ok(naive) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M"))
+    {
+        local(naive)?.with_timezone(&Utc)
+    } else if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        local(date.and_time(NaiveTime::MIN))?.with_timezone(&Utc)
+    } else if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+        // Next occurrence: today if still ahead, otherwise tomorrow
+        let today = local(now.date_naive().and_time(time))?;
+        let next = if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        };
+        next.with_timezone(&Utc)
+    } else {
+        return Err(format!(
+            "Invalid time '{}'. Use HH:MM, YYYY-MM-DD HH:MM or RFC 3339.",
+            input
+        ));
+    };
+
+    if until <= now.with_timezone(&Utc) {
+        return Err(format!("'{}' is in the past", input));
+    }
+    Ok(until)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("2x").is_err());
+        assert!(parse_duration("0m").is_err());
+    }
+
+    #[test]
+    fn test_parse_until_next_occurrence() {
+        let now = Local.with_ymd_and_hms(2026, 3, 10, 15, 0, 0).unwrap();
+        let later = parse_until("17:30", now).unwrap().with_timezone(&Local);
+        assert_eq!(later.format("%d %H:%M").to_string(), "10 17:30");
+
+        let tomorrow = parse_until("09:00", now).unwrap().with_timezone(&Local);
+        assert_eq!(tomorrow.format("%d %H:%M").to_string(), "11 09:00");
+
+        assert!(parse_until("2020-01-01 10:00", now).is_err());
+        assert!(parse_until("soon", now).is_err());
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(Duration::seconds(30)), "<1m");
+        assert_eq!(format_remaining(Duration::minutes(80)), "1h 20m");
+        assert_eq!(format_remaining(Duration::hours(51)), "2d 3h");
+    }
+}
@@ -0,0 +1,338 @@
+//! Overridable system prompts
+//!
+//! The instructions wm gives the LLM live in templates, looked up in order:
+//!
+//! 1. `.wm/prompts/<name>.md` (project, committed with the team's knowledge)
+//! 2. `~/.config/wm/prompts/<name>.md` (user-global)
+//! 3. Built-in defaults (src/prompts/*.md)
+//!
+//! Templates may use `{{variable}}` placeholders (see variables()). `wm prompts
+//! show` prints the effective template and where it comes from; `wm prompts
+//! eject` copies the built-in default out for editing.
+//!
+//! AIDEV-NOTE: Templates only hold the guidance. The output format (markers or
+//! the JSON schema description) is appended by the caller, since wm's parsers
+//! depend on it - an edited template can't break parsing.
+
+use crate::category::{self, Category};
+use crate::state;
+use crate::workspace::Workspace;
+use chrono::Local;
+use std::fs;
+use std::path::PathBuf;
+
+/// Prompt directory within .wm/ and the user-global directory
+pub const PROMPTS_DIR: &str = "prompts";
+
+/// A built-in prompt template
+#[derive(Debug, Clone, Copy)]
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: &'static str,
+}
+
+/// All templates, in pipeline order
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "extract",
+        description: "Per-turn extraction into state.md (wm extract)",
+        default: include_str!("prompts/extract.md"),
+    },
+    Template {
+        name: "distill-extract",
+        description: "Distill Pass 1: insights from one session",
+        default: include_str!("prompts/distill-extract.md"),
+    },
+    Template {
+        name: "distill-categorize",
+        description: "Distill Pass 2: sort insights into categories",
+        default: include_str!("prompts/distill-categorize.md"),
+    },
+    Template {
+        name: "compress",
+        description: "Compression of state.md (wm compress)",
+        default: include_str!("prompts/compress.md"),
+    },
+    Template {
+        name: "lint",
+        description: "Conflicting knowledge items (wm lint)",
+        default: include_str!("prompts/lint.md"),
+    },
+];
+
+/// Variables available to every template, besides one per category
+const FIXED_VARIABLES: &[(&str, &str)] = &[
+    ("project_name", "Name of the project directory"),
+    ("project_root", "Absolute path of the project"),
+    ("date", "Today's date (YYYY-MM-DD)"),
+];
+
+/// Variable holding a category's current file: "known-pitfalls" -> "existing_known_pitfalls"
+fn existing_variable(category: &Category) -> String {
+    format!("existing_{}", category.name.replace(['-', ' '], "_"))
+}
+
+/// All template variables and what they hold, including `existing_<category>`
+/// for each configured category
+pub fn variables(ws: &Workspace) -> Vec<(String, String)> {
+    let fixed = FIXED_VARIABLES
+        .iter()
+        .map(|(name, description)| (name.to_string(), description.to_string()));
+    let existing = category::all(ws).into_iter().map(|category| {
+        (
+            existing_variable(&category),
+            format!("Current .wm/distill/{}", category.filename),
+        )
+    });
+    fixed.chain(existing).collect()
+}
+
+/// Where the effective template comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Project(PathBuf),
+    Global(PathBuf),
+    BuiltIn,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Project(path) | Source::Global(path) => write!(f, "{}", path.display()),
+            Source::BuiltIn => write!(f, "built-in"),
+        }
+    }
+}
+
+fn find(name: &str) -> Result<&'static Template, String> {
+    TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names: Vec<&str> = TEMPLATES.iter().map(|t| t.name).collect();
+        format!(
+            "Unknown prompt '{}'. Use one of: {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+fn project_path(ws: &Workspace, name: &str) -> PathBuf {
+    state::wm_path(ws, PROMPTS_DIR).join(format!("{}.md", name))
+}
+
+fn global_path(name: &str) -> Option<PathBuf> {
+    state::global_path(PROMPTS_DIR).map(|dir| dir.join(format!("{}.md", name)))
+}
+
+/// The effective template text and its source
+fn resolve(ws: &Workspace, template: &Template) -> (String, Source) {
+    let project = project_path(ws, template.name);
+    if let Ok(content) = fs::read_to_string(&project) {
+        return (content, Source::Project(project));
+    }
+    if let Some(global) = global_path(template.name)
+        && let Ok(content) = fs::read_to_string(&global)
+    {
+        return (content, Source::Global(global));
+    }
+    (template.default.to_string(), Source::BuiltIn)
+}
+
+/// Load and render a template by name
+/// Panics on an unknown name - callers pass one of TEMPLATES' names.
+pub fn load(ws: &Workspace, name: &str) -> String {
+    let template = find(name).expect("built-in prompt template");
+    let (content, source) = resolve(ws, template);
+    if source != Source::BuiltIn {
+        state::log(
+            ws,
+            "prompts",
+            &format!("Using {} prompt from {}", name, source),
+        );
+    }
+    render(ws, &content, &|var| variable(ws, var))
+}
+
+/// Current value of a template variable (None = unknown variable)
+fn variable(ws: &Workspace, name: &str) -> Option<String> {
+    let root = ws.root();
+    match name {
+        "project_name" => Some(
+            root.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        ),
+        "project_root" => Some(root.display().to_string()),
+        "date" => Some(Local::now().format("%Y-%m-%d").to_string()),
+        _ if name.starts_with("existing_") => category::all(ws)
+            .into_iter()
+            .find(|category| existing_variable(category) == name)
+            .map(|category| {
+                fs::read_to_string(state::wm_path(ws, "distill").join(&category.filename))
+                    .unwrap_or_default()
+            }),
+        _ => None,
+    }
+}
+
+/// Replace `{{name}}` placeholders; unknown ones are left as written
+fn render(ws: &Workspace, template: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = after[..end].trim();
+        match lookup(name) {
+            Some(value) => output.push_str(value.trim_end()),
+            None => {
+                state::log(
+                    ws,
+                    "prompts",
+                    &format!("Unknown template variable '{}'", name),
+                );
+                output.push_str(&rest[start..start + 2 + end + 2]);
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Run wm prompts show [name] - list templates, or print one
+pub fn run_show(ws: &Workspace, name: Option<&str>, default: bool) -> Result<(), String> {
+    let Some(name) = name else {
+        for template in TEMPLATES {
+            let (_, source) = resolve(ws, template);
+            println!(
+                "{:<20} {:<48} {}",
+                template.name, template.description, source
+            );
+        }
+        println!("\nVariables:");
+        for (name, description) in variables(ws) {
+            println!("  {:<32} {}", format!("{{{{{}}}}}", name), description);
+        }
+        return Ok(());
+    };
+
+    let template = find(name)?;
+    if default {
+        print!("{}", template.default);
+    } else {
+        let (content, source) = resolve(ws, template);
+        eprintln!("# {} ({})", template.name, source);
+        print!("{}", content);
+    }
+    Ok(())
+}
+
+/// Run wm prompts eject [name] - copy built-in defaults out for editing
+pub fn run_eject(
+    ws: &Workspace,
+    name: Option<&str>,
+    global: bool,
+    force: bool,
+) -> Result<(), String> {
+    let templates: Vec<&Template> = match name {
+        Some(name) => vec![find(name)?],
+        None => TEMPLATES.iter().collect(),
+    };
+    if !global && !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first (or use --global).".to_string());
+    }
+
+    for template in templates {
+        let path = if global {
+            global_path(template.name)
+                .ok_or_else(|| "Could not determine user-global directory".to_string())?
+        } else {
+            project_path(ws, template.name)
+        };
+        if path.exists() && !force {
+            println!(
+                "Skipped {} (already exists; --force to overwrite)",
+                path.display()
+            );
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, template.default)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let lookup = |name: &str| match name {
+            "project_name" => Some("shop".to_string()),
+            "existing_metis" => Some("- Prefer pnpm\n".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            render(
+                ws,
+                "Project {{project_name}}:\n{{ existing_metis }}\n{{unknown}} {{",
+                &lookup
+            ),
+            "Project shop:\n- Prefer pnpm\n{{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn test_existing_variables_follow_categories() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = &Workspace::new(dir.path().to_path_buf());
+        let distill = state::wm_path(ws, "distill");
+        fs::create_dir_all(&distill).unwrap();
+        fs::write(distill.join("pitfalls.md"), "- Dates are UTC\n").unwrap();
+        fs::write(
+            state::wm_path(ws, "config.toml"),
+            "[categories.known-pitfalls]\nfilename = \"pitfalls.md\"\n",
+        )
+        .unwrap();
+
+        let names: Vec<String> = variables(ws).into_iter().map(|(name, _)| name).collect();
+        assert!(names.contains(&"existing_metis".to_string()));
+        assert!(names.contains(&"existing_known_pitfalls".to_string()));
+        assert_eq!(
+            variable(ws, "existing_known_pitfalls").as_deref(),
+            Some("- Dates are UTC\n")
+        );
+        assert_eq!(variable(ws, "existing_guardrails").as_deref(), Some(""));
+        assert_eq!(variable(ws, "existing_glossary"), None);
+    }
+
+    #[test]
+    fn test_builtin_templates_have_content() {
+        for template in TEMPLATES {
+            assert!(
+                !template.default.trim().is_empty(),
+                "{} is empty",
+                template.name
+            );
+            assert!(find(template.name).is_ok());
+        }
+        assert!(find("nope").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Path scopes for knowledge items
+//!
+//! An item can end with a scope tag naming the parts of the repository it
+//! applies to, e.g. `- Run migrations with sqlx [scope: backend/, crates/db/**]`.
+//! Scoped items are only injected when the session is working inside one of
+//! those paths; unscoped items always apply.
+//!
+//! AIDEV-NOTE: The tag lives in the item text itself (not a sidecar file) so it
+//! survives distill's regenerate-from-scratch writes and can be edited by hand.
+
+use crate::transcript::{read_transcript_tail, recent_file_paths};
+use crate::types::glob_match;
+use crate::workspace::Workspace;
+use std::path::{Path, PathBuf};
+
+/// Opening of the trailing scope tag
+const SCOPE_TAG: &str = "[scope:";
+
+/// How many recently touched files count towards the session's location
+const RECENT_FILES_LIMIT: usize = 20;
+
+/// How much of the end of the transcript is searched for recently touched files
+const TRANSCRIPT_TAIL_BYTES: u64 = 512 * 1024;
+
+/// Split an item into its text and path scopes (empty if unscoped)
+pub fn parse_scopes(item: &str) -> (&str, Vec<String>) {
+    let trimmed = item.trim_end();
+    let Some(body) = trimmed.strip_suffix(']') else {
+        return (item, Vec::new());
+    };
+    let Some(start) = body.rfind(SCOPE_TAG) else {
+        return (item, Vec::new());
+    };
+
+    let scopes: Vec<String> = body[start + SCOPE_TAG.len()..]
+        .split(',')
+        .map(normalize_scope)
+        .filter(|s| !s.is_empty())
+        .collect();
+    (body[..start].trim_end(), scopes)
+}
+
+/// Whether an item carries a scope tag
+pub fn is_scoped(item: &str) -> bool {
+    !parse_scopes(item).1.is_empty()
+}
+
+/// Normalize a scope to a path or glob without `./` or trailing slash
+/// Absolute paths are kept; ScopeContext makes them relative to its root.
+fn normalize_scope(scope: &str) -> String {
+    scope
+        .trim()
+        .trim_matches('`')
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Whether a project-relative path falls inside a scope
+/// Plain scopes match the path itself and anything beneath it; scopes with
+/// wildcards are matched as globs.
+pub fn scope_matches(scope: &str, path: &str) -> bool {
+    if scope.is_empty() || scope == "." || scope == "**" {
+        return true;
+    }
+    if scope.contains(['*', '?']) {
+        return glob_match(scope, path);
+    }
+    path == scope
+        || path
+            .strip_prefix(scope)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Where in the project a session is working
+#[derive(Debug, Clone, Default)]
+pub struct ScopeContext {
+    /// Project root, for scopes written as absolute paths
+    pub root: PathBuf,
+
+    /// Project-relative paths: the session cwd and recently touched files
+    pub paths: Vec<String>,
+}
+
+impl ScopeContext {
+    /// Build from hook input (`cwd` and `transcript_path` fields)
+    pub fn from_hook_input(ws: &Workspace, input: &serde_json::Value, session_id: &str) -> Self {
+        let field = |name: &str| input.get(name).and_then(|v| v.as_str());
+        let mut paths = Vec::new();
+
+        if let Some(cwd) = field("cwd") {
+            paths.extend(relative_to_project(ws, cwd));
+        }
+
+        if let Some(transcript) = field("transcript_path")
+            && let Ok(entries) = read_transcript_tail(Path::new(transcript), TRANSCRIPT_TAIL_BYTES)
+        {
+            paths.extend(
+                recent_file_paths(&entries, Some(session_id), RECENT_FILES_LIMIT)
+                    .iter()
+                    .filter_map(|p| relative_to_project(ws, p)),
+            );
+        }
+
+        ScopeContext {
+            root: ws.root().to_path_buf(),
+            paths,
+        }
+    }
+
+    /// Whether an item applies here (unscoped items always do)
+    pub fn includes(&self, item: &str) -> bool {
+        let (_, scopes) = parse_scopes(item);
+        scopes.is_empty()
+            || scopes.iter().any(|scope| {
+                let scope = self.relative_scope(scope);
+                self.paths.iter().any(|path| scope_matches(scope, path))
+            })
+    }
+
+    /// A scope relative to the project root (absolute scopes under it are stripped)
+    fn relative_scope<'a>(&self, scope: &'a str) -> &'a str {
+        Path::new(scope)
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|p| p.to_str())
+            .unwrap_or(scope)
+    }
+}
+
+/// Make a path relative to the project root (None if it lies outside)
+/// The project root itself becomes "", which only matches catch-all scopes.
+fn relative_to_project(ws: &Workspace, path: &str) -> Option<String> {
+    let path = Path::new(path);
+    if path.is_relative() {
+        return Some(path.to_string_lossy().trim_end_matches('/').to_string());
+    }
+    path.strip_prefix(ws.root())
+        .ok()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(
+            parse_scopes("Use sqlx macros [scope: backend/, crates/db/**]"),
+            (
+                "Use sqlx macros",
+                vec!["backend".to_string(), "crates/db/**".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_scopes("Never force push"),
+            ("Never force push", vec![])
+        );
+        assert_eq!(parse_scopes("See [docs]"), ("See [docs]", vec![]));
+    }
+
+    #[test]
+    fn test_scope_matches() {
+        assert!(scope_matches("backend", "backend"));
+        assert!(scope_matches("backend", "backend/src/main.rs"));
+        assert!(!scope_matches("backend", "backend-legacy/main.rs"));
+        assert!(scope_matches("crates/*/src/**", "crates/api/src/lib.rs"));
+        assert!(scope_matches("**", ""));
+    }
+
+    #[test]
+    fn test_context_includes() {
+        let context = ScopeContext {
+            root: PathBuf::from("/repo"),
+            paths: vec!["".to_string(), "frontend/app.ts".to_string()],
+        };
+        assert!(context.includes("Always applies"));
+        assert!(context.includes("Use pnpm [scope: /repo/frontend/]"));
+        assert!(context.includes("Use pnpm [scope: frontend/]"));
+        assert!(!context.includes("Use sqlx [scope: backend/]"));
+    }
+}
@@ -92,21 +92,11 @@ pub fn discover_sessions_in_dir(project_dir: &Path) -> Result<Vec<SessionInfo>,
         .collect();
 
     // Sort by modification time, newest first
-    sessions.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified_at));
 
     Ok(sessions)
 }
 
-/// Get the current project path
-/// Uses CLAUDE_PROJECT_DIR if set, otherwise current working directory
-pub fn current_project_path() -> PathBuf {
-    if let Ok(project_dir) = std::env::var("CLAUDE_PROJECT_DIR") {
-        PathBuf::from(project_dir)
-    } else {
-        std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
-    }
-}
-
 /// Information about a Claude project directory
 #[derive(Debug, Clone)]
 pub struct ProjectInfo {
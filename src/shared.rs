@@ -0,0 +1,354 @@
+//! Team-shareable knowledge in .wm/shared/
+//!
+//! Distill output, caches, hook logs and session dirs are per-user and stay
+//! gitignored. Curated guardrails, metis (and any other configured category)
+//! that the whole team should get are promoted (`wm share`) into .wm/shared/,
+//! which is meant to be committed.
+//!
+//! Shared files use a stable format so diffs and merges stay small: one item
+//! per line, each prefixed with a short ID, sorted by ID:
+//!
+//! ```markdown
+//! # Guardrails
+//!
+//! - [3f9a1c02] Never force push to main
+//! ```
+//!
+//! AIDEV-NOTE: IDs are derived from the item text when it is first shared and
+//! kept when the text is edited afterwards, so the merge driver can tell an
+//! edited item from a new one.
+
+use crate::category;
+use crate::compile;
+use crate::error::{Error, Result};
+use crate::lock;
+use crate::state;
+use crate::types::content_hash;
+use crate::workspace::Workspace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// Shared directory within .wm/
+pub const SHARED_DIR: &str = "shared";
+
+/// Length of generated item IDs (hex chars)
+const ID_LEN: usize = 8;
+
+/// Written into .wm/ by `wm init`: keep everything local except shared knowledge
+pub const GITIGNORE: &str = "\
+# wm: only shared knowledge, guardrail rules, ignore patterns and prompts are committed.
+# Caches, hook logs, sessions and config are per-user.
+*
+!.gitignore
+!.gitattributes
+!guardrails.toml
+!ignore
+!prompts/
+!prompts/*.md
+!shared/
+!shared/*.md
+";
+
+/// Written into .wm/ by `wm init`: merge shared files item-wise
+pub const GITATTRIBUTES: &str = "shared/*.md merge=wm\n";
+
+/// A single shared item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedItem {
+    pub id: String,
+    pub text: String,
+}
+
+impl SharedItem {
+    /// Create an item with an ID derived from its text
+    pub fn new(text: &str) -> Self {
+        let text = one_line(text);
+        SharedItem {
+            id: content_hash(&text)[..ID_LEN].to_string(),
+            text,
+        }
+    }
+
+    fn render(&self) -> String {
+        format!("- [{}] {}", self.id, self.text)
+    }
+}
+
+/// Path of a shared knowledge file
+pub fn shared_path(ws: &Workspace, filename: &str) -> PathBuf {
+    state::wm_path(ws, SHARED_DIR).join(filename)
+}
+
+/// Read items from a shared knowledge file (empty if missing)
+pub fn read_items(ws: &Workspace, filename: &str) -> Vec<SharedItem> {
+    parse(&std::fs::read_to_string(shared_path(ws, filename)).unwrap_or_default())
+}
+
+/// Parse shared file content; bullets without an ID get one derived from their text
+pub fn parse(content: &str) -> Vec<SharedItem> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            let item = trimmed
+                .strip_prefix("- ")
+                .or_else(|| trimmed.strip_prefix("* "))?
+                .trim();
+            if item.is_empty() {
+                return None;
+            }
+
+            Some(match split_id(item) {
+                Some((id, text)) => SharedItem {
+                    id: id.to_string(),
+                    text: one_line(text),
+                },
+                None => SharedItem::new(item),
+            })
+        })
+        .collect()
+}
+
+/// Split a leading `[id] ` off an item
+fn split_id(item: &str) -> Option<(&str, &str)> {
+    let rest = item.strip_prefix('[')?;
+    let (id, text) = rest.split_once("] ")?;
+    let is_id = id.len() >= 4 && id.chars().all(|c| c.is_ascii_hexdigit());
+    is_id.then_some((id, text.trim()))
+}
+
+/// Collapse whitespace so every item fits on one line
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Render items in the stable on-disk format (sorted by ID, one per line)
+pub fn render(title: &str, items: &[SharedItem]) -> String {
+    let mut sorted: Vec<&SharedItem> = items.iter().collect();
+    sorted.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.text.cmp(&b.text)));
+    sorted.dedup_by(|a, b| a.id == b.id && a.text == b.text);
+
+    let mut output = format!("# {}\n\n", title);
+    for item in sorted {
+        output.push_str(&item.render());
+        output.push('\n');
+    }
+    output
+}
+
+/// Title of a shareable file: one per category (None if the file can't be shared)
+pub fn title_for(ws: &Workspace, filename: &str) -> Option<String> {
+    category::for_filename(&category::all(ws), filename).map(|c| c.title.clone())
+}
+
+/// Add items to a shared file, skipping ones already present; returns how many were added
+pub fn add_items(ws: &Workspace, filename: &str, texts: &[String]) -> Result<usize> {
+    let title = title_for(ws, filename)
+        .ok_or_else(|| Error::NotFound(format!("Shared file '{}'", filename)))?;
+    let _lock = lock::acquire(&shared_path(ws, filename))
+        .map_err(|e| Error::io(format!("Failed to lock shared/{}", filename), e))?;
+    let mut items = read_items(ws, filename);
+    let mut seen: BTreeSet<String> = items
+        .iter()
+        .map(|i| compile::normalize_item(&i.text))
+        .collect();
+
+    let before = items.len();
+    for text in texts {
+        if text.trim().is_empty() || !seen.insert(compile::normalize_item(&one_line(text))) {
+            continue;
+        }
+        items.push(SharedItem::new(text));
+    }
+    let added = items.len() - before;
+
+    if added > 0 {
+        let dir = state::wm_path(ws, SHARED_DIR);
+        std::fs::create_dir_all(&dir).map_err(|e| Error::io("Failed to create .wm/shared/", e))?;
+        std::fs::write(shared_path(ws, filename), render(&title, &items))
+            .map_err(|e| Error::io(format!("Failed to write shared/{}", filename), e))?;
+    }
+
+    Ok(added)
+}
+
+/// Run wm share - promote locally distilled items into .wm/shared/
+pub fn run(ws: &Workspace, category: Option<&str>) -> std::result::Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+
+    let categories = category::all(ws);
+    let files: Vec<&str> = match category {
+        None => categories.iter().map(|c| c.filename.as_str()).collect(),
+        Some(name) => match category::find(&categories, name) {
+            Some(found) => vec![found.filename.as_str()],
+            None => {
+                return Err(format!(
+                    "Unknown category: {}. Use one of: {}.",
+                    name,
+                    category::names(&categories)
+                ));
+            }
+        },
+    };
+
+    for filename in files {
+        let local = std::fs::read_to_string(state::wm_path(ws, "distill").join(filename))
+            .unwrap_or_default();
+        let added = add_items(ws, filename, &compile::split_items(&local))?;
+        println!(
+            "Shared {} new item(s) to .wm/{}/{}",
+            added, SHARED_DIR, filename
+        );
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Merge driver
+// ============================================================================
+
+/// Result of an item-wise three-way merge
+#[derive(Debug, PartialEq, Eq)]
+pub struct MergeResult {
+    pub content: String,
+    pub conflicts: usize,
+}
+
+/// Three-way merge of shared files keyed by item ID
+///
+/// Additions from either side are kept, deletions apply unless the other side
+/// edited the item, and an item edited differently on both sides is emitted
+/// with conflict markers.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let title = [ours, theirs, base]
+        .iter()
+        .find_map(|content| {
+            content
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("# "))
+        })
+        .unwrap_or("Knowledge");
+
+    let index = |content: &str| -> BTreeMap<String, String> {
+        parse(content)
+            .into_iter()
+            .map(|item| (item.id, item.text))
+            .collect()
+    };
+    let (base, ours, theirs) = (index(base), index(ours), index(theirs));
+
+    let ids: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+    let mut lines = Vec::new();
+    let mut conflicts = 0;
+
+    for id in ids {
+        let item = |text: &String| {
+            SharedItem {
+                id: id.clone(),
+                text: text.clone(),
+            }
+            .render()
+        };
+        let original = base.get(id);
+
+        match (ours.get(id), theirs.get(id)) {
+            (Some(o), Some(t)) if o == t => lines.push(item(o)),
+            (Some(o), Some(t)) if Some(o) == original => lines.push(item(t)),
+            (Some(o), Some(t)) if Some(t) == original => lines.push(item(o)),
+            (Some(o), Some(t)) => {
+                conflicts += 1;
+                lines.push("<<<<<<< ours".to_string());
+                lines.push(item(o));
+                lines.push("=======".to_string());
+                lines.push(item(t));
+                lines.push(">>>>>>> theirs".to_string());
+            }
+            // Present on one side only: deleted by the other side if it was in
+            // base, unless this side changed it since
+            (Some(text), None) | (None, Some(text)) => {
+                if original != Some(text) {
+                    lines.push(item(text));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    let mut content = format!("# {}\n\n", title);
+    for line in lines {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    MergeResult { content, conflicts }
+}
+
+/// Run wm merge-driver %O %A %B - writes the merge result to `ours`
+/// Configure with: git config merge.wm.driver "wm merge-driver %O %A %B"
+pub fn run_merge_driver(base: &str, ours: &str, theirs: &str) -> std::result::Result<(), String> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+    };
+    let result = merge(&read(base)?, &read(ours)?, &read(theirs)?);
+
+    std::fs::write(ours, &result.content)
+        .map_err(|e| format!("Failed to write {}: {}", ours, e))?;
+
+    if result.conflicts > 0 {
+        return Err(format!(
+            "{} conflicting item edit(s) left with conflict markers",
+            result.conflicts
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render_are_stable() {
+        let content = "# Metis\n\n- [bbbb0000] Second\n- [aaaa0000] First\n- No id yet\n";
+        let items = parse(content);
+        assert_eq!(items[0].id, "bbbb0000");
+        assert_eq!(items[2].id.len(), ID_LEN);
+
+        let rendered = render("Metis", &items);
+        assert!(rendered.starts_with("# Metis\n\n"));
+        assert!(rendered.find("[aaaa0000]") < rendered.find("[bbbb0000]"));
+        assert_eq!(render("Metis", &parse(&rendered)), rendered);
+    }
+
+    #[test]
+    fn test_merge_combines_additions_and_deletions() {
+        let base = "# Metis\n\n- [aaaa0000] Keep\n- [bbbb0000] Remove\n";
+        let ours = "# Metis\n\n- [aaaa0000] Keep\n- [cccc0000] Ours\n";
+        let theirs =
+            "# Metis\n\n- [aaaa0000] Keep (edited)\n- [bbbb0000] Remove\n- [dddd0000] Theirs\n";
+
+        let result = merge(base, ours, theirs);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(
+            result.content,
+            "# Metis\n\n- [aaaa0000] Keep (edited)\n- [cccc0000] Ours\n- [dddd0000] Theirs\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicting_edits() {
+        let base = "# Metis\n\n- [aaaa0000] Original\n";
+        let ours = "# Metis\n\n- [aaaa0000] Ours\n";
+        let theirs = "# Metis\n\n- [aaaa0000] Theirs\n";
+
+        let result = merge(base, ours, theirs);
+        assert_eq!(result.conflicts, 1);
+        assert!(
+            result
+                .content
+                .contains("<<<<<<< ours\n- [aaaa0000] Ours\n=======\n")
+        );
+    }
+}
@@ -2,13 +2,14 @@
 
 use crate::session;
 use crate::state;
+use crate::workspace::Workspace;
 
 /// Run wm show <what> [--session-id ID]
-pub fn run(what: &str, session_id: Option<&str>) -> Result<(), String> {
+pub fn run(ws: &Workspace, what: &str, session_id: Option<&str>) -> Result<(), String> {
     match what {
-        "state" => show_state(),
-        "working" => show_working(session_id),
-        "sessions" => show_sessions(),
+        "state" => show_state(ws),
+        "working" => show_working(ws, session_id),
+        "sessions" => show_sessions(ws),
         _ => Err(format!(
             "Unknown target: {}. Use: state, working, sessions",
             what
@@ -16,12 +17,12 @@ pub fn run(what: &str, session_id: Option<&str>) -> Result<(), String> {
     }
 }
 
-fn show_state() -> Result<(), String> {
-    if !state::is_initialized() {
+fn show_state(ws: &Workspace) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
-    let path = state::wm_path("state.md");
+    let path = state::wm_path(ws, "state.md");
     match std::fs::read_to_string(&path) {
         Ok(content) if content.trim().is_empty() => {
             println!("_No knowledge captured yet. Run 'wm extract' after some conversations._");
@@ -39,15 +40,15 @@ fn show_state() -> Result<(), String> {
     }
 }
 
-fn show_working(session_id: Option<&str>) -> Result<(), String> {
-    if !state::is_initialized() {
+fn show_working(ws: &Workspace, session_id: Option<&str>) -> Result<(), String> {
+    if !state::is_initialized(ws) {
         return Err("Not initialized. Run 'wm init' first.".to_string());
     }
 
     let mut has_content = false;
 
     // Read dive context if present
-    let dive_path = state::wm_path("dive_context.md");
+    let dive_path = state::wm_path(ws, "dive_context.md");
     if let Ok(dive_content) = std::fs::read_to_string(&dive_path)
         && !dive_content.trim().is_empty()
     {
@@ -60,10 +61,10 @@ fn show_working(session_id: Option<&str>) -> Result<(), String> {
     // Read working set (compiled state)
     let working_content = match session_id {
         Some(id) => {
-            let path = state::session_dir(id).join("working_set.md");
+            let path = state::session_dir(ws, id).join("working_set.md");
             std::fs::read_to_string(&path)
         }
-        None => state::read_working_set(),
+        None => state::read_working_set(ws),
     };
 
     if let Ok(content) = working_content
@@ -87,8 +88,8 @@ fn show_working(session_id: Option<&str>) -> Result<(), String> {
     Ok(())
 }
 
-fn show_sessions() -> Result<(), String> {
-    let project_path = session::current_project_path();
+fn show_sessions(ws: &Workspace) -> Result<(), String> {
+    let project_path = ws.root().to_path_buf();
     let sessions = session::discover_sessions(&project_path)?;
 
     if sessions.is_empty() {
@@ -101,7 +102,7 @@ fn show_sessions() -> Result<(), String> {
 
     for s in &sessions {
         // Check if we have local state for this session
-        let has_local_state = state::session_dir(&s.session_id)
+        let has_local_state = state::session_dir(ws, &s.session_id)
             .join("extraction_state.json")
             .exists();
         let marker = if has_local_state { "●" } else { "○" };
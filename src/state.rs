@@ -1,19 +1,25 @@
 //! State management - file I/O helpers for .wm/
 
-use crate::types::Config;
-use chrono::Local;
+use crate::config;
+use crate::lock::{self, FileLock};
+use crate::types::{Config, Operation, SessionPauses};
+use crate::workspace::Workspace;
+use chrono::{Local, Utc};
+use std::collections::BTreeSet;
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 const WM_DIR: &str = ".wm";
 const WORKING_SET_FILE: &str = "working_set.md";
 const HOOK_LOG_FILE: &str = "hook.log";
-const CONFIG_FILE: &str = "config.toml";
+const INJECTED_FILE: &str = "injected.json";
+const PAUSED_FILE: &str = "paused.json";
 
 /// Log a message to .wm/hook.log
-pub fn log(context: &str, message: &str) {
-    let path = wm_path(HOOK_LOG_FILE);
+pub fn log(ws: &Workspace, context: &str, message: &str) {
+    let path = wm_path(ws, HOOK_LOG_FILE);
     let timestamp = Local::now().format("%H:%M:%S");
     let line = format!("[{}] [{}] {}\n", timestamp, context, message);
 
@@ -25,75 +31,239 @@ pub fn log(context: &str, message: &str) {
         .and_then(|mut f| f.write_all(line.as_bytes()));
 }
 
-/// Get the .wm directory path for the current project
-/// Uses CLAUDE_PROJECT_DIR if set (from hooks), otherwise falls back to cwd
-pub fn wm_dir() -> PathBuf {
-    if let Ok(project_dir) = std::env::var("CLAUDE_PROJECT_DIR") {
-        PathBuf::from(project_dir).join(WM_DIR)
-    } else {
-        PathBuf::from(WM_DIR)
+/// Environment variables that pin the project root, in priority order
+/// AIDEV-NOTE: WM_PROJECT_DIR is the user-facing override; CLAUDE_PROJECT_DIR is
+/// set by Claude Code for hooks.
+const PROJECT_DIR_VARS: &[&str] = &["WM_PROJECT_DIR", "CLAUDE_PROJECT_DIR"];
+
+/// Project root pinned explicitly: `flag` (--project-dir), then env vars
+pub fn explicit_project_dir(flag: Option<PathBuf>) -> Option<PathBuf> {
+    flag.or_else(|| {
+        PROJECT_DIR_VARS
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// Resolve the project root for a CLI invocation
+/// Uses the explicit root if pinned, otherwise the nearest ancestor of cwd that
+/// contains .wm/, otherwise cwd itself
+pub fn project_dir(explicit: Option<PathBuf>) -> PathBuf {
+    if let Some(dir) = explicit {
+        return dir;
     }
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    find_store_root(&cwd).unwrap_or(cwd)
 }
 
-/// Check if .wm/ exists in current directory
-pub fn is_initialized() -> bool {
-    wm_dir().exists()
+/// Find the nearest directory at or above `start` that contains .wm/
+/// AIDEV-NOTE: Stops at the git root (a directory containing .git) so a store
+/// in an unrelated parent directory is never picked up from inside a repo.
+pub fn find_store_root(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        if dir.join(WM_DIR).is_dir() {
+            return Some(dir.to_path_buf());
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Get the .wm directory path of a workspace
+pub fn wm_dir(ws: &Workspace) -> PathBuf {
+    ws.root().join(WM_DIR)
+}
+
+/// Check if .wm/ exists in a workspace
+pub fn is_initialized(ws: &Workspace) -> bool {
+    wm_dir(ws).exists()
 }
 
 /// Get path to a file within .wm/
-pub fn wm_path(filename: &str) -> PathBuf {
-    wm_dir().join(filename)
+pub fn wm_path(ws: &Workspace, filename: &str) -> PathBuf {
+    wm_dir(ws).join(filename)
+}
+
+/// Get the user-global store directory (~/.config/wm/)
+/// Overridable with WM_GLOBAL_DIR. Holds knowledge that applies across projects,
+/// laid out like .wm/ (e.g. distill/guardrails.md).
+pub fn global_dir() -> Option<PathBuf> {
+    match std::env::var("WM_GLOBAL_DIR") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::home_dir().map(|home| home.join(".config").join("wm")),
+    }
+}
+
+/// Get path to a file within the user-global store
+pub fn global_path(filename: &str) -> Option<PathBuf> {
+    global_dir().map(|dir| dir.join(filename))
 }
 
 /// Read the last compiled working set (legacy global path)
-pub fn read_working_set() -> io::Result<String> {
-    fs::read_to_string(wm_path(WORKING_SET_FILE))
+pub fn read_working_set(ws: &Workspace) -> io::Result<String> {
+    fs::read_to_string(wm_path(ws, WORKING_SET_FILE))
 }
 
 /// Write the compiled working set (legacy global path)
-pub fn write_working_set(content: &str) -> io::Result<()> {
-    fs::write(wm_path(WORKING_SET_FILE), content)
+pub fn write_working_set(ws: &Workspace, content: &str) -> io::Result<()> {
+    fs::write(wm_path(ws, WORKING_SET_FILE), content)
 }
 
 /// Get session-specific directory path
-pub fn session_dir(session_id: &str) -> PathBuf {
-    wm_path(&format!("sessions/{}", session_id))
+pub fn session_dir(ws: &Workspace, session_id: &str) -> PathBuf {
+    wm_path(ws, &format!("sessions/{}", session_id))
 }
 
 /// Write working set to session-specific path
 /// AIDEV-NOTE: Per-session working_set prevents race conditions when
 /// multiple sessions compile concurrently in the same project folder.
-pub fn write_working_set_for_session(session_id: &str, content: &str) -> io::Result<()> {
-    let dir = session_dir(session_id);
+pub fn write_working_set_for_session(
+    ws: &Workspace,
+    session_id: &str,
+    content: &str,
+) -> io::Result<()> {
+    let dir = session_dir(ws, session_id);
     fs::create_dir_all(&dir)?;
     fs::write(dir.join(WORKING_SET_FILE), content)
 }
 
-/// Read project-level config, returns default if not found
-pub fn read_config() -> Config {
-    let path = wm_path(CONFIG_FILE);
-    match fs::read_to_string(&path) {
-        Ok(content) => toml::from_str(&content).unwrap_or_default(),
-        Err(_) => Config::default(),
-    }
+/// Read the set of item hashes already injected into a session
+/// Returns an empty set if nothing has been recorded yet
+pub fn read_injected_items(ws: &Workspace, session_id: &str) -> BTreeSet<String> {
+    fs::read_to_string(session_dir(ws, session_id).join(INJECTED_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
 }
 
-/// Write project-level config
-pub fn write_config(config: &Config) -> io::Result<()> {
-    let path = wm_path(CONFIG_FILE);
-    let content = toml::to_string_pretty(config)
+/// Record the set of item hashes injected into a session
+pub fn write_injected_items(
+    ws: &Workspace,
+    session_id: &str,
+    items: &BTreeSet<String>,
+) -> io::Result<()> {
+    let dir = session_dir(ws, session_id);
+    fs::create_dir_all(&dir)?;
+    let content = serde_json::to_string_pretty(items)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    fs::write(path, content)
+    write_atomic(&dir.join(INJECTED_FILE), &content)
 }
 
-/// Check if extract operation is enabled
-pub fn is_extract_enabled() -> bool {
-    read_config().operations.extract
+/// Lock a session's injected record for read-modify-write
+pub fn lock_injected_items(ws: &Workspace, session_id: &str) -> io::Result<FileLock> {
+    lock::acquire(&session_dir(ws, session_id).join(INJECTED_FILE))
+}
+
+/// Write a file via temp file + rename so readers never see a partial write
+/// AIDEV-NOTE: The temp name is unique per process and call, so concurrent
+/// writers (hooks, MCP, distill) never write into or rename each other's file.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = fs::write(&tmp_path, content).and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
 }
 
-/// Check if compile operation is enabled
-pub fn is_compile_enabled() -> bool {
-    read_config().operations.compile
+/// Read the effective config (defaults, global, project, env; see config.rs)
+/// AIDEV-NOTE: Fails safe - if a config file or WM_* override doesn't parse,
+/// extract is treated as paused (the unreadable setting may be what paused it)
+/// rather than silently resuming with defaults. Use parse_config to surface the error.
+pub fn read_config(ws: &Workspace) -> Config {
+    parse_config(ws).unwrap_or_else(|_| fail_safe_config())
+}
+
+/// Defaults used while config is invalid
+fn fail_safe_config() -> Config {
+    let mut config = Config::default();
+    config.operations.extract = false;
+    config
+}
+
+/// Read the effective config, reporting parse errors
+pub fn parse_config(ws: &Workspace) -> Result<Config, String> {
+    config::load(ws)
+}
+
+/// Problems with config files and WM_* overrides (parse errors, unknown keys)
+pub fn config_problems(ws: &Workspace) -> Vec<String> {
+    config::problems(ws)
+}
+
+/// Read-modify-write project-level config under its lock
+/// Only changed keys are written. Refuses to touch a config.toml that doesn't
+/// parse, so it is never clobbered.
+pub fn update_config(ws: &Workspace, change: impl FnOnce(&mut Config)) -> io::Result<Config> {
+    config::update_project(ws, change).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Check if extract is enabled for a session (false while config is invalid)
+pub fn is_extract_enabled(ws: &Workspace, session_id: Option<&str>) -> bool {
+    if let Err(e) = parse_config(ws) {
+        log(ws, "config", &format!("{} - treating extract as paused", e));
+        return false;
+    }
+    is_enabled(ws, Operation::Extract, session_id)
+}
+
+/// Check if compile is enabled for a session
+pub fn is_compile_enabled(ws: &Workspace, session_id: Option<&str>) -> bool {
+    is_enabled(ws, Operation::Compile, session_id)
+}
+
+/// An operation runs unless paused project-wide or for this session
+/// AIDEV-NOTE: Timed pauses are only compared against the clock; nothing rewrites
+/// config when one expires. The stale entry is harmless and the next pause or
+/// resume replaces it.
+fn is_enabled(ws: &Workspace, op: Operation, session_id: Option<&str>) -> bool {
+    let now = Utc::now();
+    read_config(ws).operations.pause(op, now).is_none()
+        && session_id.is_none_or(|id| read_session_pauses(ws, id).pause(op, now).is_none())
+}
+
+/// Read a session's pauses (empty if none)
+pub fn read_session_pauses(ws: &Workspace, session_id: &str) -> SessionPauses {
+    fs::read_to_string(session_dir(ws, session_id).join(PAUSED_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Read-modify-write a session's pauses under their lock
+pub fn update_session_pauses(
+    ws: &Workspace,
+    session_id: &str,
+    change: impl FnOnce(&mut SessionPauses),
+) -> io::Result<SessionPauses> {
+    let path = session_dir(ws, session_id).join(PAUSED_FILE);
+    let _lock = lock::acquire(&path)?;
+    let mut pauses = read_session_pauses(ws, session_id);
+    change(&mut pauses);
+
+    if pauses.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+    } else {
+        let content = serde_json::to_string_pretty(&pauses)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_atomic(&path, &content)?;
+    }
+    Ok(pauses)
 }
 
 // ============================================================================
@@ -103,18 +273,18 @@ pub fn is_compile_enabled() -> bool {
 const DIVES_DIR: &str = "dives";
 
 /// Get the dives directory path (.wm/dives/)
-pub fn dive_dir() -> PathBuf {
-    wm_path(DIVES_DIR)
+pub fn dive_dir(ws: &Workspace) -> PathBuf {
+    wm_path(ws, DIVES_DIR)
 }
 
 /// Get path to a named dive prep (.wm/dives/{name}.md)
-pub fn dive_prep_path(name: &str) -> PathBuf {
-    dive_dir().join(format!("{}.md", name))
+pub fn dive_prep_path(ws: &Workspace, name: &str) -> PathBuf {
+    dive_dir(ws).join(format!("{}.md", name))
 }
 
 /// List all named dive preps (returns names without .md extension)
-pub fn list_dive_preps() -> io::Result<Vec<String>> {
-    let dir = dive_dir();
+pub fn list_dive_preps(ws: &Workspace) -> io::Result<Vec<String>> {
+    let dir = dive_dir(ws);
     if !dir.exists() {
         return Ok(Vec::new());
     }
@@ -134,18 +304,75 @@ pub fn list_dive_preps() -> io::Result<Vec<String>> {
 }
 
 /// Get the currently active dive prep name (None = use legacy fallback)
-pub fn current_dive() -> Option<String> {
-    read_config().dive.current
+pub fn current_dive(ws: &Workspace) -> Option<String> {
+    read_config(ws).dive.current
 }
 
 /// Set the current dive prep (None to clear)
-pub fn set_current_dive(name: Option<&str>) -> io::Result<()> {
-    let mut config = read_config();
-    config.dive.current = name.map(String::from);
-    write_config(&config)
+pub fn set_current_dive(ws: &Workspace, name: Option<&str>) -> io::Result<()> {
+    update_config(ws, |config| config.dive.current = name.map(String::from)).map(|_| ())
 }
 
 /// Ensure the dives directory exists
-pub fn ensure_dive_dir() -> io::Result<()> {
-    fs::create_dir_all(dive_dir())
+pub fn ensure_dive_dir(ws: &Workspace) -> io::Result<()> {
+    fs::create_dir_all(dive_dir(ws))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_store_root_walks_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b/c");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(dir.path().join("a").join(WM_DIR)).unwrap();
+
+        assert_eq!(find_store_root(&nested), Some(dir.path().join("a")));
+        assert_eq!(find_store_root(dir.path()), None);
+    }
+
+    #[test]
+    fn test_find_store_root_stops_at_git_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(repo.join(".git")).unwrap();
+        fs::create_dir(dir.path().join(WM_DIR)).unwrap();
+
+        assert_eq!(find_store_root(&nested), None);
+
+        fs::create_dir(repo.join(WM_DIR)).unwrap();
+        assert_eq!(find_store_root(&nested), Some(repo));
+    }
+
+    #[test]
+    fn test_write_atomic_concurrent_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let contents: Vec<String> = (0..8)
+            .map(|i| format!("writer = {}\n", i).repeat(500))
+            .collect();
+
+        std::thread::scope(|scope| {
+            for content in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        write_atomic(path, content).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(contents.contains(&fs::read_to_string(&path).unwrap()));
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name != "config.toml")
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
 }
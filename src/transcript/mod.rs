@@ -1,4 +1,7 @@
 pub mod reader;
 pub mod types;
 
-pub use reader::{format_context, get_messages_in_window, get_messages_since, read_transcript};
+pub use reader::{
+    format_context, get_messages_in_window, get_messages_since, ignored_tool_ids, read_transcript,
+    read_transcript_tail, recent_file_paths, session_facts,
+};
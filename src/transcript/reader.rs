@@ -1,17 +1,43 @@
 use chrono::{DateTime, Utc};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::Path;
 
-use crate::transcript::types::TranscriptEntry;
-use crate::types::{strip_xml_tags, ReadError};
+use crate::ignore::{IgnoreRules, SessionFacts};
+use crate::transcript::types::{TranscriptEntry, UserContent};
+use crate::types::{ReadError, strip_xml_tags};
+use std::collections::HashSet;
 
 /// Read and parse a transcript JSONL file
 ///
 /// Skips malformed lines rather than failing entirely
 pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptEntry>, ReadError> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    parse_lines(BufReader::new(File::open(path)?), "transcript")
+}
+
+/// Read and parse only the last `max_bytes` of a transcript JSONL file
+/// AIDEV-NOTE: For per-prompt hooks that only care about recent activity -
+/// transcripts grow without bound, and parsing the whole file on every prompt
+/// gets slow. The (probably partial) line the cut lands in is dropped.
+pub fn read_transcript_tail(
+    path: &Path,
+    max_bytes: u64,
+) -> Result<Vec<TranscriptEntry>, ReadError> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len <= max_bytes {
+        return parse_lines(BufReader::new(file), "transcript");
+    }
+
+    file.seek(SeekFrom::Start(len - max_bytes))?;
+    let mut reader = BufReader::new(file);
+    reader.read_until(b'\n', &mut Vec::new())?;
+    parse_lines(reader, "transcript tail")
+}
+
+/// Parse JSONL transcript lines, warning about (and skipping) malformed ones
+/// `what` names the input in warnings, as line numbers count from its start.
+fn parse_lines(reader: impl BufRead, what: &str) -> Result<Vec<TranscriptEntry>, ReadError> {
     let mut entries = Vec::new();
 
     for (line_num, line_result) in reader.lines().enumerate() {
@@ -25,8 +51,9 @@ pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptEntry>, ReadError> {
             Err(e) => {
                 // Log warning but continue - don't fail on malformed lines
                 eprintln!(
-                    "Warning: skipping malformed line {} in transcript: {}",
+                    "Warning: skipping malformed line {} in {}: {}",
                     line_num + 1,
+                    what,
                     e
                 );
             }
@@ -144,8 +171,103 @@ fn tool_summary(name: &str, input: Option<&serde_json::Value>) -> String {
     }
 }
 
+/// Files touched by tool uses, most recent first (deduplicated)
+/// AIDEV-NOTE: Used by compile to decide which path-scoped items apply. Only
+/// file-addressed tools count; Bash commands and search patterns are too vague.
+pub fn recent_file_paths(
+    entries: &[TranscriptEntry],
+    session_id: Option<&str>,
+    limit: usize,
+) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+
+    for entry in entries.iter().rev() {
+        if session_id.is_some() && entry.session_id() != session_id {
+            continue;
+        }
+        for (name, input) in entry.tool_uses().into_iter().rev() {
+            if !matches!(
+                name,
+                "Edit" | "Write" | "Read" | "MultiEdit" | "NotebookEdit"
+            ) {
+                continue;
+            }
+            let Some(path) = input
+                .and_then(|i| i.get("file_path").or_else(|| i.get("notebook_path")))
+                .and_then(|v| v.as_str())
+            else {
+                continue;
+            };
+            if !paths.iter().any(|p| p == path) {
+                paths.push(path.to_string());
+            }
+            if paths.len() >= limit {
+                return paths;
+            }
+        }
+    }
+
+    paths
+}
+
+/// IDs of tool uses that touch an ignored path or run an ignored command
+/// AIDEV-NOTE: Computed over the whole transcript, not the extraction window,
+/// so a result is still dropped when its tool use fell before the cutoff.
+pub fn ignored_tool_ids(entries: &[TranscriptEntry], rules: &IgnoreRules) -> HashSet<String> {
+    entries
+        .iter()
+        .flat_map(|entry| entry.tool_use_blocks())
+        .filter(|block| {
+            let field = |key: &str| {
+                block
+                    .input
+                    .as_ref()
+                    .and_then(|input| input.get(key))
+                    .and_then(|v| v.as_str())
+            };
+            match block.name.as_deref() {
+                Some("Bash") => field("command").is_some_and(|c| rules.ignores_command(c)),
+                _ => ["file_path", "notebook_path", "path"]
+                    .into_iter()
+                    .filter_map(field)
+                    .any(|path| rules.ignores_path(path)),
+            }
+        })
+        .filter_map(|block| block.id.clone())
+        .collect()
+}
+
+/// Session ID, start time and first user message, for session-level ignore rules
+pub fn session_facts<'a>(entries: &'a [TranscriptEntry], session_id: &'a str) -> SessionFacts<'a> {
+    let mut session = entries
+        .iter()
+        .filter(|e| e.session_id() == Some(session_id));
+    let started = session
+        .clone()
+        .find_map(|e| e.timestamp())
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&Utc));
+    let first_user_message = session.find_map(|e| match e {
+        TranscriptEntry::User { message, .. } => match &message.content {
+            UserContent::Text(text) => Some(text.as_str()),
+            UserContent::Blocks(blocks) => blocks
+                .iter()
+                .find(|b| b.block_type == "text")
+                .and_then(|b| b.text.as_deref()),
+        },
+        _ => None,
+    });
+
+    SessionFacts {
+        id: session_id,
+        started,
+        first_user_message,
+    }
+}
+
 /// Format messages for context (for sending to extraction LLM)
-pub fn format_context(messages: &[&TranscriptEntry]) -> String {
+/// Results of tool uses in `ignored` are dropped and their inputs hidden.
+pub fn format_context(messages: &[&TranscriptEntry], ignored: &HashSet<String>) -> String {
     let mut output = String::new();
 
     for entry in messages {
@@ -161,7 +283,10 @@ pub fn format_context(messages: &[&TranscriptEntry]) -> String {
                 // Include tool results (what Claude read/executed)
                 let tool_results = entry.tool_results();
                 if !tool_results.is_empty() {
-                    for (_id, content) in &tool_results {
+                    for (_id, content) in tool_results
+                        .iter()
+                        .filter(|(id, _)| id.is_none_or(|id| !ignored.contains(id)))
+                    {
                         output.push_str("TOOL_RESULT: ");
                         output.push_str(content);
                         output.push_str("\n\n");
@@ -178,7 +303,7 @@ pub fn format_context(messages: &[&TranscriptEntry]) -> String {
                 }
             }
             TranscriptEntry::Assistant { .. } => {
-                let tool_uses = entry.tool_uses();
+                let tool_uses = entry.tool_use_blocks();
 
                 // Include thinking if present (shows Claude's reasoning)
                 if let Some(thinking) = entry.assistant_thinking() {
@@ -189,9 +314,14 @@ pub fn format_context(messages: &[&TranscriptEntry]) -> String {
 
                 if !tool_uses.is_empty() {
                     output.push_str("TOOLS: ");
-                    for (name, input) in &tool_uses {
+                    for block in &tool_uses {
+                        let name = block.name.as_deref().unwrap_or_default();
                         output.push_str(name);
-                        let summary = tool_summary(name, *input);
+                        let summary = if block.id.as_ref().is_some_and(|id| ignored.contains(id)) {
+                            "[ignored]".to_string()
+                        } else {
+                            tool_summary(name, block.input.as_ref())
+                        };
                         if !summary.is_empty() {
                             output.push('(');
                             output.push_str(&summary);
@@ -287,4 +417,81 @@ mod tests {
         let result = get_messages_since(&entries, None, None);
         assert_eq!(result.len(), 2);
     }
+
+    #[test]
+    fn test_read_transcript_tail_drops_cut_line() {
+        let line = |uuid: &str| {
+            format!(
+                r#"{{"type":"assistant","uuid":"{}","sessionId":"s1","timestamp":"2025-01-15T10:00:00Z","message":{{"role":"assistant","content":[{{"type":"text","text":"hi"}}]}}}}"#,
+                uuid
+            )
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let content = format!("{}\n{}\n{}\n", line("a"), line("b"), line("c"));
+        std::fs::write(&path, &content).unwrap();
+
+        let uuids = |entries: Vec<TranscriptEntry>| -> Vec<String> {
+            entries
+                .iter()
+                .map(|e| {
+                    serde_json::to_value(e).unwrap()["uuid"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()
+        };
+        // The cut lands inside "b", which is dropped rather than misparsed
+        let tail = (line("c").len() + 10) as u64;
+        assert_eq!(uuids(read_transcript_tail(&path, tail).unwrap()), vec!["c"]);
+        assert_eq!(
+            uuids(read_transcript_tail(&path, content.len() as u64).unwrap()),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_recent_file_paths() {
+        let lines = [
+            r#"{"type":"assistant","uuid":"a","sessionId":"s1","timestamp":"2025-01-15T10:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","name":"Read","input":{"file_path":"/p/a.rs"}},{"type":"tool_use","name":"Bash","input":{"command":"ls"}}]}}"#,
+            r#"{"type":"assistant","uuid":"b","sessionId":"s2","timestamp":"2025-01-15T10:00:01Z","message":{"role":"assistant","content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/p/other.rs"}}]}}"#,
+            r#"{"type":"assistant","uuid":"c","sessionId":"s1","timestamp":"2025-01-15T10:00:02Z","message":{"role":"assistant","content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/p/b.rs"}},{"type":"tool_use","name":"Edit","input":{"file_path":"/p/a.rs"}}]}}"#,
+        ];
+        let entries: Vec<TranscriptEntry> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(
+            recent_file_paths(&entries, Some("s1"), 10),
+            vec!["/p/a.rs", "/p/b.rs"]
+        );
+        assert_eq!(recent_file_paths(&entries, None, 1), vec!["/p/a.rs"]);
+    }
+
+    #[test]
+    fn test_format_context_drops_ignored_tools() {
+        let lines = [
+            r#"{"type":"assistant","uuid":"a","sessionId":"s1","timestamp":"2025-01-15T10:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"/repo/secrets/key.txt"}},{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"/repo/src/main.rs"}}]}}"#,
+            r#"{"type":"user","uuid":"b","sessionId":"s1","timestamp":"2025-01-15T10:00:01Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"TOPSECRET"},{"type":"tool_result","tool_use_id":"t2","content":"fn main() {}"}]}}"#,
+        ];
+        let entries: Vec<TranscriptEntry> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let rules = IgnoreRules::new(
+            std::path::PathBuf::from("/repo"),
+            "secrets/\n",
+            &Default::default(),
+        );
+
+        let ignored = ignored_tool_ids(&entries, &rules);
+        let refs: Vec<&TranscriptEntry> = entries.iter().collect();
+        let formatted = format_context(&refs, &ignored);
+        assert!(!formatted.contains("TOPSECRET"));
+        assert!(!formatted.contains("key.txt"));
+        assert!(formatted.contains("Read([ignored])"));
+        assert!(formatted.contains("fn main() {}"));
+    }
 }
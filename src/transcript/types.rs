@@ -85,6 +85,7 @@ pub struct AssistantContentBlock {
     pub text: Option<String>,
     pub thinking: Option<String>,
     // Tool use fields
+    pub id: Option<String>,
     pub name: Option<String>,
     pub input: Option<serde_json::Value>,
 }
@@ -221,12 +222,19 @@ impl TranscriptEntry {
 
     /// Extract tool uses from assistant message as (name, input_json)
     pub fn tool_uses(&self) -> Vec<(&str, Option<&serde_json::Value>)> {
+        self.tool_use_blocks()
+            .into_iter()
+            .filter_map(|b| Some((b.name.as_deref()?, b.input.as_ref())))
+            .collect()
+    }
+
+    /// Tool use blocks from assistant message (with their IDs)
+    pub fn tool_use_blocks(&self) -> Vec<&AssistantContentBlock> {
         match self {
             TranscriptEntry::Assistant { message, .. } => message
                 .content
                 .iter()
-                .filter(|b| b.block_type == "tool_use")
-                .filter_map(|b| Some((b.name.as_deref()?, b.input.as_ref())))
+                .filter(|b| b.block_type == "tool_use" && b.name.is_some())
                 .collect(),
             _ => Vec::new(),
         }
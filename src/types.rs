@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
@@ -42,6 +43,86 @@ pub fn strip_xml_tags(text: &str, open_tag: &str, close_tag: &str) -> String {
     result.trim().to_string()
 }
 
+/// Stable content hash (FNV-1a, 64-bit) rendered as 16 hex chars
+///
+/// Used to identify knowledge items across runs. Unlike `DefaultHasher`, the
+/// output is guaranteed stable across Rust versions, so it is safe to persist.
+pub fn content_hash(text: &str) -> String {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let hash = text.trim().bytes().fold(FNV_OFFSET, |acc, b| {
+        (acc ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Match a path against a glob pattern
+///
+/// Supports `*` (any run of characters except `/`), `**` (any run including `/`)
+/// and `?` (one character except `/`). A trailing `/**` also matches the
+/// directory itself. Paths are compared with forward slashes.
+/// AIDEV-NOTE: Memoized on (pattern position, path position), so patterns with
+/// several `**` stay polynomial. The PreToolUse guard runs this on every tool call.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    struct Matcher<'a> {
+        p: &'a [u8],
+        s: &'a [u8],
+        memo: Vec<Option<bool>>,
+    }
+
+    impl Matcher<'_> {
+        fn matches(&mut self, pi: usize, si: usize) -> bool {
+            let slot = pi * (self.s.len() + 1) + si;
+            if let Some(known) = self.memo[slot] {
+                return known;
+            }
+            let (p, s) = (self.p, self.s);
+            let result = match p.get(pi) {
+                None => si == s.len(),
+                Some(b'*') if p.get(pi + 1) == Some(&b'*') => {
+                    // `**/` may match zero directories
+                    let rest = pi + 2;
+                    let rest_no_slash = if p.get(rest) == Some(&b'/') {
+                        rest + 1
+                    } else {
+                        rest
+                    };
+                    (si..=s.len()).any(|i| self.matches(rest, i) || self.matches(rest_no_slash, i))
+                }
+                Some(b'*') => {
+                    let end = s[si..]
+                        .iter()
+                        .position(|&c| c == b'/')
+                        .map_or(s.len(), |offset| si + offset);
+                    (si..=end).any(|i| self.matches(pi + 1, i))
+                }
+                Some(b'?') => s.get(si).is_some_and(|&c| c != b'/') && self.matches(pi + 1, si + 1),
+                Some(&c) => s.get(si) == Some(&c) && self.matches(pi + 1, si + 1),
+            };
+            self.memo[slot] = Some(result);
+            result
+        }
+    }
+
+    fn matches(p: &[u8], s: &[u8]) -> bool {
+        let mut matcher = Matcher {
+            p,
+            s,
+            memo: vec![None; (p.len() + 1) * (s.len() + 1)],
+        };
+        matcher.matches(0, 0)
+    }
+
+    let path = path.replace('\\', "/");
+    let pattern = pattern.trim_start_matches("./");
+    let path = path.trim_start_matches("./");
+    matches(pattern.as_bytes(), path.as_bytes())
+        || pattern
+            .strip_suffix("/**")
+            .is_some_and(|dir| matches(dir.as_bytes(), path.as_bytes()))
+}
+
 // =============================================================================
 // Shared Error Type
 // =============================================================================
@@ -83,6 +164,9 @@ pub trait SessionLike {
     fn session_id(&self) -> &str;
     fn size_bytes(&self) -> u64;
 
+    /// When the session was last active (transcript modification time)
+    fn modified_at(&self) -> DateTime<Utc>;
+
     /// Format session info for display (used in dry-run output)
     fn display_info(&self) -> String;
 }
@@ -103,6 +187,9 @@ impl SessionLike for SessionInfo {
     fn size_bytes(&self) -> u64 {
         self.size_bytes
     }
+    fn modified_at(&self) -> DateTime<Utc> {
+        self.modified_at
+    }
     fn display_info(&self) -> String {
         let size_kb = self.size_bytes / 1024;
         format!(
@@ -131,6 +218,9 @@ impl SessionLike for CodexSessionInfo {
     fn size_bytes(&self) -> u64 {
         self.size_bytes
     }
+    fn modified_at(&self) -> DateTime<Utc> {
+        self.modified_at
+    }
     fn display_info(&self) -> String {
         let size_kb = self.size_bytes / 1024;
         let cwd_display = self
@@ -148,13 +238,19 @@ impl SessionLike for CodexSessionInfo {
     }
 }
 
-/// Hook-specific output for UserPromptSubmit hooks
+/// Hook-specific output for SessionStart, UserPromptSubmit and PreToolUse hooks
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HookSpecificOutput {
     pub hook_event_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub additional_context: Option<String>,
+    /// PreToolUse only: "allow", "deny" or "ask"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision: Option<String>,
+    /// PreToolUse only: explanation shown alongside the decision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision_reason: Option<String>,
 }
 
 /// Hook response format (matches Claude Code expectations)
@@ -171,11 +267,281 @@ pub struct HookResponse {
 /// Stored in .wm/config.toml
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
+    /// .wm/ layout version (0 = predates versioning; see migrate.rs)
+    #[serde(default)]
+    pub version: u32,
+
     #[serde(default)]
     pub operations: OperationsConfig,
 
     #[serde(default)]
     pub dive: DiveConfig,
+
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    #[serde(default)]
+    pub ignore: IgnoreConfig,
+
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    #[serde(default)]
+    pub llm: LlmConfig,
+
+    #[serde(default)]
+    pub categories: CategoriesConfig,
+
+    #[serde(default)]
+    pub confidence: ConfidenceConfig,
+
+    #[serde(default)]
+    pub lint: LintConfig,
+}
+
+/// Confidence scoring of distilled items (see confidence.rs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceConfig {
+    /// Days after which an unreinforced item's confidence halves (0 = no decay)
+    #[serde(default = "default_confidence_half_life_days")]
+    pub half_life_days: u32,
+
+    /// Items scoring below this are left out of the working set
+    #[serde(default = "default_confidence_inject_threshold")]
+    pub inject_threshold: f64,
+
+    /// Items scoring below this are listed by `wm stale`
+    #[serde(default = "default_confidence_stale_threshold")]
+    pub stale_threshold: f64,
+}
+
+fn default_confidence_half_life_days() -> u32 {
+    90
+}
+
+fn default_confidence_inject_threshold() -> f64 {
+    0.2
+}
+
+fn default_confidence_stale_threshold() -> f64 {
+    0.3
+}
+
+impl Default for ConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            half_life_days: default_confidence_half_life_days(),
+            inject_threshold: default_confidence_inject_threshold(),
+            stale_threshold: default_confidence_stale_threshold(),
+        }
+    }
+}
+
+/// Contradiction checks across knowledge files (see lint.rs)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// Check for conflicting items after each distill Pass 2
+    #[serde(default)]
+    pub after_distill: bool,
+}
+
+/// Knowledge categories distill sorts insights into, keyed by name
+/// (`[categories.<name>]`; see category.rs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CategoriesConfig(pub BTreeMap<String, CategoryConfig>);
+
+impl Default for CategoriesConfig {
+    fn default() -> Self {
+        let category = |description: &str, priority: u32, filename: &str, decay| CategoryConfig {
+            description: description.to_string(),
+            priority,
+            filename: filename.to_string(),
+            always: true,
+            decay,
+        };
+        Self(BTreeMap::from([
+            (
+                "guardrails".to_string(),
+                category(
+                    "Hard constraints that must NEVER be violated: prohibitions (\"Never do X\", \"Always do Y before Z\"), safety rules against data loss, security issues or broken builds, and non-negotiable project requirements. E.g. \"Never commit .env files\", \"Always run tests before pushing\", \"Never delete migrations\".",
+                    10,
+                    "guardrails.md",
+                    false,
+                ),
+            ),
+            (
+                "metis".to_string(),
+                category(
+                    "Wisdom and patterns about HOW to work effectively: how the user likes things done, approaches that work well in this codebase, why things are the way they are, and soft guidance that may have exceptions. E.g. \"Prefer functional approaches\", \"User likes concise commit messages\", \"Check existing patterns first\".",
+                    20,
+                    "metis.md",
+                    true,
+                ),
+            ),
+        ]))
+    }
+}
+
+/// One knowledge category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryConfig {
+    /// What belongs in this category, as told to the LLM in Pass 2
+    #[serde(default)]
+    pub description: String,
+
+    /// Injection order: lower numbers come first in the working set
+    #[serde(default = "default_category_priority")]
+    pub priority: u32,
+
+    /// Output file in .wm/distill/ ("" = <name>.md)
+    #[serde(default)]
+    pub filename: String,
+
+    /// Inject into every session (false = distilled and searchable, never injected)
+    #[serde(default = "default_true")]
+    pub always: bool,
+
+    /// Lose confidence over time unless reinforced (guardrails don't by default)
+    #[serde(default = "default_true")]
+    pub decay: bool,
+}
+
+fn default_category_priority() -> u32 {
+    50
+}
+
+/// Sessions and commands never mined for knowledge (paths live in .wm/ignore)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoreConfig {
+    /// Session IDs (globs, e.g. "4f1c*") to skip
+    #[serde(default)]
+    pub sessions: Vec<String>,
+
+    /// Local dates ("2026-03-10") or ranges ("2026-03-01..2026-03-05") to skip
+    #[serde(default)]
+    pub dates: Vec<String>,
+
+    /// Skip sessions whose first user message contains one of these
+    #[serde(default = "default_ignore_markers")]
+    pub markers: Vec<String>,
+
+    /// Shell commands (globs, `*` matches anything) whose output is dropped
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+fn default_ignore_markers() -> Vec<String> {
+    vec!["#wm:private".to_string()]
+}
+
+impl Default for IgnoreConfig {
+    fn default() -> Self {
+        Self {
+            sessions: Vec::new(),
+            dates: Vec::new(),
+            markers: default_ignore_markers(),
+            commands: Vec::new(),
+        }
+    }
+}
+
+/// Configuration for knowledge snapshots in .wm/history/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    /// Snapshots kept per knowledge file (0 disables history)
+    #[serde(default = "default_history_retention")]
+    pub retention: usize,
+}
+
+fn default_history_retention() -> usize {
+    50
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            retention: default_history_retention(),
+        }
+    }
+}
+
+/// Timeouts and retries for LLM calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmConfig {
+    /// Seconds before a call is killed (0 waits forever)
+    #[serde(default = "default_llm_timeout_secs")]
+    pub timeout_secs: u32,
+
+    /// Extra attempts after a retryable failure (timeout, rate limit, overload, network)
+    #[serde(default = "default_llm_retries")]
+    pub retries: u32,
+
+    /// Delay before the first retry; doubles on each further retry (with jitter)
+    #[serde(default = "default_llm_backoff_secs")]
+    pub backoff_secs: u32,
+
+    /// Ask for JSON matching a schema (false = always use the marker format)
+    #[serde(default = "default_true")]
+    pub structured_output: bool,
+}
+
+fn default_llm_timeout_secs() -> u32 {
+    300
+}
+
+fn default_llm_retries() -> u32 {
+    3
+}
+
+fn default_llm_backoff_secs() -> u32 {
+    2
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_llm_timeout_secs(),
+            retries: default_llm_retries(),
+            backoff_secs: default_llm_backoff_secs(),
+            structured_output: true,
+        }
+    }
+}
+
+/// Audit trail of LLM calls in .wm/audit/ (opt-in)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Record every LLM call (command, model, sizes, prompt hash)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Also keep the full prompt and response, with secrets redacted
+    #[serde(default)]
+    pub payloads: bool,
+
+    /// Days to keep audit records (0 keeps them forever)
+    #[serde(default = "default_audit_retention_days")]
+    pub retention_days: u32,
+
+    /// Extra regexes whose matches are redacted from stored payloads
+    #[serde(default)]
+    pub redact: Vec<String>,
+}
+
+fn default_audit_retention_days() -> u32 {
+    30
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            payloads: false,
+            retention_days: default_audit_retention_days(),
+            redact: Vec::new(),
+        }
+    }
 }
 
 /// Configuration for named dive preps
@@ -192,6 +558,14 @@ pub struct OperationsConfig {
 
     #[serde(default = "default_true")]
     pub compile: bool,
+
+    /// When a timed `wm pause extract --for/--until` ends (None = until resumed)
+    #[serde(default)]
+    pub extract_paused_until: Option<DateTime<Utc>>,
+
+    /// When a timed `wm pause compile --for/--until` ends (None = until resumed)
+    #[serde(default)]
+    pub compile_paused_until: Option<DateTime<Utc>>,
 }
 
 fn default_true() -> bool {
@@ -203,6 +577,154 @@ impl Default for OperationsConfig {
         Self {
             extract: true,
             compile: true,
+            extract_paused_until: None,
+            compile_paused_until: None,
+        }
+    }
+}
+
+impl OperationsConfig {
+    /// Project-wide pause of an operation, if one is in effect at `now`
+    pub fn pause(&self, op: Operation, now: DateTime<Utc>) -> Option<Pause> {
+        let (enabled, until) = match op {
+            Operation::Extract => (self.extract, self.extract_paused_until),
+            Operation::Compile => (self.compile, self.compile_paused_until),
+        };
+        let pause = Pause { until };
+        (!enabled && pause.is_active(now)).then_some(pause)
+    }
+
+    /// Pause (with an optional end) or resume an operation
+    pub fn set_paused(&mut self, op: Operation, pause: Option<Pause>) {
+        let (enabled, until) = match op {
+            Operation::Extract => (&mut self.extract, &mut self.extract_paused_until),
+            Operation::Compile => (&mut self.compile, &mut self.compile_paused_until),
+        };
+        *enabled = pause.is_none();
+        *until = pause.and_then(|p| p.until);
+    }
+}
+
+/// Operations that can be paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Extract,
+    Compile,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 2] = [Operation::Extract, Operation::Compile];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Extract => "extract",
+            Operation::Compile => "compile",
+        }
+    }
+
+    /// Parse an operation name (None = unknown)
+    pub fn parse(name: &str) -> Option<Operation> {
+        Operation::ALL.into_iter().find(|op| op.name() == name)
+    }
+}
+
+/// A pause of one operation; ends at `until`, or when resumed if None
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pause {
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Pause {
+    /// Whether the pause still holds at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+/// Pauses scoped to a single session
+/// Stored in .wm/sessions/<session-id>/paused.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionPauses {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<Pause>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compile: Option<Pause>,
+}
+
+impl SessionPauses {
+    /// Session pause of an operation, if one is in effect at `now`
+    pub fn pause(&self, op: Operation, now: DateTime<Utc>) -> Option<Pause> {
+        let pause = match op {
+            Operation::Extract => self.extract,
+            Operation::Compile => self.compile,
+        };
+        pause.filter(|p| p.is_active(now))
+    }
+
+    pub fn set_paused(&mut self, op: Operation, pause: Option<Pause>) {
+        match op {
+            Operation::Extract => self.extract = pause,
+            Operation::Compile => self.compile = pause,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.extract.is_none() && self.compile.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("src/generated/**", "src/generated/api/v1.rs"));
+        assert!(glob_match("src/generated/**", "src/generated"));
+        assert!(glob_match("*.lock", "Cargo.lock"));
+        assert!(!glob_match("*.lock", "sub/Cargo.lock"));
+        assert!(glob_match("**/*.lock", "sub/Cargo.lock"));
+        assert!(glob_match("**/*.lock", "Cargo.lock"));
+        assert!(glob_match(
+            "migrations/????_*.sql",
+            "migrations/0001_init.sql"
+        ));
+        assert!(!glob_match("src/*.rs", "src/a/b.rs"));
+    }
+
+    #[test]
+    fn test_glob_match_many_double_stars_is_fast() {
+        let path = format!("{}/z.rs", vec!["a"; 40].join("/"));
+        assert!(!glob_match("**/a/**/b/**/c/**/d", &path));
+        assert!(glob_match("**/a/**/a/**/z.rs", &path));
+    }
+
+    #[test]
+    fn test_content_hash_stable() {
+        assert_eq!(content_hash("abc"), content_hash("  abc\n"));
+        assert_ne!(content_hash("abc"), content_hash("abd"));
+        assert_eq!(content_hash("").len(), 16);
+    }
+
+    #[test]
+    fn test_timed_pause_expires() {
+        let now = Utc::now();
+        let mut ops = OperationsConfig::default();
+        ops.set_paused(
+            Operation::Extract,
+            Some(Pause {
+                until: Some(now + chrono::Duration::hours(1)),
+            }),
+        );
+        assert!(ops.pause(Operation::Extract, now).is_some());
+        assert!(ops.pause(Operation::Compile, now).is_none());
+        assert!(
+            ops.pause(Operation::Extract, now + chrono::Duration::hours(2))
+                .is_none()
+        );
+
+        ops.set_paused(Operation::Extract, None);
+        assert!(ops.extract && ops.extract_paused_until.is_none());
+    }
 }
@@ -0,0 +1,365 @@
+//! Token and cost accounting for LLM calls
+//!
+//! Every LLM call appends one line to .wm/usage.jsonl with the tokens and cost
+//! the Claude CLI reported, tagged with the command and (when known) the
+//! session it was made for. `wm usage` aggregates the log by day, command or
+//! session, and `wm distill --max-cost/--max-tokens` checks `Budget` against
+//! what the calls made under it have spent.
+//!
+//! AIDEV-NOTE: Unlike the audit trail this is always on - it holds no content,
+//! only counts. Writes are best-effort and never fail the LLM call.
+
+use crate::lock;
+use crate::state;
+use crate::workspace::Workspace;
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex};
+
+/// Usage log within .wm/ (one JSON object per line)
+pub const USAGE_FILE: &str = "usage.jsonl";
+
+/// Tokens and cost of one or more calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Totals {
+    #[serde(default)]
+    pub calls: u64,
+
+    #[serde(default)]
+    pub input_tokens: u64,
+
+    #[serde(default)]
+    pub output_tokens: u64,
+
+    /// USD, as reported by the backend (0 when it reports none)
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+impl Totals {
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl AddAssign for Totals {
+    fn add_assign(&mut self, other: Self) {
+        self.calls += other.calls;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// One LLM call in usage.jsonl
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+
+    /// wm command that made the call (extract, distill, compress)
+    pub command: String,
+
+    /// Session the call was made for, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Append a call to usage.jsonl
+pub fn record(
+    ws: &Workspace,
+    command: &str,
+    session: Option<&str>,
+    model: Option<&str>,
+    totals: Totals,
+) {
+    if !state::is_initialized(ws) {
+        return;
+    }
+
+    let record = UsageRecord {
+        timestamp: Utc::now(),
+        command: command.to_string(),
+        session: session.map(String::from),
+        model: model.map(String::from),
+        totals,
+    };
+    if let Err(e) = append(ws, &record) {
+        state::log(ws, "usage", &format!("Failed to record usage: {}", e));
+    }
+}
+
+fn append(ws: &Workspace, record: &UsageRecord) -> std::io::Result<()> {
+    let path = state::wm_path(ws, USAGE_FILE);
+    let _lock = lock::acquire(&path)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+/// All records, oldest first
+pub fn list(ws: &Workspace) -> Vec<UsageRecord> {
+    let content = fs::read_to_string(state::wm_path(ws, USAGE_FILE)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Spending limits for a run (None = unlimited)
+/// AIDEV-NOTE: Counts only the LLM calls it is passed to (see llm::call_claude),
+/// kept in memory rather than re-read from usage.jsonl, so a budget still holds
+/// if the log can't be written and isn't charged for other runs - in this
+/// process or another wm process - spending at the same time. Clones share
+/// the counter.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub max_cost_usd: Option<f64>,
+    pub max_tokens: Option<u64>,
+
+    /// Spent by calls made under this budget
+    spent: Arc<Mutex<Totals>>,
+}
+
+impl Budget {
+    /// Start a budget with nothing spent
+    pub fn new(max_cost_usd: Option<f64>, max_tokens: Option<u64>) -> Self {
+        Budget {
+            max_cost_usd,
+            max_tokens,
+            spent: Arc::default(),
+        }
+    }
+
+    /// Count a call made under this budget
+    pub fn add(&self, totals: Totals) {
+        if let Ok(mut spent) = self.spent.lock() {
+            *spent += totals;
+        }
+    }
+
+    /// Spent by calls made under this budget
+    pub fn spent(&self) -> Totals {
+        self.spent.lock().map(|t| *t).unwrap_or_default()
+    }
+
+    /// Which limit has been reached, if any
+    pub fn exhausted(&self) -> Option<String> {
+        exceeded(self.max_cost_usd, self.max_tokens, &self.spent())
+    }
+}
+
+fn exceeded(max_cost_usd: Option<f64>, max_tokens: Option<u64>, spent: &Totals) -> Option<String> {
+    if let Some(max) = max_cost_usd
+        && spent.cost_usd >= max
+    {
+        return Some(format!("spent ${:.4} of ${:.4}", spent.cost_usd, max));
+    }
+    if let Some(max) = max_tokens
+        && spent.tokens() >= max
+    {
+        return Some(format!("used {} of {} tokens", spent.tokens(), max));
+    }
+    None
+}
+
+/// How `wm usage` groups records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Day,
+    Command,
+    Session,
+}
+
+impl GroupBy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "day" => Some(GroupBy::Day),
+            "command" => Some(GroupBy::Command),
+            "session" => Some(GroupBy::Session),
+            _ => None,
+        }
+    }
+
+    fn key(self, record: &UsageRecord) -> String {
+        match self {
+            GroupBy::Day => record
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d")
+                .to_string(),
+            GroupBy::Command => record.command.clone(),
+            GroupBy::Session => record.session.clone().unwrap_or_else(|| "-".to_string()),
+        }
+    }
+}
+
+/// Sum records per group key (sorted by key)
+pub fn aggregate(records: &[UsageRecord], by: GroupBy) -> BTreeMap<String, Totals> {
+    let mut groups: BTreeMap<String, Totals> = BTreeMap::new();
+    for record in records {
+        *groups.entry(by.key(record)).or_default() += record.totals;
+    }
+    groups
+}
+
+// ============================================================================
+// CLI
+// ============================================================================
+
+/// Run wm usage - totals by day and command (or one grouping with `by`)
+pub fn run(ws: &Workspace, by: Option<&str>, days: Option<u32>) -> Result<(), String> {
+    if !state::is_initialized(ws) {
+        return Err("Not initialized. Run 'wm init' first.".to_string());
+    }
+    let by = by
+        .map(|name| {
+            GroupBy::parse(name)
+                .ok_or_else(|| format!("Unknown grouping: {}. Use day, command or session.", name))
+        })
+        .transpose()?;
+
+    let since = days.map(|d| Utc::now() - Duration::days(i64::from(d)));
+    let records: Vec<UsageRecord> = list(ws)
+        .into_iter()
+        .filter(|r| since.is_none_or(|since| r.timestamp >= since))
+        .collect();
+    if records.is_empty() {
+        println!("No LLM calls recorded yet.");
+        return Ok(());
+    }
+
+    let groupings = match by {
+        Some(by) => vec![by],
+        None => vec![GroupBy::Day, GroupBy::Command],
+    };
+    for (i, by) in groupings.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let header = match by {
+            GroupBy::Day => "Day",
+            GroupBy::Command => "Command",
+            GroupBy::Session => "Session",
+        };
+        print_row(header, None);
+        for (key, totals) in aggregate(&records, by) {
+            print_row(&key, Some(&totals));
+        }
+    }
+
+    let mut total = Totals::default();
+    for record in &records {
+        total += record.totals;
+    }
+    println!();
+    print_row("Total", Some(&total));
+    Ok(())
+}
+
+fn print_row(label: &str, totals: Option<&Totals>) {
+    match totals {
+        None => println!(
+            "{:<38} {:>6} {:>12} {:>12} {:>10}",
+            label, "Calls", "Input tok", "Output tok", "Cost"
+        ),
+        Some(t) => println!(
+            "{:<38} {:>6} {:>12} {:>12} {:>10}",
+            label,
+            t.calls,
+            t.input_tokens,
+            t.output_tokens,
+            format!("${:.4}", t.cost_usd)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(command: &str, session: Option<&str>, cost_usd: f64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            session: session.map(String::from),
+            model: None,
+            totals: Totals {
+                calls: 1,
+                input_tokens: 100,
+                output_tokens: 10,
+                cost_usd,
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate_by_command_and_session() {
+        let records = vec![
+            record("distill", Some("a"), 0.5),
+            record("distill", Some("b"), 0.25),
+            record("compress", None, 0.1),
+        ];
+
+        let by_command = aggregate(&records, GroupBy::Command);
+        assert_eq!(by_command["distill"].calls, 2);
+        assert_eq!(by_command["distill"].input_tokens, 200);
+        assert_eq!(by_command["distill"].cost_usd, 0.75);
+
+        let by_session = aggregate(&records, GroupBy::Session);
+        assert_eq!(by_session.keys().collect::<Vec<_>>(), vec!["-", "a", "b"]);
+    }
+
+    #[test]
+    fn test_usage_record_flattens_totals() {
+        let json = serde_json::to_string(&record("extract", None, 0.01)).unwrap();
+        assert!(json.contains("\"input_tokens\":100"));
+        assert!(!json.contains("session"));
+        let parsed: UsageRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.totals.output_tokens, 10);
+    }
+
+    #[test]
+    fn test_budget_limits() {
+        let spent = Totals {
+            calls: 3,
+            input_tokens: 900,
+            output_tokens: 100,
+            cost_usd: 0.2,
+        };
+        assert!(exceeded(Some(0.2), None, &spent).is_some());
+        assert!(exceeded(Some(0.5), None, &spent).is_none());
+        assert!(exceeded(None, Some(1000), &spent).is_some());
+        assert!(exceeded(None, Some(5000), &spent).is_none());
+        assert!(exceeded(None, None, &spent).is_none());
+    }
+
+    #[test]
+    fn test_budget_counts_only_its_own_calls() {
+        let call = Totals {
+            calls: 1,
+            input_tokens: 600,
+            output_tokens: 400,
+            cost_usd: 0.1,
+        };
+        let first = Budget::new(None, Some(1500));
+        let second = Budget::new(None, Some(1500));
+
+        first.add(call);
+        assert_eq!(first.spent().tokens(), 1000);
+        assert_eq!(second.spent(), Totals::default());
+
+        first.clone().add(call);
+        assert_eq!(first.spent().calls, 2);
+        assert!(first.exhausted().is_some());
+        assert!(second.exhausted().is_none());
+    }
+}
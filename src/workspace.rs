@@ -0,0 +1,183 @@
+//! Library entry point - a typed handle to a project's .wm/ store
+//!
+//! Lets other tools (superego, internal tooling) embed wm instead of shelling
+//! out and scraping stdout.
+//!
+//! AIDEV-NOTE: A Workspace is also the context every internal module takes
+//! (`ws: &Workspace`) to resolve .wm/ paths - there is no process-wide root, so
+//! several workspaces can be used side by side, from any thread.
+
+use crate::compile::{self, WorkingSet};
+use crate::config;
+use crate::distill::{self, DistillOptions, DistillReport, Progress};
+use crate::dive::{self, DivePrep};
+use crate::error::{Error, Result};
+use crate::init;
+use crate::migrate;
+use crate::state;
+use crate::types::Config;
+use std::path::{Path, PathBuf};
+
+/// A project containing (or about to contain) a .wm/ store
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    /// Open the workspace rooted at `root` (the directory containing .wm/)
+    pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        let workspace = Self::at(root);
+        if !state::is_initialized(&workspace) {
+            return Err(Error::NotInitialized(workspace.wm_dir()));
+        }
+        migrate::auto_migrate(&workspace);
+        Ok(workspace)
+    }
+
+    /// Create a new .wm/ store at `root` and open it
+    pub fn init(root: impl AsRef<Path>) -> Result<Self> {
+        let workspace = Self::at(root);
+        init::create(&workspace)?;
+        Ok(workspace)
+    }
+
+    fn at(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        Self::new(root.canonicalize().unwrap_or_else(|_| root.to_path_buf()))
+    }
+
+    /// Workspace at `root` as given, initialized or not (for the CLI and init)
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Project root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the .wm/ directory
+    pub fn wm_dir(&self) -> PathBuf {
+        self.root.join(".wm")
+    }
+
+    /// Compile the working set (dive context + guardrails + metis)
+    pub fn compile(&self) -> WorkingSet {
+        compile::compile_working_set(self)
+    }
+
+    /// Accumulated freeform knowledge from state.md (empty if none)
+    pub fn state(&self) -> String {
+        std::fs::read_to_string(state::wm_path(self, "state.md")).unwrap_or_default()
+    }
+
+    /// Run batch distillation over this project's sessions
+    ///
+    /// `progress` receives the status lines and warnings `wm distill` prints;
+    /// the returned report carries the counts and the categorized items.
+    pub fn distill(
+        &self,
+        options: DistillOptions,
+        progress: impl Fn(Progress<'_>),
+    ) -> Result<DistillReport> {
+        distill::run(self, options, &progress)
+    }
+
+    /// List named dive preps, marking the current one
+    pub fn dives(&self) -> Result<Vec<DivePrep>> {
+        dive::list_preps(self)
+    }
+
+    /// Name of the current dive prep, if any
+    pub fn current_dive(&self) -> Option<String> {
+        state::current_dive(self)
+    }
+
+    /// Create a named dive prep (skeleton content if `content` is None)
+    pub fn create_dive(&self, name: &str, content: Option<&str>) -> Result<()> {
+        dive::create(self, name, content)
+    }
+
+    /// Make a named dive prep current
+    pub fn switch_dive(&self, name: &str) -> Result<()> {
+        dive::switch_to(self, name)
+    }
+
+    /// Delete a named dive prep; returns true if it was current
+    pub fn delete_dive(&self, name: &str) -> Result<bool> {
+        dive::remove(self, name)
+    }
+
+    /// Read the effective config (defaults, user-global, project, WM_* env)
+    pub fn config(&self) -> Config {
+        state::read_config(self)
+    }
+
+    /// Change the project config, writing only the keys `change` modifies
+    ///
+    /// `change` sees the project layer alone (defaults underneath), so global
+    /// and WM_* settings are never baked into .wm/config.toml. Returns the
+    /// updated project layer.
+    pub fn update_config(&self, change: impl FnOnce(&mut Config)) -> Result<Config> {
+        config::update_project(self, change).map_err(Error::Config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            Workspace::open(dir.path()),
+            Err(Error::NotInitialized(_))
+        ));
+
+        let ws = Workspace::init(dir.path()).unwrap();
+        assert!(ws.wm_dir().is_dir());
+        assert!(matches!(
+            Workspace::init(dir.path()),
+            Err(Error::AlreadyInitialized(_))
+        ));
+
+        ws.create_dive("auth-rework", None).unwrap();
+        ws.switch_dive("auth-rework").unwrap();
+        assert_eq!(
+            ws.dives().unwrap(),
+            vec![DivePrep {
+                name: "auth-rework".to_string(),
+                is_current: true,
+            }]
+        );
+        assert!(ws.compile().content.contains("# Dive: auth-rework"));
+        assert!(matches!(
+            ws.create_dive("Bad Name", None),
+            Err(Error::InvalidName { .. })
+        ));
+
+        assert!(ws.delete_dive("auth-rework").unwrap());
+        assert_eq!(ws.current_dive(), None);
+    }
+
+    #[test]
+    fn test_workspaces_are_independent() {
+        let (a_dir, b_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let a = Workspace::init(a_dir.path()).unwrap();
+        let b = Workspace::init(b_dir.path()).unwrap();
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| a.create_dive("only-in-a", None).unwrap());
+            scope.spawn(|| b.create_dive("only-in-b", None).unwrap());
+        });
+        a.switch_dive("only-in-a").unwrap();
+
+        assert_eq!(a.current_dive().as_deref(), Some("only-in-a"));
+        assert_eq!(b.current_dive(), None);
+        let names: Vec<String> = b.dives().unwrap().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["only-in-b".to_string()]);
+    }
+}